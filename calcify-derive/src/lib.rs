@@ -0,0 +1,300 @@
+#![crate_name = "calcify_derive"]
+//! # Calcify Derive
+//!
+//! `#[derive(Serializable, Deserializable)]` for structs and enums, following the same conventions as the built in calcify types.
+//! Use it through the `derive` feature of calcify, which re-exports both macros next to the traits.
+//!
+//! ## Format
+//!
+//! | Type | json | msg |
+//! | ----------- | ----------- | ----------- |
+//! | Struct | `{"field":value,...}` | `[value,...]` in field order |
+//! | Tuple struct | `[value,...]` | `[value,...]` |
+//! | Unit struct | `[]` | `[]` |
+//! | Unit variant | `"Variant"` | `"Variant"` |
+//! | Struct variant | `{"Variant":{"field":value,...}}` | `{"Variant":[value,...]}` |
+//! | Tuple variant | `{"Variant":[value,...]}` | `{"Variant":[value,...]}` |
+//!
+//! Every field must itself be Serializable or Deserializable, and generic type parameters get that bound added.
+//!
+//! # Example
+//! ```
+//! use calcify::{Serializable, Deserializable, ThreeVec};
+//!
+//! #[derive(Debug, PartialEq, Serializable, Deserializable)]
+//! struct Particle {
+//!     m: f64,
+//!     r: ThreeVec,
+//! }
+//!
+//! let p = Particle { m: 1.0, r: ThreeVec::new(1.0,2.0,3.0) };
+//! assert_eq!(p.to_json(),"{\"m\":1,\"r\":{\"x0\":1,\"x1\":2,\"x2\":3}}");
+//! assert_eq!(Particle::from_json(&p.to_json()).unwrap(),p);
+//! let msg = p.to_msg().unwrap();
+//! assert_eq!(Particle::from_msg(&msg).unwrap().0,p);
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, Span};
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Generics, Ident, Index};
+
+/// Derives `calcify::Serializable`, see the crate docs for the format.
+#[proc_macro_derive(Serializable)]
+pub fn derive_serializable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match serializable(&input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Derives `calcify::Deserializable`, see the crate docs for the format.
+#[proc_macro_derive(Deserializable)]
+pub fn derive_deserializable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match deserializable(&input) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Adds `bound` to every type parameter
+fn add_bounds(generics: &Generics, bound: syn::TypeParamBound) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(bound.clone());
+    }
+    generics
+}
+
+/// Names the fields for use as bindings, `f0`, `f1`... for tuple fields
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    fields.iter().enumerate().map(|(i,f)| match &f.ident {
+        Some(id) => id.clone(),
+        None => format_ident!("f{}",i),
+    }).collect()
+}
+
+/// Names the fields as they appear in json
+fn keys(fields: &Fields) -> Vec<String> {
+    fields.iter().map(|f| f.ident.as_ref().map(|id| id.to_string()).unwrap_or_default()).collect()
+}
+
+/// Returns expressions for (json, msg) of the bound fields, the msg appended to `buf`
+fn write_fields(fields: &Fields, binds: &[Ident]) -> (TokenStream2, TokenStream2) {
+    let keys = keys(fields);
+    let json = match fields {
+        Fields::Named(_) => quote! {
+            ::calcify::__private::json_object(&[#((#keys, ::calcify::Serializable::to_json(#binds))),*])
+        },
+        _ => quote! {
+            ::calcify::__private::json_array(&[#(::calcify::Serializable::to_json(#binds)),*])
+        },
+    };
+    let msg = quote! {
+        #(buf.extend(::calcify::Serializable::to_msg(#binds)?);)*
+    };
+    (json, msg)
+}
+
+fn serializable(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::calcify::Serializable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (json, msg) = match &input.data {
+        Data::Struct(data) => {
+            let binds = bindings(&data.fields);
+            let members: Vec<TokenStream2> = data.fields.iter().enumerate().map(|(i,f)| match &f.ident {
+                Some(id) => quote!(#id),
+                None => { let i = Index::from(i); quote!(#i) },
+            }).collect();
+            let (json, msg) = write_fields(&data.fields, &binds);
+            let len = binds.len() as u32;
+            (
+                quote! {
+                    #(let #binds = &self.#members;)*
+                    #json
+                },
+                quote! {
+                    #(let #binds = &self.#members;)*
+                    let mut buf = ::calcify::__private::msg_array(#len)?;
+                    #msg
+                    Ok(buf)
+                },
+            )
+        },
+        Data::Enum(data) => {
+            let mut json_arms = Vec::new();
+            let mut msg_arms = Vec::new();
+            for variant in data.variants.iter() {
+                let ident = &variant.ident;
+                let vname = ident.to_string();
+                let binds = bindings(&variant.fields);
+                let pat = match &variant.fields {
+                    Fields::Named(_) => quote!(#name::#ident { #(#binds),* }),
+                    Fields::Unnamed(_) => quote!(#name::#ident ( #(#binds),* )),
+                    Fields::Unit => quote!(#name::#ident),
+                };
+                if let Fields::Unit = variant.fields {
+                    json_arms.push(quote!(#pat => ::calcify::__private::json_variant(#vname, None)));
+                    msg_arms.push(quote!(#pat => ::calcify::__private::msg_variant(#vname, None)));
+                } else {
+                    let (json, msg) = write_fields(&variant.fields, &binds);
+                    let len = binds.len() as u32;
+                    json_arms.push(quote!(#pat => ::calcify::__private::json_variant(#vname, Some(#json))));
+                    msg_arms.push(quote!(#pat => {
+                        let mut buf = ::calcify::__private::msg_variant(#vname, Some(#len))?;
+                        #msg
+                        Ok(buf)
+                    }));
+                }
+            }
+            (
+                quote!(match self { #(#json_arms,)* }),
+                quote!(match self { #(#msg_arms,)* }),
+            )
+        },
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "Serializable cannot be derived for unions")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::calcify::Serializable for #name #ty_generics #where_clause {
+            fn to_json(&self) -> ::std::string::String {
+                #json
+            }
+            fn to_msg(&self) -> ::std::result::Result<::std::vec::Vec<u8>, ::calcify::__private::ValueWriteError> {
+                #msg
+            }
+        }
+    })
+}
+
+/// Returns (from_json_value, from_msg) statements that bind every field, from `value` and `bytes` respectively
+///
+/// Errors reading a field are wrapped in a ParseError naming it, `path.field`.
+fn read_fields(path: &str, fields: &Fields, binds: &[Ident]) -> (TokenStream2, TokenStream2) {
+    let keys = keys(fields);
+    let idx: Vec<usize> = (0..binds.len()).collect();
+    let len = binds.len();
+    let labels: Vec<String> = fields.iter().enumerate().map(|(i,f)| match &f.ident {
+        Some(id) => format!("{}.{}",path,id),
+        None => format!("{}.{}",path,i),
+    }).collect();
+    let json = match fields {
+        Fields::Named(_) => quote! {
+            #(let #binds = ::calcify::Deserializable::from_json_value(value.get(#keys)?)
+                .map_err(|e| ::calcify::__private::field_error(#labels, e))?;)*
+        },
+        _ => quote! {
+            let vals = ::calcify::__private::json_fields(value, #len)?;
+            #(let #binds = ::calcify::Deserializable::from_json_value(&vals[#idx])
+                .map_err(|e| ::calcify::__private::field_error(#labels, e))?;)*
+        },
+    };
+    let msg = quote! {
+        #(let (#binds, rest) = ::calcify::Deserializable::from_msg(bytes)
+            .map_err(|e| ::calcify::__private::field_error(#labels, e))?; bytes = rest;)*
+    };
+    (json, msg)
+}
+
+/// Builds `path` from the bound fields
+fn construct(path: TokenStream2, fields: &Fields, binds: &[Ident]) -> TokenStream2 {
+    match fields {
+        Fields::Named(_) => quote!(#path { #(#binds),* }),
+        Fields::Unnamed(_) => quote!(#path ( #(#binds),* )),
+        Fields::Unit => quote!(#path),
+    }
+}
+
+fn deserializable(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = add_bounds(&input.generics, parse_quote!(::calcify::Deserializable));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let (json, msg) = match &input.data {
+        Data::Struct(data) => {
+            let binds = bindings(&data.fields);
+            let (json, msg) = read_fields(&name.to_string(), &data.fields, &binds);
+            let build = construct(quote!(#name), &data.fields, &binds);
+            let len = binds.len() as u32;
+            let json = match data.fields {
+                Fields::Unit => quote!(::calcify::__private::json_fields(value, 0)?;),
+                _ => json,
+            };
+            (
+                quote! {
+                    #json
+                    Ok(#build)
+                },
+                quote! {
+                    let mut bytes = bytes;
+                    ::calcify::__private::read_fields(&mut bytes, #len)?;
+                    #msg
+                    Ok((#build, bytes))
+                },
+            )
+        },
+        Data::Enum(data) => {
+            let mut json_arms = Vec::new();
+            let mut msg_arms = Vec::new();
+            for variant in data.variants.iter() {
+                let ident = &variant.ident;
+                let vname = ident.to_string();
+                let binds = bindings(&variant.fields);
+                let build = construct(quote!(#name::#ident), &variant.fields, &binds);
+                if let Fields::Unit = variant.fields {
+                    json_arms.push(quote!((#vname, None) => Ok(#build)));
+                    msg_arms.push(quote!((#vname, None) => Ok((#build, bytes))));
+                } else {
+                    let (json, msg) = read_fields(&format!("{}::{}",name,ident), &variant.fields, &binds);
+                    let len = binds.len() as u32;
+                    json_arms.push(quote!((#vname, Some(value)) => {
+                        #json
+                        Ok(#build)
+                    }));
+                    msg_arms.push(quote!((#vname, Some(#len)) => {
+                        #msg
+                        Ok((#build, bytes))
+                    }));
+                }
+            }
+            (
+                quote! {
+                    match ::calcify::__private::json_variant_parts(value)? {
+                        #(#json_arms,)*
+                        (variant, _) => Err(::std::boxed::Box::new(::calcify::errors::CalcifyError::key(variant))),
+                    }
+                },
+                quote! {
+                    let mut bytes = bytes;
+                    let (variant, len) = ::calcify::__private::read_variant(&mut bytes)?;
+                    match (variant.as_str(), len) {
+                        #(#msg_arms,)*
+                        _ => Err(::std::boxed::Box::new(::calcify::errors::CalcifyError::key(&variant))),
+                    }
+                },
+            )
+        },
+        Data::Union(_) => return Err(syn::Error::new(Span::call_site(), "Deserializable cannot be derived for unions")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::calcify::Deserializable for #name #ty_generics #where_clause {
+            fn from_json(string: &str) -> ::std::result::Result<Self, ::std::boxed::Box<dyn ::std::error::Error>> {
+                Self::from_json_value(&::calcify::JsonValue::parse(string)?)
+            }
+            #[allow(unused_variables)]
+            fn from_json_value(value: &::calcify::JsonValue) -> ::std::result::Result<Self, ::std::boxed::Box<dyn ::std::error::Error>> {
+                #json
+            }
+            fn from_msg(bytes: &[u8]) -> ::std::result::Result<(Self, &[u8]), ::std::boxed::Box<dyn ::std::error::Error>> {
+                #msg
+            }
+        }
+    })
+}
@@ -0,0 +1,120 @@
+use std::error;
+
+use calcify::{Serializable, Deserializable, TreeSubtype, ThreeVec, FourVec, Collection, Tree};
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Particle {
+    pid: u64,
+    m: f64,
+    r: ThreeVec,
+    p: FourVec,
+}
+
+impl TreeSubtype for Particle {
+    const NAME: &'static str = "Particle";
+}
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Pair(f64, ThreeVec);
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Marker;
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+enum Event {
+    Start,
+    Hit { pid: u64, at: ThreeVec },
+    Decay(u64, u64),
+    Stop,
+}
+
+fn particle() -> Particle {
+    Particle {
+        pid: 7,
+        m: 0.511,
+        r: ThreeVec::new(1.0,2.0,3.0),
+        p: FourVec::new(1.0,0.0,0.5,0.0),
+    }
+}
+
+#[test]
+fn test_struct() -> Result<(), Box<dyn error::Error>> {
+    let p = particle();
+    let json = p.to_json();
+    assert!(json.starts_with("{\"pid\":7,\"m\":0.511,\"r\":{"));
+    assert_eq!(Particle::from_json(&json)?,p);
+    assert_eq!(Particle::from_json(" { \"m\":0.511, \"pid\":7, \"p\":{\"m0\":1.0,\"m1\":0.0,\"m2\":0.5,\"m3\":0.0}, \"r\":{\"x0\":1.0,\"x1\":2.0,\"x2\":3.0} } ")?,p);
+
+    let msg = p.to_msg()?;
+    let mut expected = vec![0x94];
+    expected.extend(7u64.to_msg()?);
+    expected.extend(0.511f64.to_msg()?);
+    expected.extend(p.r.to_msg()?);
+    expected.extend(p.p.to_msg()?);
+    assert_eq!(msg,expected);
+    let (back, rest) = Particle::from_msg(&msg)?;
+    assert_eq!(back,p);
+    assert!(rest.is_empty());
+
+    assert!(Particle::from_json("{\"pid\":7}").is_err());
+    assert!(Particle::from_msg(&msg[..msg.len()-1]).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tuple_unit_generic() -> Result<(), Box<dyn error::Error>> {
+    let pair = Pair(2.0,ThreeVec::new(0.0,1.0,0.0));
+    assert_eq!(pair.to_json(),format!("[2,{}]",pair.1.to_json()));
+    assert_eq!(Pair::from_json(&pair.to_json())?,pair);
+    assert_eq!(Pair::from_msg(&pair.to_msg()?)?.0,pair);
+    assert!(Pair::from_json("[2]").is_err());
+
+    assert_eq!(Marker.to_json(),"[]");
+    assert_eq!(Marker::from_json("[]")?,Marker);
+    assert_eq!(Marker::from_msg(&Marker.to_msg()?)?.0,Marker);
+
+    let w = Wrapper { inner: particle() };
+    assert_eq!(Wrapper::<Particle>::from_json(&w.to_json())?,w);
+    assert_eq!(Wrapper::<Particle>::from_msg(&w.to_msg()?)?.0,w);
+    Ok(())
+}
+
+#[test]
+fn test_enum() -> Result<(), Box<dyn error::Error>> {
+    let events = [
+        Event::Start,
+        Event::Hit { pid: 3, at: ThreeVec::new(1.0,1.0,0.0) },
+        Event::Decay(3,4),
+        Event::Stop,
+    ];
+    assert_eq!(events[0].to_json(),"\"Start\"");
+    assert_eq!(events[2].to_json(),"{\"Decay\":[3,4]}");
+    assert!(events[1].to_json().starts_with("{\"Hit\":{\"pid\":3,\"at\":{"));
+    for ev in events.iter() {
+        assert_eq!(&Event::from_json(&ev.to_json())?,ev);
+        assert_eq!(&Event::from_msg(&ev.to_msg()?)?.0,ev);
+    }
+    assert!(Event::from_json("\"Pause\"").is_err());
+    assert!(Event::from_json("{\"Start\":[]}").is_err());
+    assert!(Event::from_json("{\"Decay\":[3]}").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_in_tree() -> Result<(), Box<dyn error::Error>> {
+    let col: Collection<Particle> = Collection::from(vec![particle(),particle()]);
+    let mut tree = Tree::new("Derived");
+    tree.add_branch("particles", col)?;
+    let json = tree.to_json();
+    let mut back = Tree::from_json(&json)?;
+    assert_eq!(back.read_branch::<Particle>("particles")?,Collection::from(vec![particle(),particle()]));
+    let msg = tree.to_msg()?;
+    let mut back = Tree::from_msg(&msg)?.0;
+    assert_eq!(back.read_branch::<Particle>("particles")?,Collection::from(vec![particle(),particle()]));
+    Ok(())
+}
@@ -59,9 +59,9 @@ fn main() -> Result<(),Box<dyn error::Error>> {
 
     ftree.add_feed("init_state", init_state)?;
 
-    ttree.add_branch("init_hist", init_hist, "Bin")?;
-    ttree.add_branch("init_spread", init_spread, "Point")?;
-    ttree.add_branch("init_heat", init_heat, "PointBin")?;
+    ttree.add_branch("init_hist", init_hist)?;
+    ttree.add_branch("init_spread", init_spread)?;
+    ttree.add_branch("init_heat", init_heat)?;
 
     universe.run(*RUN_T);
 
@@ -69,7 +69,7 @@ fn main() -> Result<(),Box<dyn error::Error>> {
     let mid1_hist: Collection<Bin> = mid1_state.map(|x| {x.r().r()}).hist(50);
 
     ftree.add_feed("mid1_state", mid1_state)?;
-    ttree.add_branch("mid1_hist", mid1_hist, "Bin")?;
+    ttree.add_branch("mid1_hist", mid1_hist)?;
 
     universe.run(*RUN_T);
 
@@ -77,7 +77,7 @@ fn main() -> Result<(),Box<dyn error::Error>> {
     let mid2_hist: Collection<Bin> = mid2_state.map(|x| {x.r().r()}).hist(50);
 
     ftree.add_feed("mid2_state", mid2_state)?;
-    ttree.add_branch("mid2_hist", mid2_hist, "Bin")?;
+    ttree.add_branch("mid2_hist", mid2_hist)?;
 
     universe.run(*RUN_T);
 
@@ -91,11 +91,13 @@ fn main() -> Result<(),Box<dyn error::Error>> {
                                                             .cut(|p| p.r() <= 1.0)
                                                             .hist(50,50);
 
+    ttree.add_branch("fin_state", fin_state.clone())?;
     ftree.add_feed("fin_state", fin_state)?;
-    ttree.add_branch("fin_hist", fin_hist, "Bin")?;
-    ttree.add_branch("fin_spread", fin_spread, "Point")?;
-    ttree.add_branch("fin_heat", fin_heat, "PointBin")?;
+    ttree.add_branch("fin_hist", fin_hist)?;
+    ttree.add_branch("fin_spread", fin_spread)?;
+    ttree.add_branch("fin_heat", fin_heat)?;
 
+    std::fs::create_dir_all("./scratch")?;
     ftree.write_msg("./scratch/universe_states.msg")?;
     ttree.write_msg("./scratch/universe_data.msg")?;
     Ok(())
@@ -7,10 +7,12 @@ use rayon::prelude::*;
 
 extern crate rmp;
 use rmp::encode::*;
+use rmp::decode::*;
 
 extern crate calcify;
 pub use calcify::ThreeVec;
-use calcify::{Serializable, Deserializable};
+use calcify::{Serializable, Deserializable, TreeSubtype, JsonValue};
+use calcify::errors::CalcifyError;
 
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -117,6 +119,37 @@ impl Serializable for Particle {
     }
 }
 
+impl Deserializable for Particle {
+    fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let pid = value.get("pid")?.as_u64()? as usize;
+        let mass = value.get("m")?.as_f64()?;
+        let charge = value.get("q")?.as_f64()?;
+        let position = ThreeVec::from_json_value(value.get("r")?)?;
+        let velocity = ThreeVec::from_json_value(value.get("v")?)?;
+        Ok(Particle{pid,mass,charge,position,velocity,t_force:ThreeVec::new(0.0,0.0,0.0)})
+    }
+
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        if let Ok(5) = read_array_len(&mut bytes) {
+            let (pid,rest) = u64::from_msg(bytes)?;
+            let (mass,rest) = f64::from_msg(rest)?;
+            let (charge,rest) = f64::from_msg(rest)?;
+            let (position,rest) = ThreeVec::from_msg(rest)?;
+            let (velocity,rest) = ThreeVec::from_msg(rest)?;
+            return Ok((Particle{pid:pid as usize,mass,charge,position,velocity,t_force:ThreeVec::new(0.0,0.0,0.0)},rest));
+        }
+        Err(Box::new(CalcifyError::parse("Particle")))
+    }
+}
+
+impl TreeSubtype for Particle {
+    const NAME: &'static str = "Particle";
+}
+
 impl FromStr for Particle {
     type Err = Box<dyn error::Error>;
 
@@ -0,0 +1,518 @@
+//! # calcify
+//!
+//! Command line tool for inspecting and converting Tree and FeedTree files, a native counterpart to ICalcify.
+//!
+//! Files are read as json if they start with `{`, otherwise as msg, in either the legacy layout or a container.
+//! Run `calcify help` for the commands.
+
+use std::any::Any;
+use std::env;
+use std::error;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+use calcify::{Tree, FeedTree, Branch, BranchInfo, MergePolicy, JsonValue, FieldValue, Serializable, Deserializable};
+use calcify::io::{ToFile, FromFile};
+use calcify::errors::CalcifyError;
+
+const USAGE: &str = "\
+Usage: calcify <command> [arguments]
+
+Commands:
+  info <file>                      Print the metadata, and the subtype, length and size of every branch or feed
+  dump <file> <branch> [--csv]     Print a branch or feed as json, or as csv with a column per field
+  convert <in> <out> [--container | --compressed]
+                                   Rewrite a file as json if <out> ends in .json, otherwise as msg
+  merge <out> <in>... [--policy keep-first | keep-last | error] [--container | --compressed]
+                                   Merge Trees or FeedTrees, metadata conflicts fail unless a policy is given
+  validate <file>                  Read a file completely, checking every checksum and branch
+  help                             Print this message
+";
+
+/// Evaluates `$body` with `$t` naming the built in type of `$subtype`, or `$other` if it isn't one
+macro_rules! with_subtype {
+    ($subtype:expr, $t:ident => $body:expr, _ => $other:expr) => {
+        calcify::builtin_subtypes!(subtype_arms ($subtype, $t => $body, _ => $other))
+    };
+}
+
+/// A match arm for every built in subtype, called through `calcify::builtin_subtypes!`
+macro_rules! subtype_arms {
+    (($subtype:expr, $t:ident => $body:expr, _ => $other:expr) $($ty:ty => $name:literal),*) => {
+        match $subtype {
+            $($name => { type $t = $ty; $body },)*
+            _ => $other,
+        }
+    };
+}
+
+/// How an output file is written
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Layout {
+    Json,
+    Msg,
+    Container,
+    Compressed,
+}
+
+impl Layout {
+    /// Json if `path` ends in .json, otherwise msg, or a container if asked for one
+    fn of(path: &str, flags: &[&str]) -> Layout {
+        if path.ends_with(".json") {
+            Layout::Json
+        } else if flags.contains(&"--compressed") {
+            Layout::Compressed
+        } else if flags.contains(&"--container") {
+            Layout::Container
+        } else {
+            Layout::Msg
+        }
+    }
+}
+
+fn write_as<F: ToFile + Serializable>(file: &F, path: &str, layout: Layout) -> Result<(), Box<dyn error::Error>> {
+    match layout {
+        Layout::Json => file.write_json(path),
+        Layout::Msg => file.write_msg(path),
+        Layout::Container => file.write_container(path),
+        Layout::Compressed => file.write_msg_compressed(path),
+    }
+}
+
+/// Reads json if the first byte that isn't whitespace is `{`, otherwise msg
+fn read_as<F: FromFile + Deserializable>(bytes: &[u8]) -> Result<F, Box<dyn error::Error>> {
+    if bytes.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+        F::read_json_from(bytes)
+    } else {
+        F::read_msg_from(bytes)
+    }
+}
+
+/// True for the errors of reading a FeedTree as a Tree, or a Tree as a FeedTree
+fn wrong_kind(err: &(dyn error::Error + 'static)) -> bool {
+    matches!(err.downcast_ref::<CalcifyError>(), Some(CalcifyError::SchemaError{..}))
+}
+
+/// A FeedTree of any element type
+///
+/// FeedTrees of built in subtypes are read as their type, so they convert between json and msg unchanged,
+/// and all others as JsonValues.
+trait Feeds {
+    fn subtype(&self) -> String;
+    fn fields(&self) -> Vec<(&str,&FieldValue)>;
+    /// Returns the name, and the subtype, length and msg size, of every feed
+    fn summary(&self) -> Result<Vec<(String,BranchInfo)>, Box<dyn error::Error>>;
+    fn feed_json(&self, key: &str) -> Option<String>;
+    fn write(&self, path: &str, layout: Layout) -> Result<(), Box<dyn error::Error>>;
+    fn merge(&mut self, other: Box<dyn Feeds>, policy: MergePolicy) -> Result<(), Box<dyn error::Error>>;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: 'static + Serializable + Deserializable> Feeds for FeedTree<T> {
+    fn subtype(&self) -> String {
+        self.get_field("SubType").map(|f| f.to_string()).unwrap_or_default()
+    }
+
+    fn fields(&self) -> Vec<(&str,&FieldValue)> {
+        FeedTree::fields(self).collect()
+    }
+
+    fn summary(&self) -> Result<Vec<(String,BranchInfo)>, Box<dyn error::Error>> {
+        let mut out = Vec::new();
+        for (name,feed) in self.feeds() {
+            out.push((String::from(name), BranchInfo{subtype: Feeds::subtype(self), len: feed.len(), size: feed.to_msg()?.len()}));
+        }
+        Ok(out)
+    }
+
+    fn feed_json(&self, key: &str) -> Option<String> {
+        self.feeds().find(|(name,_)| *name == key).map(|(_,feed)| feed.to_json())
+    }
+
+    fn write(&self, path: &str, layout: Layout) -> Result<(), Box<dyn error::Error>> {
+        write_as(self, path, layout)
+    }
+
+    fn merge(&mut self, other: Box<dyn Feeds>, policy: MergePolicy) -> Result<(), Box<dyn error::Error>> {
+        let subtype = other.subtype();
+        match other.into_any().downcast::<FeedTree<T>>() {
+            Ok(other) => Ok(FeedTree::merge(self, *other, policy)?),
+            Err(_) => Err(Box::new(CalcifyError::merge("FeedTree").caused_by(CalcifyError::SubtypeError{subtype, expected: Feeds::subtype(self)}))),
+        }
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A Tree or FeedTree read from a file
+enum File {
+    Tree(Tree),
+    Feeds(Box<dyn Feeds>),
+}
+
+impl File {
+    /// Reads `path` as a Tree, or failing that as a FeedTree
+    fn open(path: &str) -> Result<File, Box<dyn error::Error>> {
+        let bytes = fs::read(path).map_err(|e| format!("{}: {}",path,e))?;
+        let tree_err = match read_as::<Tree>(&bytes) {
+            Ok(tree) => return Ok(File::Tree(tree)),
+            Err(e) if wrong_kind(e.as_ref()) => e,
+            Err(e) => return Err(e),
+        };
+        let generic: FeedTree<JsonValue> = match read_as(&bytes) {
+            Ok(generic) => generic,
+            Err(e) if wrong_kind(e.as_ref()) => return Err(tree_err),
+            Err(e) => return Err(e),
+        };
+        let subtype = Feeds::subtype(&generic);
+        let feeds: Box<dyn Feeds> = with_subtype!(subtype.as_str(),
+            T => Box::new(read_as::<FeedTree<T>>(&bytes)?),
+            _ => Box::new(generic));
+        Ok(File::Feeds(feeds))
+    }
+
+    fn write(&self, path: &str, layout: Layout) -> Result<(), Box<dyn error::Error>> {
+        match self {
+            File::Tree(tree) => write_as(tree, path, layout),
+            File::Feeds(feeds) => feeds.write(path, layout),
+        }
+    }
+
+    fn merge(&mut self, other: File, policy: MergePolicy) -> Result<(), Box<dyn error::Error>> {
+        match (self, other) {
+            (File::Tree(tree), File::Tree(other)) => Ok(tree.merge(other, policy)?),
+            (File::Feeds(feeds), File::Feeds(other)) => feeds.merge(other, policy),
+            _ => Err(Box::new(CalcifyError::merge("a Tree with a FeedTree"))),
+        }
+    }
+}
+
+fn fields_of(file: &File) -> Vec<(&str,&FieldValue)> {
+    let mut fields = match file {
+        File::Tree(tree) => tree.fields().collect(),
+        File::Feeds(feeds) => feeds.fields(),
+    };
+    fields.sort_by(|a,b| a.0.cmp(b.0));
+    fields
+}
+
+fn info(path: &str, out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let file = File::open(path)?;
+    let mut rows: Vec<(String,BranchInfo,bool)> = Vec::new();
+    match &file {
+        File::Tree(tree) => {
+            writeln!(out, "Tree {}", path)?;
+            for (name,branch) in tree.branches() {
+                rows.push((String::from(name), branch.info()?, branch.is_raw()));
+            }
+        },
+        File::Feeds(feeds) => {
+            writeln!(out, "FeedTree {}", path)?;
+            for (name,info) in feeds.summary()? {
+                rows.push((name, info, false));
+            }
+        },
+    }
+    writeln!(out, "Metadata:")?;
+    for (key,value) in fields_of(&file) {
+        writeln!(out, "  {} = {}", key, value)?;
+    }
+    writeln!(out, "{}:", if let File::Tree(_) = file { "Branches" } else { "Feeds" })?;
+    rows.sort_by(|a,b| a.0.cmp(&b.0));
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0);
+    let sub_width = rows.iter().map(|r| r.1.subtype.len()).max().unwrap_or(0);
+    for (name,info,raw) in rows {
+        writeln!(out, "  {:<width$}  {:<sub_width$}  {:>9} elements  {:>11} bytes{}", name, info.subtype, info.len, info.size,
+            if raw { "  (undecoded)" } else { "" }, width=width, sub_width=sub_width)?;
+    }
+    Ok(())
+}
+
+/// Returns the json of a Branch, without the subtype
+fn branch_json(branch: &Branch) -> Result<JsonValue, Box<dyn error::Error>> {
+    Ok(JsonValue::parse(&branch.to_json())?.get("branch")?.clone())
+}
+
+fn dump(path: &str, key: &str, csv: bool, out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let value = match File::open(path)? {
+        File::Tree(tree) => {
+            let branch = tree.branches().find(|(name,_)| *name == key).ok_or_else(|| CalcifyError::key(key))?.1;
+            branch_json(branch)?
+        },
+        File::Feeds(feeds) => JsonValue::parse(&feeds.feed_json(key).ok_or_else(|| CalcifyError::key(key))?)?,
+    };
+    if csv {
+        write_csv(value.as_array()?, out)
+    } else {
+        writeln!(out, "{}", value.to_json())?;
+        Ok(())
+    }
+}
+
+/// Flattens a value into named columns, `r.x0` for fields of fields and `range.0` for array elements
+fn flatten(prefix: &str, value: &JsonValue, columns: &mut Vec<(String,String)>) {
+    let name = |key: &str| if prefix.is_empty() { String::from(key) } else { format!("{}.{}",prefix,key) };
+    match value {
+        JsonValue::Object(pairs) => {
+            for (key,val) in pairs {
+                flatten(&name(key), val, columns);
+            }
+        },
+        JsonValue::Array(vals) => {
+            for (i,val) in vals.iter().enumerate() {
+                flatten(&name(&i.to_string()), val, columns);
+            }
+        },
+        JsonValue::Null => columns.push((name_or_value(prefix), String::new())),
+        JsonValue::Bool(b) => columns.push((name_or_value(prefix), b.to_string())),
+        JsonValue::Number(n) => columns.push((name_or_value(prefix), n.clone())),
+        JsonValue::String(s) => columns.push((name_or_value(prefix), csv_field(s))),
+    }
+}
+
+fn name_or_value(prefix: &str) -> String {
+    if prefix.is_empty() { String::from("value") } else { String::from(prefix) }
+}
+
+/// Quotes a csv field if it needs it
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"",s.replace('"',"\"\""))
+    } else {
+        String::from(s)
+    }
+}
+
+/// Writes the elements of a Collection as csv, with the columns of the first element as the header
+fn write_csv(elements: &[JsonValue], out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let mut header: Vec<String> = Vec::new();
+    for (i,element) in elements.iter().enumerate() {
+        let mut columns = Vec::new();
+        flatten("", element, &mut columns);
+        if i == 0 {
+            header = columns.iter().map(|(name,_)| name.clone()).collect();
+            let names: Vec<String> = header.iter().map(|name| csv_field(name)).collect();
+            writeln!(out, "{}", names.join(","))?;
+        }
+        let row: Vec<&str> = header.iter()
+            .map(|name| columns.iter().find(|(col,_)| col == name).map_or("", |(_,val)| val.as_str()))
+            .collect();
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn convert(input: &str, output: &str, flags: &[&str], out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let layout = Layout::of(output, flags);
+    File::open(input)?.write(output, layout)?;
+    writeln!(out, "Wrote {} as {:?}", output, layout)?;
+    Ok(())
+}
+
+fn merge(output: &str, inputs: &[&str], flags: &[&str], policy: MergePolicy, out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let (first, rest) = inputs.split_first().ok_or("merge needs at least one input file")?;
+    let mut merged = File::open(first)?;
+    for path in rest {
+        merged.merge(File::open(path)?, policy).map_err(|e| format!("{}: {}",path,chain(e.as_ref())))?;
+    }
+    merged.write(output, Layout::of(output, flags))?;
+    writeln!(out, "Merged {} files into {}", inputs.len(), output)?;
+    Ok(())
+}
+
+fn validate(path: &str, out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    match File::open(path)? {
+        File::Tree(mut tree) => {
+            let mut names: Vec<String> = tree.branch_names().iter().map(|s| String::from(*s)).collect();
+            names.sort();
+            let mut failed = 0;
+            for name in names.iter() {
+                let branch = tree.branches().find(|(key,_)| key == name).map(|(_,b)| b);
+                let (subtype, raw) = match branch {
+                    Some(branch) => (String::from(branch.subtype()), branch.is_raw()),
+                    None => continue,
+                };
+                if !raw {
+                    continue;
+                }
+                let err: Option<CalcifyError> = with_subtype!(subtype.as_str(),
+                    T => tree.read_branch::<T>(name).err(),
+                    _ => None);
+                match err {
+                    Some(err) => {
+                        failed += 1;
+                        writeln!(out, "  {}: {}", name, chain(&err))?;
+                    },
+                    None => writeln!(out, "  {}: subtype {:?} is not built in, left undecoded", name, subtype)?,
+                }
+            }
+            if failed > 0 {
+                return Err(format!("{} of {} branches of {} do not decode",failed,names.len(),path).into());
+            }
+            writeln!(out, "{}: valid Tree with {} branches", path, names.len())?;
+        },
+        File::Feeds(feeds) => {
+            writeln!(out, "{}: valid FeedTree<{}> with {} feeds", path, feeds.subtype(), feeds.summary()?.len())?;
+        },
+    }
+    Ok(())
+}
+
+/// Joins an error and its sources into one line
+fn chain(err: &(dyn error::Error + 'static)) -> String {
+    let mut msg = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        msg = format!("{}: {}",msg,err);
+        source = err.source();
+    }
+    msg
+}
+
+fn policy(name: &str) -> Result<MergePolicy, Box<dyn error::Error>> {
+    match name {
+        "keep-first" => Ok(MergePolicy::KeepFirst),
+        "keep-last" => Ok(MergePolicy::KeepLast),
+        "error" => Ok(MergePolicy::Error),
+        _ => Err(format!("unknown merge policy {:?}, expected keep-first, keep-last or error",name).into()),
+    }
+}
+
+/// Runs the command in `args`, without the program name
+fn run(args: &[String], out: &mut dyn Write) -> Result<(), Box<dyn error::Error>> {
+    let mut positional: Vec<&str> = Vec::new();
+    let mut flags: Vec<&str> = Vec::new();
+    let mut merge_policy = MergePolicy::Error;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--policy" => merge_policy = policy(iter.next().ok_or("--policy needs a value")?)?,
+            "--csv" | "--container" | "--compressed" => flags.push(arg),
+            "-h" | "--help" => positional.insert(0, "help"),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {:?}\n\n{}",flag,USAGE).into()),
+            _ => positional.push(arg),
+        }
+    }
+    match positional.as_slice() {
+        ["info", path] => info(path, out),
+        ["dump", path, key] => dump(path, key, flags.contains(&"--csv"), out),
+        ["convert", input, output] => convert(input, output, &flags, out),
+        ["merge", output, inputs @ ..] if !inputs.is_empty() => merge(output, inputs, &flags, merge_policy, out),
+        ["validate", path] => validate(path, out),
+        ["help", ..] => { write!(out, "{}", USAGE)?; Ok(()) },
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Err(err) = run(&args, &mut out) {
+        eprintln!("calcify: {}", chain(err.as_ref()));
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calcify::{Collection, ThreeVec, Bin};
+
+    fn run_str(args: &[&str]) -> Result<String, Box<dyn error::Error>> {
+        let args: Vec<String> = args.iter().map(|s| String::from(*s)).collect();
+        let mut out = Vec::new();
+        run(&args, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    /// Path of a file for the tests in the system temp directory
+    fn temp(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("calcify_{}_{}", std::process::id(), name));
+        String::from(path.to_str().unwrap())
+    }
+
+    fn test_tree(seed: i64) -> Result<Tree, Box<dyn error::Error>> {
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Seed", seed)?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.5]))?;
+        ttree.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,2),Bin::new(1.0,2.0,3)]))?;
+        ttree.add_branch_as("strs", Collection::from(vec![String::from("a,b")]), "Object")?;
+        Ok(ttree)
+    }
+
+    #[test]
+    fn test_cli_tree() -> Result<(), Box<dyn error::Error>> {
+        test_tree(1)?.write_msg(&temp("test_cli_1.msg"))?;
+        test_tree(2)?.write_container(&temp("test_cli_2.msg"))?;
+
+        let info = run_str(&["info", &temp("test_cli_1.msg")])?;
+        assert!(info.starts_with(&format!("Tree {}\nMetadata:\n  Name = Test_Tree\n  Seed = 1\nBranches:\n", temp("test_cli_1.msg"))));
+        assert!(info.contains("  hist  Bin             2 elements"));
+        assert!(info.lines().any(|line| line.starts_with("  strs  Object") && line.ends_with("(undecoded)")));
+
+        assert_eq!(run_str(&["dump", &temp("test_cli_1.msg"), "fcol"])?,"[1,2.5]\n");
+        assert_eq!(run_str(&["dump", &temp("test_cli_1.msg"), "hist", "--csv"])?,"count,range.0,range.1\n2,0,1\n3,1,2\n");
+        assert_eq!(run_str(&["dump", &temp("test_cli_1.msg"), "strs", "--csv"])?,"value\n\"a,b\"\n");
+        assert!(run_str(&["dump", &temp("test_cli_1.msg"), "none"]).is_err());
+
+        run_str(&["convert", &temp("test_cli_2.msg"), &temp("test_cli_2.json")])?;
+        let mut jj = Tree::read_json(&temp("test_cli_2.json"))?;
+        assert_eq!(jj.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,2),Bin::new(1.0,2.0,3)]));
+
+        assert!(run_str(&["merge", &temp("test_cli_merged.msg"), &temp("test_cli_1.msg"), &temp("test_cli_2.json")]).is_err());
+        run_str(&["merge", &temp("test_cli_merged.msg"), &temp("test_cli_1.msg"), &temp("test_cli_2.json"), "--policy", "keep-last", "--container"])?;
+        let mut merged = Tree::read_msg(&temp("test_cli_merged.msg"))?;
+        assert_eq!(merged.get_field("Seed"),Some(&FieldValue::Int(2)));
+        assert_eq!(merged.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.5,1.0,2.5]));
+        assert_eq!(merged.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,4),Bin::new(1.0,2.0,6)]));
+
+        assert!(run_str(&["validate", &temp("test_cli_merged.msg")])?.ends_with("valid Tree with 3 branches\n"));
+        let mut bytes = fs::read(temp("test_cli_merged.msg"))?;
+        let last = bytes.len()-1;
+        bytes[last] ^= 0xff;
+        fs::write(temp("test_cli_corrupt.msg"), bytes)?;
+        let err = run_str(&["validate", &temp("test_cli_corrupt.msg")]).unwrap_err();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::ChecksumError{..})));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_feedtree() -> Result<(), Box<dyn error::Error>> {
+        test_tree(1)?.write_msg(&temp("test_cli_feeds_tree.msg"))?;
+        let mut ftree = FeedTree::new("Test_Tree","ThreeVec");
+        ftree.add_feed("fcol", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        ftree.write_msg(&temp("test_cli_feeds.msg"))?;
+
+        let info = run_str(&["info", &temp("test_cli_feeds.msg")])?;
+        assert!(info.starts_with(&format!("FeedTree {}\nMetadata:\n  Name = Test_Tree\n  SubType = ThreeVec\nFeeds:\n  fcol  ThreeVec", temp("test_cli_feeds.msg"))));
+        assert_eq!(run_str(&["dump", &temp("test_cli_feeds.msg"), "fcol", "--csv"])?,"x0,x1,x2\n1,2,3\n");
+
+        run_str(&["convert", &temp("test_cli_feeds.msg"), &temp("test_cli_feeds.json")])?;
+        assert_eq!(FeedTree::<ThreeVec>::read_json(&temp("test_cli_feeds.json"))?,ftree);
+        run_str(&["merge", &temp("test_cli_feeds_merged.msg"), &temp("test_cli_feeds.msg"), &temp("test_cli_feeds.json")])?;
+        let mut merged = FeedTree::<ThreeVec>::read_msg(&temp("test_cli_feeds_merged.msg"))?;
+        assert_eq!(merged.get_feed("fcol").unwrap().len(),2);
+
+        let other: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+        other.write_msg(&temp("test_cli_feeds_f64.msg"))?;
+        let err = run_str(&["merge", &temp("test_cli_feeds_merged.msg"), &temp("test_cli_feeds.msg"), &temp("test_cli_feeds_f64.msg")]).unwrap_err();
+        assert!(err.to_string().contains("Cannot merge FeedTree"));
+        assert!(run_str(&["merge", &temp("test_cli_feeds_merged.msg"), &temp("test_cli_feeds.msg"), &temp("test_cli_feeds_tree.msg")]).is_err());
+        assert!(run_str(&["validate", &temp("test_cli_feeds.json")])?.contains("valid FeedTree<ThreeVec> with 1 feeds"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_args() {
+        assert!(run_str(&["help"]).unwrap().starts_with("Usage: calcify"));
+        assert!(run_str(&[]).is_err());
+        assert!(run_str(&["info"]).is_err());
+        assert!(run_str(&["info", &temp("none.msg")]).is_err());
+        assert!(run_str(&["merge", "out.msg", "in.msg", "--policy", "newest"]).is_err());
+        assert!(run_str(&["dump", "in.msg", "fcol", "--tsv"]).is_err());
+    }
+}
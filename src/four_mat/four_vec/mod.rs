@@ -7,6 +7,7 @@ use std::ops::SubAssign;
 use std::ops::Mul;
 use std::ops::Neg;
 use std::iter;
+use std::convert::TryFrom;
 use std::fmt;
 use std::error;
 
@@ -16,7 +17,7 @@ use three_mat::ThreeVec;
 
 use crate::utils;
 use utils::consts;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 extern crate rmp;
@@ -119,7 +120,7 @@ impl FourVec {
     ///
     /// # Panics
     ///
-    /// * `slice` length < 4
+    /// * `slice` length < 4, see `TryFrom`
     pub fn from(slice: &[f64]) -> FourVec {
 
         FourVec {
@@ -260,6 +261,30 @@ impl FourVec {
 
 }
 
+/// Returns a new FourVec from a slice, without panicking
+///
+/// # Example
+/// ```
+/// use std::convert::TryFrom;
+/// use calcify::FourVec;
+/// assert_eq!(FourVec::try_from(&[4.0,1.0,2.0,3.0][..]).unwrap(),FourVec::new(4.0,1.0,2.0,3.0));
+/// assert!(FourVec::try_from(&[4.0,1.0][..]).is_err());
+/// ```
+///
+/// # Errors
+///
+/// * `CalcifyError::LengthError` if `slice` length < 4
+impl TryFrom<&[f64]> for FourVec {
+    type Error = CalcifyError;
+
+    fn try_from(slice: &[f64]) -> Result<FourVec,CalcifyError> {
+        match slice {
+            [m0, m1, m2, m3, ..] => Ok(FourVec::new(*m0,*m1,*m2,*m3)),
+            _ => Err(CalcifyError::LengthError{expected: 4, found: slice.len()}),
+        }
+    }
+}
+
 impl fmt::Display for FourVec {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "[{:.*}, {:.*}, {:.*}, {:.*}]", 5, self.m0(), 5, self.m1(), 5, self.m2(), 5, self.m3())
@@ -285,20 +310,14 @@ impl Serializable for FourVec {
 impl Deserializable for FourVec {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut m0: f64 = NAN;
-        let mut m1: f64 = NAN;
-        let mut m2: f64 = NAN;
-        let mut m3: f64 = NAN;
-        for dim in s.trim_matches(|p| p == '{' || p == '}' ).split(',') {
-            let n_v: Vec<&str> = dim.split(':').collect();
-            match n_v[0] {
-                "\"m0\"" => m0 = n_v[1].parse::<f64>()?,
-                "\"m1\"" => m1 = n_v[1].parse::<f64>()?,
-                "\"m2\"" => m2 = n_v[1].parse::<f64>()?,
-                "\"m3\"" => m3 = n_v[1].parse::<f64>()?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
-        }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let m0 = value.get("m0")?.as_f64()?;
+        let m1 = value.get("m1")?.as_f64()?;
+        let m2 = value.get("m2")?.as_f64()?;
+        let m3 = value.get("m3")?.as_f64()?;
         Ok(FourVec{m0,m1,m2,m3})
     }
 
@@ -310,7 +329,7 @@ impl Deserializable for FourVec {
             }
             Ok((FourVec::from(&x),bytes))
         } else {
-            Err(Box::new(CalcifyError::ParseError))
+            Err(Box::new(CalcifyError::parse("FourVec")))
         }
     }
 }
@@ -428,6 +447,42 @@ impl Neg for FourVec {
 }
 
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::FourVec;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        m0: f64,
+        m1: f64,
+        m2: f64,
+        m3: f64,
+    }
+
+    impl Serialize for FourVec {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{m0: self.m0, m1: self.m1, m2: self.m2, m3: self.m3}.serialize(serializer)
+            } else {
+                (self.m0, self.m1, self.m2, self.m3).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for FourVec {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                Ok(FourVec::new(f.m0, f.m1, f.m2, f.m3))
+            } else {
+                let (m0, m1, m2, m3) = <(f64,f64,f64,f64)>::deserialize(deserializer)?;
+                Ok(FourVec::new(m0, m1, m2, m3))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
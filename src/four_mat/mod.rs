@@ -6,6 +6,7 @@ use std::ops::Sub;
 use std::ops::SubAssign;
 use std::ops::Mul;
 use std::ops::Neg;
+use std::convert::TryFrom;
 use std::fmt;
 use std::error;
 
@@ -21,7 +22,7 @@ use crate::utils;
 
 use three_mat::ThreeVec;
 
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 extern crate rmp;
@@ -76,7 +77,7 @@ impl FourMat {
     ///
     /// # Panics
     ///
-    /// * `slice` length < 4
+    /// * `slice` length < 4, see `TryFrom`
     pub fn from(slice: &[FourVec]) -> FourMat {
 
         FourMat {
@@ -280,6 +281,22 @@ impl FourMat {
     }
 }
 
+/// Returns a new FourMat from a slice, without panicking
+///
+/// # Errors
+///
+/// * `CalcifyError::LengthError` if `slice` length < 4
+impl TryFrom<&[FourVec]> for FourMat {
+    type Error = CalcifyError;
+
+    fn try_from(slice: &[FourVec]) -> Result<FourMat,CalcifyError> {
+        match slice {
+            [n0, n1, n2, n3, ..] => Ok(FourMat::new(*n0,*n1,*n2,*n3)),
+            _ => Err(CalcifyError::LengthError{expected: 4, found: slice.len()}),
+        }
+    }
+}
+
 impl fmt::Display for FourMat {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "[{},\n{},\n{},\n{}]", self.n0(), self.n1(), self.n2(), self.n3())
@@ -311,20 +328,14 @@ impl Serializable for FourMat {
 impl Deserializable for FourMat {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut n0: FourVec = FourVec::new(NAN,NAN,NAN,NAN);
-        let mut n1: FourVec = FourVec::new(NAN,NAN,NAN,NAN);
-        let mut n2: FourVec = FourVec::new(NAN,NAN,NAN,NAN);
-        let mut n3: FourVec = FourVec::new(NAN,NAN,NAN,NAN);
-        for dim in s.replace("}}","|}").replace("},","}|").replace(":{",":!{").trim_matches(|p| p == '{' || p == '}' ).split_terminator('|') {
-            let n_v: Vec<&str> = dim.split(":!").collect();
-            match n_v[0] {
-                "\"n0\"" => n0 = FourVec::from_json(n_v[1])?,
-                "\"n1\"" => n1 = FourVec::from_json(n_v[1])?,
-                "\"n2\"" => n2 = FourVec::from_json(n_v[1])?,
-                "\"n3\"" => n3 = FourVec::from_json(n_v[1])?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
-        }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let n0 = FourVec::from_json_value(value.get("n0")?)?;
+        let n1 = FourVec::from_json_value(value.get("n1")?)?;
+        let n2 = FourVec::from_json_value(value.get("n2")?)?;
+        let n3 = FourVec::from_json_value(value.get("n3")?)?;
         Ok(FourMat{n0,n1,n2,n3})
     }
 
@@ -338,7 +349,7 @@ impl Deserializable for FourMat {
             }
             Ok((FourMat::from(&x),bytes))
         } else {
-            Err(Box::new(CalcifyError::ParseError))
+            Err(Box::new(CalcifyError::parse("FourMat")))
         }
     }
 }
@@ -535,6 +546,50 @@ pub fn boost<'a>(initial: FourVec, v: ThreeVec) -> Result<FourVec,CalcifyError>
     Ok(ll*initial)
 }
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{FourMat, FourVec};
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize)]
+    struct Fields<'a> {
+        n0: &'a FourVec,
+        n1: &'a FourVec,
+        n2: &'a FourVec,
+        n3: &'a FourVec,
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedFields {
+        n0: FourVec,
+        n1: FourVec,
+        n2: FourVec,
+        n3: FourVec,
+    }
+
+    impl Serialize for FourMat {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{n0: &self.n0, n1: &self.n1, n2: &self.n2, n3: &self.n3}.serialize(serializer)
+            } else {
+                (&self.n0, &self.n1, &self.n2, &self.n3).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for FourMat {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = OwnedFields::deserialize(deserializer)?;
+                Ok(FourMat::new(f.n0, f.n1, f.n2, f.n3))
+            } else {
+                let (n0, n1, n2, n3) = <(FourVec,FourVec,FourVec,FourVec)>::deserialize(deserializer)?;
+                Ok(FourMat::new(n0, n1, n2, n3))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -9,6 +9,11 @@
 //! Python command line utility and module for analyzing Tree files.
 //!
 //! Check it out [here!](https://github.com/JTPond/ICalcify "ICalcify GitHub")
+//!
+//! ## Command line
+//!
+//! The `calcify` binary, installed with `cargo install calcify`, prints the metadata and branches of Tree and FeedTree files,
+//! dumps a branch as json or csv, converts between json and msg, merges files and validates them. Run `calcify help` for usage.
 
 mod field;
 mod tree;
@@ -20,8 +25,14 @@ pub use field::ThreeField;
 pub use field::ThreeVecField;
 
 pub use tree::Branch;
+pub use tree::BranchInfo;
+pub use tree::RawBranch;
+pub use tree::TreeSubtype;
 pub use tree::Tree;
+pub use tree::TreeFile;
+pub use tree::MergePolicy;
 pub use tree::FeedTree;
+pub use tree::FeedStream;
 pub use tree::Collection;
 pub use tree::Bin;
 pub use tree::Point;
@@ -36,6 +47,8 @@ pub use four_mat::FourMat;
 
 pub use three_mat::ThreeMat;
 pub use three_mat::ThreeVec;
+pub use three_mat::EulerOrder;
+pub use three_mat::Quaternion;
 pub use three_mat::{radians_between, degrees_between};
 
 pub use utils::consts;
@@ -43,3 +56,13 @@ pub use utils::errors;
 pub use utils::io;
 pub use utils::Serializable;
 pub use utils::Deserializable;
+pub use utils::JsonValue;
+pub use utils::FieldValue;
+
+#[cfg(feature = "derive")]
+pub use calcify_derive::{Serializable, Deserializable};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::utils::derive::*;
+}
@@ -4,6 +4,7 @@ use std::ops::Sub;
 use std::ops::SubAssign;
 use std::ops::Mul;
 use std::ops::Neg;
+use std::convert::TryFrom;
 use std::fmt;
 use std::error;
 use std::f64::NAN;
@@ -13,8 +14,16 @@ mod three_vec;
 pub use three_vec::ThreeVec;
 pub use three_vec::{radians_between, degrees_between};
 
+/// Rotation Module
+mod rotation;
+pub use rotation::EulerOrder;
+
+/// Quaternion Module
+mod quaternion;
+pub use quaternion::Quaternion;
+
 use crate::utils;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 extern crate rmp;
@@ -65,7 +74,7 @@ impl ThreeMat {
     ///
     /// # Panics
     ///
-    /// * `slice` length < 3
+    /// * `slice` length < 3, see `TryFrom`
     pub fn from(slice: &[ThreeVec]) -> ThreeMat {
 
         ThreeMat {
@@ -233,6 +242,302 @@ impl ThreeMat {
     pub fn c2(&self) -> ThreeVec {
         ThreeVec::new(*self.r0.x2(),*self.r1.x2(),*self.r2.x2())
     }
+
+    /// Returns the transpose of the matrix.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(4.0,5.0,6.0),
+    ///               ThreeVec::new(7.0,8.0,9.0)
+    ///            );
+    /// assert_eq!(*mat3.transpose().r0(),mat3.c0());
+    /// assert_eq!(mat3.transpose().transpose(),mat3);
+    /// ```
+    pub fn transpose(&self) -> ThreeMat {
+        ThreeMat::new(self.c0(),self.c1(),self.c2())
+    }
+
+    /// Returns the trace of the matrix, the sum of the diagonal.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(4.0,5.0,6.0),
+    ///               ThreeVec::new(7.0,8.0,9.0)
+    ///            );
+    /// assert_eq!(mat3.trace(),15.0);
+    /// ```
+    pub fn trace(&self) -> f64 {
+        *self.r0.x0() + *self.r1.x1() + *self.r2.x2()
+    }
+
+    /// Returns the determinant of the matrix.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(0.0,1.0,4.0),
+    ///               ThreeVec::new(5.0,6.0,0.0)
+    ///            );
+    /// assert_eq!(mat3.det(),1.0);
+    /// assert_eq!(ThreeMat::one().det(),0.0);
+    /// ```
+    pub fn det(&self) -> f64 {
+        self.r0.triple(self.r1,self.r2)
+    }
+
+    /// Returns the adjugate of the matrix, the transpose of its cofactor matrix.
+    ///
+    /// Defined for singular matrices too, `mat3*mat3.adjugate()` is `mat3.det()` times the identity.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(4.0,5.0,6.0),
+    ///               ThreeVec::new(7.0,8.0,9.0)
+    ///            );
+    /// assert_eq!(
+    ///     mat3.adjugate(),
+    ///     ThreeMat::new(ThreeVec::new(-3.0,6.0,-3.0),
+    ///                 ThreeVec::new(6.0,-12.0,6.0),
+    ///                 ThreeVec::new(-3.0,6.0,-3.0)));
+    /// assert_eq!(mat3*mat3.adjugate(),ThreeMat::zero());
+    /// ```
+    pub fn adjugate(&self) -> ThreeMat {
+        ThreeMat::new(self.r1^self.r2,self.r2^self.r0,self.r0^self.r1).transpose()
+    }
+
+    /// Returns the inverse of the matrix.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::SingularMatrixError` if the determinant is zero, to within rounding relative to the size of the rows
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(0.0,1.0,4.0),
+    ///               ThreeVec::new(5.0,6.0,0.0)
+    ///            );
+    /// let inv = mat3.inverse().unwrap();
+    /// assert_eq!(
+    ///     inv,
+    ///     ThreeMat::new(ThreeVec::new(-24.0,18.0,5.0),
+    ///                 ThreeVec::new(20.0,-15.0,-4.0),
+    ///                 ThreeVec::new(-5.0,4.0,1.0)));
+    /// assert_eq!(mat3*inv,ThreeMat::eye());
+    ///
+    /// let singular = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(4.0,5.0,6.0),
+    ///               ThreeVec::new(7.0,8.0,9.0)
+    ///            );
+    /// assert!(singular.inverse().is_err());
+    /// ```
+    pub fn inverse(&self) -> Result<ThreeMat,CalcifyError> {
+        let det = self.det();
+        if !det.is_finite() || det.abs() <= f64::EPSILON*self.r0.r()*self.r1.r()*self.r2.r() {
+            return Err(CalcifyError::SingularMatrixError);
+        }
+        Ok(self.adjugate()*(1.0/det))
+    }
+
+    /// Returns x solving `mat3*x = b`.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::SingularMatrixError` if the matrix is singular, see `inverse`
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,2.0,3.0),
+    ///               ThreeVec::new(0.0,1.0,4.0),
+    ///               ThreeVec::new(5.0,6.0,0.0)
+    ///            );
+    /// let x = mat3.solve(ThreeVec::new(6.0,5.0,11.0)).unwrap();
+    /// assert_eq!(x,ThreeVec::new(1.0,1.0,1.0));
+    /// assert!(ThreeMat::one().solve(x).is_err());
+    /// ```
+    pub fn solve(&self, b: ThreeVec) -> Result<ThreeVec,CalcifyError> {
+        Ok(self.inverse()?*b)
+    }
+
+    /// Returns the eigenvalues and eigenvectors of a symmetric matrix, e.g. an inertia or stress tensor.
+    ///
+    /// The eigenvalues are in ascending order, and the eigenvectors are the matching columns of an orthonormal
+    /// ThreeMat with determinant 1, so it is the rotation to the principal axes, and `mat3 = v*diag*v.transpose()`.
+    /// Found with the cyclic Jacobi method.
+    ///
+    /// # Note
+    ///
+    /// Only the symmetric part of the matrix, `(mat3 + mat3.transpose())*0.5`, is used.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(2.0,1.0,0.0),
+    ///               ThreeVec::new(1.0,2.0,0.0),
+    ///               ThreeVec::new(0.0,0.0,5.0)
+    ///            );
+    /// let (vals, vecs) = mat3.sym_eigen();
+    /// assert!((vals - ThreeVec::new(1.0,3.0,5.0)).r() < 1e-12);
+    /// assert!((mat3*vecs.c1() - vecs.c1()*3.0).r() < 1e-12);
+    /// assert!((vecs*vecs.transpose() - ThreeMat::eye()).r0().r() < 1e-12);
+    /// ```
+    pub fn sym_eigen(&self) -> (ThreeVec, ThreeMat) {
+        let rows = self.rows();
+        let mut a = [[0.0; 3]; 3];
+        for (i, row) in a.iter_mut().enumerate() {
+            for (j, el) in row.iter_mut().enumerate() {
+                *el = 0.5*(rows[i][j] + rows[j][i]);
+            }
+        }
+        let mut v = ThreeMat::eye().rows();
+        for _sweep in 0..50 {
+            let off = a[0][1]*a[0][1] + a[0][2]*a[0][2] + a[1][2]*a[1][2];
+            let norm: f64 = a.iter().flatten().map(|x| x*x).sum();
+            if off <= f64::EPSILON*f64::EPSILON*norm {
+                break;
+            }
+            for &(p, q) in [(0, 1), (0, 2), (1, 2)].iter() {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // Rotation in the p-q plane that zeroes a[p][q]
+                let theta = (a[q][q] - a[p][p])/(2.0*a[p][q]);
+                let t = theta.signum()/(theta.abs() + (theta*theta + 1.0).sqrt());
+                let c = 1.0/(t*t + 1.0).sqrt();
+                let s = t*c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c*akp - s*akq;
+                    row[q] = s*akp + c*akq;
+                }
+                let (ap, aq) = (a[p], a[q]);
+                a[p] = [0, 1, 2].map(|k| c*ap[k] - s*aq[k]);
+                a[q] = [0, 1, 2].map(|k| s*ap[k] + c*aq[k]);
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c*vkp - s*vkq;
+                    row[q] = s*vkp + c*vkq;
+                }
+            }
+        }
+        let mut order = [0, 1, 2];
+        order.sort_by(|&i, &j| a[i][i].partial_cmp(&a[j][j]).unwrap_or(std::cmp::Ordering::Equal));
+        let col = |i: usize| ThreeVec::new(v[0][i], v[1][i], v[2][i]);
+        let mut vecs = ThreeMat::new(col(order[0]), col(order[1]), col(order[2])).transpose();
+        if vecs.det() < 0.0 {
+            vecs = ThreeMat::new(col(order[0]), col(order[1]), -col(order[2])).transpose();
+        }
+        (ThreeVec::new(a[order[0]][order[0]], a[order[1]][order[1]], a[order[2]][order[2]]), vecs)
+    }
+
+    /// Returns the eigenvalues of any matrix, as (real, imaginary) pairs.
+    ///
+    /// The roots of the characteristic polynomial, in ascending order of the real part.
+    /// A complex conjugate pair is returned with the positive imaginary part first. For symmetric matrices
+    /// all three are real, and `sym_eigen` is more accurate.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// // Rotation by 90 degrees about x0
+    /// let mat3 = ThreeMat::new(
+    ///               ThreeVec::new(1.0,0.0,0.0),
+    ///               ThreeVec::new(0.0,0.0,-1.0),
+    ///               ThreeVec::new(0.0,1.0,0.0)
+    ///            );
+    /// let vals = mat3.eigenvalues();
+    /// assert!((vals[0].0).abs() < 1e-12 && (vals[0].1 - 1.0).abs() < 1e-12);
+    /// assert!((vals[1].0).abs() < 1e-12 && (vals[1].1 + 1.0).abs() < 1e-12);
+    /// assert!((vals[2].0 - 1.0).abs() < 1e-12 && vals[2].1 == 0.0);
+    /// ```
+    pub fn eigenvalues(&self) -> [(f64,f64); 3] {
+        // λ³ + aλ² + bλ + c, depressed to t³ + pt + q by λ = t - a/3
+        let a = -self.trace();
+        let b = (self.trace()*self.trace() - (*self*(*self)).trace())/2.0;
+        let c = -self.det();
+        let p = b - a*a/3.0;
+        let q = 2.0*a*a*a/27.0 - a*b/3.0 + c;
+        let disc = q*q/4.0 + p*p*p/27.0;
+        let mut vals = if disc <= 0.0 {
+            // Three real roots
+            let m = 2.0*(-p/3.0).sqrt();
+            let arg = if m == 0.0 { 0.0 } else { (3.0*q/(p*m)).clamp(-1.0, 1.0) };
+            let phi = arg.acos()/3.0;
+            let roots: Vec<(f64,f64)> = (0..3)
+                .map(|k| (m*(phi - 2.0*std::f64::consts::PI*(k as f64)/3.0).cos() - a/3.0, 0.0))
+                .collect();
+            [roots[0], roots[1], roots[2]]
+        } else {
+            // One real root, and a complex pair from the remaining quadratic λ² + eλ + f
+            let sq = disc.sqrt();
+            let real = (-q/2.0 + sq).cbrt() + (-q/2.0 - sq).cbrt() - a/3.0;
+            let e = a + real;
+            let f = b + e*real;
+            let im = (4.0*f - e*e).max(0.0).sqrt()/2.0;
+            [(real, 0.0), (-e/2.0, im), (-e/2.0, -im)]
+        };
+        vals.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(std::cmp::Ordering::Equal).then(y.1.partial_cmp(&x.1).unwrap_or(std::cmp::Ordering::Equal)));
+        vals
+    }
+
+    /// Returns the elements as rows of arrays
+    fn rows(&self) -> [[f64; 3]; 3] {
+        [
+            [*self.r0.x0(), *self.r0.x1(), *self.r0.x2()],
+            [*self.r1.x0(), *self.r1.x1(), *self.r1.x2()],
+            [*self.r2.x0(), *self.r2.x1(), *self.r2.x2()],
+        ]
+    }
+
+    /// Returns a new ThreeMat from rows of arrays
+    fn from_rows(rows: [[f64; 3]; 3]) -> ThreeMat {
+        ThreeMat {
+            r0: ThreeVec::from(&rows[0]),
+            r1: ThreeVec::from(&rows[1]),
+            r2: ThreeVec::from(&rows[2]),
+        }
+    }
+}
+
+/// Returns a new ThreeMat from a slice, without panicking
+///
+/// # Errors
+///
+/// * `CalcifyError::LengthError` if `slice` length < 3
+impl TryFrom<&[ThreeVec]> for ThreeMat {
+    type Error = CalcifyError;
+
+    fn try_from(slice: &[ThreeVec]) -> Result<ThreeMat,CalcifyError> {
+        match slice {
+            [r0, r1, r2, ..] => Ok(ThreeMat::new(*r0,*r1,*r2)),
+            _ => Err(CalcifyError::LengthError{expected: 3, found: slice.len()}),
+        }
+    }
 }
 
 impl fmt::Display for ThreeMat {
@@ -262,18 +567,13 @@ impl Serializable for ThreeMat {
 impl Deserializable for ThreeMat {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut r0: ThreeVec = ThreeVec::new(NAN,NAN,NAN);
-        let mut r1: ThreeVec = ThreeVec::new(NAN,NAN,NAN);
-        let mut r2: ThreeVec = ThreeVec::new(NAN,NAN,NAN);
-        for dim in s.replace("}}","|}").replace("},","}|").replace(":{",":!{").trim_matches(|p| p == '{' || p == '}' ).split_terminator('|') {
-            let n_v: Vec<&str> = dim.split(":!").collect();
-            match n_v[0] {
-                "\"r0\"" => r0 = ThreeVec::from_json(n_v[1])?,
-                "\"r1\"" => r1 = ThreeVec::from_json(n_v[1])?,
-                "\"r2\"" => r2 = ThreeVec::from_json(n_v[1])?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
-        }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let r0 = ThreeVec::from_json_value(value.get("r0")?)?;
+        let r1 = ThreeVec::from_json_value(value.get("r1")?)?;
+        let r2 = ThreeVec::from_json_value(value.get("r2")?)?;
         Ok(ThreeMat{r0,r1,r2})
     }
 
@@ -287,7 +587,7 @@ impl Deserializable for ThreeMat {
             }
             Ok((ThreeMat::from(&x),bytes))
         } else {
-            Err(Box::new(CalcifyError::ParseError))
+            Err(Box::new(CalcifyError::parse("ThreeMat")))
         }
     }
 }
@@ -429,6 +729,48 @@ impl Neg for ThreeMat {
 }
 
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{ThreeMat, ThreeVec};
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize)]
+    struct Fields<'a> {
+        r0: &'a ThreeVec,
+        r1: &'a ThreeVec,
+        r2: &'a ThreeVec,
+    }
+
+    #[derive(Deserialize)]
+    struct OwnedFields {
+        r0: ThreeVec,
+        r1: ThreeVec,
+        r2: ThreeVec,
+    }
+
+    impl Serialize for ThreeMat {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{r0: &self.r0, r1: &self.r1, r2: &self.r2}.serialize(serializer)
+            } else {
+                (&self.r0, &self.r1, &self.r2).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ThreeMat {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = OwnedFields::deserialize(deserializer)?;
+                Ok(ThreeMat::new(f.r0, f.r1, f.r2))
+            } else {
+                let (r0, r1, r2) = <(ThreeVec,ThreeVec,ThreeVec)>::deserialize(deserializer)?;
+                Ok(ThreeMat::new(r0, r1, r2))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -577,6 +919,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_inverse() {
+        let _test_mat = ThreeMat::new(ThreeVec::new(4.0,7.0,2.0),
+                                    ThreeVec::new(3.0,6.0,1.0),
+                                    ThreeVec::new(2.0,5.0,3.0));
+        assert_eq!(_test_mat.det(),9.0);
+        assert_eq!(_test_mat.transpose().det(),9.0);
+        assert_eq!(_test_mat.trace(),13.0);
+        assert_eq!(_test_mat*_test_mat.adjugate(),ThreeMat::eye()*9.0);
+        let inv = _test_mat.inverse().unwrap();
+        let diff = _test_mat*inv - ThreeMat::eye();
+        for row in [diff.r0(),diff.r1(),diff.r2()].iter() {
+            assert!(row.r() < 1e-12);
+        }
+        let x = ThreeVec::new(1.0,-2.0,0.5);
+        assert!((_test_mat.solve(_test_mat*x).unwrap() - x).r() < 1e-12);
+
+        // Singular to within rounding, at any scale
+        let _singular = ThreeMat::new(ThreeVec::new(1e-3,2e-3,3e-3),
+                                    ThreeVec::new(4e-3,5e-3,6e-3),
+                                    ThreeVec::new(7e-3,8e-3,9e-3));
+        assert!(matches!(_singular.inverse(),Err(CalcifyError::SingularMatrixError)));
+        assert!(matches!((_singular*1e9).solve(x),Err(CalcifyError::SingularMatrixError)));
+        assert!(ThreeMat::zero().inverse().is_err());
+        assert!((ThreeMat::eye()*1e-6).inverse().is_ok());
+    }
+
+    fn rotation(angle: f64) -> ThreeMat {
+        // About (1,1,1)/sqrt(3), so no element is zero
+        let (c, s) = (angle.cos(), angle.sin());
+        let n = 1.0/3.0_f64.sqrt();
+        let t = 1.0 - c;
+        ThreeMat::new(ThreeVec::new(c + n*n*t, n*n*t - n*s, n*n*t + n*s),
+                    ThreeVec::new(n*n*t + n*s, c + n*n*t, n*n*t - n*s),
+                    ThreeVec::new(n*n*t - n*s, n*n*t + n*s, c + n*n*t))
+    }
+
+    fn close(a: ThreeMat, b: ThreeMat) -> bool {
+        let diff = a - b;
+        diff.r0().r() + diff.r1().r() + diff.r2().r() < 1e-10
+    }
+
+    #[test]
+    fn test_sym_eigen() {
+        let (vals, vecs) = ThreeMat::new(ThreeVec::new(3.0,0.0,0.0),
+                                    ThreeVec::new(0.0,-1.0,0.0),
+                                    ThreeVec::new(0.0,0.0,2.0)).sym_eigen();
+        assert_eq!(vals,ThreeVec::new(-1.0,2.0,3.0));
+        assert_eq!(vecs.c0().r(),1.0);
+        assert_eq!(vecs.det(),1.0);
+
+        let rot = rotation(0.7);
+        let diag = ThreeMat::new(ThreeVec::new(1.0,0.0,0.0),
+                                ThreeVec::new(0.0,2.0,0.0),
+                                ThreeVec::new(0.0,0.0,4.0));
+        let _test_mat = rot*diag*rot.transpose();
+        let (vals, vecs) = _test_mat.sym_eigen();
+        assert!((vals - ThreeVec::new(1.0,2.0,4.0)).r() < 1e-12);
+        assert!(close(vecs*vecs.transpose(), ThreeMat::eye()));
+        assert!((vecs.det() - 1.0).abs() < 1e-12);
+        assert!(close(vecs*diag*vecs.transpose(), _test_mat));
+        // Eigenvectors are the columns of the rotation, up to sign
+        assert!((vecs.c2()*rot.c2()).abs() > 1.0 - 1e-12);
+
+        // Repeated eigenvalues still give an orthonormal basis
+        let _degenerate = rot*ThreeMat::new(ThreeVec::new(2.0,0.0,0.0),
+                                    ThreeVec::new(0.0,2.0,0.0),
+                                    ThreeVec::new(0.0,0.0,-1.0))*rot.transpose();
+        let (vals, vecs) = _degenerate.sym_eigen();
+        assert!((vals - ThreeVec::new(-1.0,2.0,2.0)).r() < 1e-12);
+        assert!(close(vecs*vecs.transpose(), ThreeMat::eye()));
+        assert!(close(_degenerate*vecs, vecs*ThreeMat::new(ThreeVec::new(-1.0,0.0,0.0),
+                                                        ThreeVec::new(0.0,2.0,0.0),
+                                                        ThreeVec::new(0.0,0.0,2.0))));
+        assert_eq!(ThreeMat::zero().sym_eigen(),(ThreeVec::new(0.0,0.0,0.0),ThreeMat::eye()));
+    }
+
+    #[test]
+    fn test_eigenvalues() {
+        let rot = rotation(0.7);
+        let vals = rot.eigenvalues();
+        assert!((vals[0].0 - 0.7_f64.cos()).abs() < 1e-12 && (vals[0].1 - 0.7_f64.sin()).abs() < 1e-12);
+        assert!((vals[1].0 - 0.7_f64.cos()).abs() < 1e-12 && (vals[1].1 + 0.7_f64.sin()).abs() < 1e-12);
+        assert!((vals[2].0 - 1.0).abs() < 1e-12 && vals[2].1 == 0.0);
+
+        let _triangular = ThreeMat::new(ThreeVec::new(3.0,5.0,-2.0),
+                                    ThreeVec::new(0.0,-1.0,7.0),
+                                    ThreeVec::new(0.0,0.0,2.0));
+        let vals = _triangular.eigenvalues();
+        for (val, expected) in vals.iter().zip([-1.0,2.0,3.0].iter()) {
+            assert!((val.0 - expected).abs() < 1e-12 && val.1 == 0.0);
+        }
+        let _symmetric = rot*ThreeMat::new(ThreeVec::new(1.0,0.0,0.0),
+                                    ThreeVec::new(0.0,2.0,0.0),
+                                    ThreeVec::new(0.0,0.0,4.0))*rot.transpose();
+        let (sym, _) = _symmetric.sym_eigen();
+        let vals = _symmetric.eigenvalues();
+        assert!((ThreeVec::new(vals[0].0,vals[1].0,vals[2].0) - sym).r() < 1e-9);
+        assert_eq!((ThreeMat::eye()*2.0).eigenvalues(),[(2.0,0.0); 3]);
+    }
+
     #[test]
     fn test_copy() {
         let xx = ThreeMat::new(ThreeVec::new(1.0,1.0,1.0),
@@ -0,0 +1,629 @@
+use std::ops::Add;
+use std::ops::AddAssign;
+use std::ops::Sub;
+use std::ops::SubAssign;
+use std::ops::Mul;
+use std::ops::Neg;
+use std::fmt;
+use std::error;
+
+use super::ThreeMat;
+use super::ThreeVec;
+
+use crate::utils;
+use utils::{Serializable, Deserializable, JsonValue};
+use utils::errors::CalcifyError;
+
+extern crate rmp;
+use rmp::encode::*;
+use rmp::decode::*;
+
+/// Quaternion, `w + x*i + y*j + z*k`
+///
+/// Unit quaternions represent rotations, with the same conventions as the rotation matrices of ThreeMat:
+/// `q.to_rotation()*vec3 == q.rotate(vec3)`, and the Hamilton product `q1*q2` rotates by `q2` then `q1`.
+/// `q` and `-q` are the same rotation.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Quaternion {
+    w: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Quaternion {
+    /// Returns a new Quaternion from four f64s
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - f64, the scalar part
+    /// * `x` - f64
+    /// * `y` - f64
+    /// * `z` - f64
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::new(1.0,0.0,0.0,0.0);
+    /// ```
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion {
+            w,
+            x,
+            y,
+            z,
+        }
+    }
+
+    /// Returns a new Quaternion from its scalar and vector parts
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::from_parts(1.0,ThreeVec::new(2.0,3.0,4.0));
+    /// assert_eq!(quat,Quaternion::new(1.0,2.0,3.0,4.0));
+    /// ```
+    pub fn from_parts(w: f64, v: ThreeVec) -> Quaternion {
+        Quaternion {
+            w,
+            x: *v.x0(),
+            y: *v.x1(),
+            z: *v.x2(),
+        }
+    }
+
+    /// Returns the identity Quaternion, no rotation
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let vec3 = ThreeVec::new(1.0,2.0,3.0);
+    /// assert_eq!(Quaternion::identity().rotate(vec3),vec3);
+    /// ```
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0,0.0,0.0,0.0)
+    }
+
+    /// Returns a reference to the scalar part
+    pub fn w(&self) -> &f64 {
+        &self.w
+    }
+
+    /// Returns a reference to the i component
+    pub fn x(&self) -> &f64 {
+        &self.x
+    }
+
+    /// Returns a reference to the j component
+    pub fn y(&self) -> &f64 {
+        &self.y
+    }
+
+    /// Returns a reference to the k component
+    pub fn z(&self) -> &f64 {
+        &self.z
+    }
+
+    /// Returns the vector part as a ThreeVec
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::new(1.0,2.0,3.0,4.0);
+    /// assert_eq!(quat.vector(),ThreeVec::new(2.0,3.0,4.0));
+    /// ```
+    pub fn vector(&self) -> ThreeVec {
+        ThreeVec::new(self.x,self.y,self.z)
+    }
+
+    /// Returns the four component dot product
+    pub fn dot(&self, other: Quaternion) -> f64 {
+        self.w*other.w + self.x*other.x + self.y*other.y + self.z*other.z
+    }
+
+    /// Returns the norm
+    ///
+    /// Found with `hypot`, so it doesn't overflow or underflow for very large or small components.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::new(1.0,1.0,1.0,1.0);
+    /// assert_eq!(quat.norm(),2.0);
+    /// ```
+    pub fn norm(&self) -> f64 {
+        self.w.hypot(self.x).hypot(self.y).hypot(self.z)
+    }
+
+    /// Returns the conjugate, `w - x*i - y*j - z*k`, the inverse rotation of a unit Quaternion
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::new(1.0,2.0,3.0,4.0);
+    /// assert_eq!(quat.conjugate(),Quaternion::new(1.0,-2.0,-3.0,-4.0));
+    /// assert_eq!(quat*quat.conjugate(),Quaternion::new(30.0,0.0,0.0,0.0));
+    /// ```
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::new(self.w,-self.x,-self.y,-self.z)
+    }
+
+    /// Returns the Quaternion scaled to norm one
+    ///
+    /// The zero Quaternion is returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::new(0.0,3.0,0.0,4.0);
+    /// assert_eq!(quat.normalize(),Quaternion::new(0.0,0.6,0.0,0.8));
+    /// ```
+    pub fn normalize(&self) -> Quaternion {
+        let n = self.norm();
+        if n == 0.0 {
+            *self
+        } else {
+            Quaternion::new(self.w/n,self.x/n,self.y/n,self.z/n)
+        }
+    }
+
+    /// Returns the rotation by `angle` radians about `axis`, see `ThreeMat::from_axis_angle`
+    ///
+    /// The axis need not be a unit vector. The zero axis gives the identity.
+    ///
+    /// # Example
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::from_axis_angle(ThreeVec::new(0.0,0.0,1.0),PI);
+    /// assert!((quat.rotate(ThreeVec::new(1.0,0.0,0.0)) - ThreeVec::new(-1.0,0.0,0.0)).r() < 1e-15);
+    /// ```
+    pub fn from_axis_angle(axis: ThreeVec, angle: f64) -> Quaternion {
+        let n = axis.unit();
+        if n.r() == 0.0 {
+            return Quaternion::identity();
+        }
+        let (s, c) = (0.5*angle).sin_cos();
+        Quaternion::from_parts(c,n*s)
+    }
+
+    /// Returns the unit axis and angle in [0,π] of the rotation, see `ThreeMat::to_axis_angle`
+    ///
+    /// The identity has the zero axis.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::from_axis_angle(ThreeVec::new(0.0,2.0,0.0),-1.0);
+    /// let (axis, angle) = quat.to_axis_angle();
+    /// assert_eq!(axis,ThreeVec::new(0.0,-1.0,0.0));
+    /// assert!((angle - 1.0).abs() < 1e-15);
+    /// ```
+    pub fn to_axis_angle(&self) -> (ThreeVec, f64) {
+        let q = if self.w < 0.0 { -*self } else { *self };
+        let v = q.vector();
+        (v.unit(), 2.0*v.r().atan2(q.w))
+    }
+
+    /// Returns the unit Quaternion, with `w >= 0`, of a rotation matrix
+    ///
+    /// The matrix is assumed to be a rotation, see `ThreeMat::is_rotation`.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// use calcify::Quaternion;
+    /// let rot = ThreeMat::from_axis_angle(ThreeVec::new(1.0,2.0,3.0),2.0);
+    /// let quat = Quaternion::from_rotation(rot);
+    /// let vec3 = ThreeVec::new(-1.0,0.5,2.0);
+    /// assert!((quat.rotate(vec3) - rot*vec3).r() < 1e-14);
+    /// ```
+    pub fn from_rotation(mat: ThreeMat) -> Quaternion {
+        let m = mat.rows();
+        let trace = mat.trace();
+        // Divide by the largest of 4w², 4x², 4y² and 4z², Shepperd's method
+        let q = if trace > 0.0 {
+            let s = 2.0*(1.0 + trace).sqrt();
+            Quaternion::new(0.25*s, (m[2][1] - m[1][2])/s, (m[0][2] - m[2][0])/s, (m[1][0] - m[0][1])/s)
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = 2.0*(1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt();
+            Quaternion::new((m[2][1] - m[1][2])/s, 0.25*s, (m[0][1] + m[1][0])/s, (m[0][2] + m[2][0])/s)
+        } else if m[1][1] > m[2][2] {
+            let s = 2.0*(1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt();
+            Quaternion::new((m[0][2] - m[2][0])/s, (m[0][1] + m[1][0])/s, 0.25*s, (m[1][2] + m[2][1])/s)
+        } else {
+            let s = 2.0*(1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt();
+            Quaternion::new((m[1][0] - m[0][1])/s, (m[0][2] + m[2][0])/s, (m[1][2] + m[2][1])/s, 0.25*s)
+        };
+        if q.w < 0.0 {
+            -q.normalize()
+        } else {
+            q.normalize()
+        }
+    }
+
+    /// Returns the rotation matrix of the Quaternion
+    ///
+    /// The Quaternion need not be a unit Quaternion. The zero Quaternion gives the identity.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::from_axis_angle(ThreeVec::new(1.0,2.0,3.0),2.0);
+    /// let rot = ThreeMat::from_axis_angle(ThreeVec::new(1.0,2.0,3.0),2.0);
+    /// let diff = quat.to_rotation() - rot;
+    /// assert!(diff.r0().r() + diff.r1().r() + diff.r2().r() < 1e-14);
+    /// ```
+    pub fn to_rotation(&self) -> ThreeMat {
+        let n = self.dot(*self);
+        if n == 0.0 {
+            return ThreeMat::eye();
+        }
+        let s = 2.0/n;
+        let (w, x, y, z) = (self.w, self.x, self.y, self.z);
+        ThreeMat::new(ThreeVec::new(1.0 - s*(y*y + z*z), s*(x*y - z*w), s*(x*z + y*w)),
+                    ThreeVec::new(s*(x*y + z*w), 1.0 - s*(x*x + z*z), s*(y*z - x*w)),
+                    ThreeVec::new(s*(x*z - y*w), s*(y*z + x*w), 1.0 - s*(x*x + y*y)))
+    }
+
+    /// Rotates a ThreeVec, also available as `quat*vec3`
+    ///
+    /// The Quaternion is normalized first. The zero Quaternion leaves the vector unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let quat = Quaternion::from_axis_angle(ThreeVec::new(1.0,0.0,0.0),PI/2.0);
+    /// let vec3 = ThreeVec::new(0.0,1.0,0.0);
+    /// assert!((quat.rotate(vec3) - ThreeVec::new(0.0,0.0,1.0)).r() < 1e-15);
+    /// assert_eq!(quat*vec3,quat.rotate(vec3));
+    /// ```
+    pub fn rotate(&self, vec: ThreeVec) -> ThreeVec {
+        let q = self.normalize();
+        let u = q.vector();
+        let t = (u^vec)*2.0;
+        vec + t*q.w + (u^t)
+    }
+
+    /// Spherical linear interpolation between two rotations, by `t` in [0,1]
+    ///
+    /// Both are normalized, and the interpolation takes the shorter way round, so the result may be the negative
+    /// of `other` at `t = 1`. The angle of rotation between the result and `self` grows uniformly with `t`.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::Quaternion;
+    /// let axis = ThreeVec::new(1.0,1.0,0.0);
+    /// let start = Quaternion::identity();
+    /// let end = Quaternion::from_axis_angle(axis,2.0);
+    /// let half = start.slerp(end,0.5);
+    /// assert!((half - Quaternion::from_axis_angle(axis,1.0)).norm() < 1e-15);
+    /// ```
+    pub fn slerp(&self, other: Quaternion, t: f64) -> Quaternion {
+        let a = self.normalize();
+        let mut b = other.normalize();
+        if a.dot(b) < 0.0 {
+            b = -b;
+        }
+        // Half the angle of rotation between them, accurate for small angles too
+        let theta = 2.0*(a - b).norm().atan2((a + b).norm());
+        if theta < 1e-6 {
+            return (a*(1.0 - t) + b*t).normalize();
+        }
+        (a*((1.0 - t)*theta).sin() + b*(t*theta).sin())*(1.0/theta.sin())
+    }
+}
+
+impl fmt::Display for Quaternion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:.*}, {:.*}, {:.*}, {:.*}]", 5, self.w(), 5, self.x(), 5, self.y(), 5, self.z())
+    }
+}
+
+impl Serializable for Quaternion {
+    fn to_json(&self) -> String {
+        format!("{{\"w\":{},\"x\":{},\"y\":{},\"z\":{}}}", self.w(), self.x(), self.y(), self.z())
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut buf = Vec::with_capacity(5);
+        write_array_len(&mut buf, 4)?;
+        write_f64(&mut buf, *self.w())?;
+        write_f64(&mut buf, *self.x())?;
+        write_f64(&mut buf, *self.y())?;
+        write_f64(&mut buf, *self.z())?;
+        Ok(buf)
+    }
+}
+
+impl Deserializable for Quaternion {
+
+    fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let w = value.get("w")?.as_f64()?;
+        let x = value.get("x")?.as_f64()?;
+        let y = value.get("y")?.as_f64()?;
+        let z = value.get("z")?.as_f64()?;
+        Ok(Quaternion{w,x,y,z})
+    }
+
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        if let Ok(4) = read_array_len(&mut bytes){
+            let w = read_f64(&mut bytes)?;
+            let x = read_f64(&mut bytes)?;
+            let y = read_f64(&mut bytes)?;
+            let z = read_f64(&mut bytes)?;
+            Ok((Quaternion{w,x,y,z},bytes))
+        } else {
+            Err(Box::new(CalcifyError::parse("Quaternion")))
+        }
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w + other.w,
+            x: self.x + other.x,
+            y: self.y + other.y,
+            z: self.z + other.z,
+        }
+    }
+}
+
+impl AddAssign for Quaternion {
+    fn add_assign(&mut self, other: Quaternion) {
+        self.w += other.w;
+        self.x += other.x;
+        self.y += other.y;
+        self.z += other.z;
+    }
+}
+
+impl Sub for Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w - other.w,
+            x: self.x - other.x,
+            y: self.y - other.y,
+            z: self.z - other.z,
+        }
+    }
+}
+
+impl SubAssign for Quaternion {
+    fn sub_assign(&mut self, other: Quaternion) {
+        self.w -= other.w;
+        self.x -= other.x;
+        self.y -= other.y;
+        self.z -= other.z;
+    }
+}
+
+impl Mul<f64> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, coef: f64) -> Quaternion {
+        Quaternion {
+            w: self.w * coef,
+            x: self.x * coef,
+            y: self.y * coef,
+            z: self.z * coef,
+        }
+    }
+}
+
+impl Mul<Quaternion> for f64 {
+    type Output = Quaternion;
+
+    fn mul(self, quat: Quaternion) -> Quaternion {
+        quat*self
+    }
+}
+
+impl Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+    /// Hamilton product
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use calcify::Quaternion;
+    /// let i = Quaternion::new(0.0,1.0,0.0,0.0);
+    /// let j = Quaternion::new(0.0,0.0,1.0,0.0);
+    ///
+    /// assert_eq!(
+    ///    i*j,
+    ///    Quaternion::new(0.0,0.0,0.0,1.0)
+    /// );
+    /// assert_eq!(i*i,Quaternion::new(-1.0,0.0,0.0,0.0));
+    /// ```
+    fn mul(self, other: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w*other.w - self.x*other.x - self.y*other.y - self.z*other.z,
+            x: self.w*other.x + self.x*other.w + self.y*other.z - self.z*other.y,
+            y: self.w*other.y - self.x*other.z + self.y*other.w + self.z*other.x,
+            z: self.w*other.z + self.x*other.y - self.y*other.x + self.z*other.w,
+        }
+    }
+}
+
+impl Mul<ThreeVec> for Quaternion {
+    type Output = ThreeVec;
+    /// Rotation of a ThreeVec, see `Quaternion::rotate`
+    fn mul(self, vec: ThreeVec) -> ThreeVec {
+        self.rotate(vec)
+    }
+}
+
+impl Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Quaternion {
+        Quaternion {
+            w: -self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Quaternion;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        w: f64,
+        x: f64,
+        y: f64,
+        z: f64,
+    }
+
+    impl Serialize for Quaternion {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{w: self.w, x: self.x, y: self.y, z: self.z}.serialize(serializer)
+            } else {
+                (self.w, self.x, self.y, self.z).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Quaternion {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                Ok(Quaternion::new(f.w, f.x, f.y, f.z))
+            } else {
+                let (w, x, y, z) = <(f64,f64,f64,f64)>::deserialize(deserializer)?;
+                Ok(Quaternion::new(w, x, y, z))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::{Collection, FeedTree};
+
+    fn close(a: ThreeMat, b: ThreeMat) -> bool {
+        let diff = a - b;
+        diff.r0().r() + diff.r1().r() + diff.r2().r() < 1e-12
+    }
+
+    #[test]
+    fn test_product() {
+        let _test_quat1 = Quaternion::new(1.0,2.0,3.0,4.0);
+        let _test_quat2 = Quaternion::new(-0.5,1.0,0.0,2.0);
+
+        assert_eq!(_test_quat1*_test_quat2,Quaternion::new(-10.5,6.0,-1.5,-3.0));
+        assert_eq!((_test_quat1*_test_quat2).conjugate(),_test_quat2.conjugate()*_test_quat1.conjugate());
+        assert_eq!((_test_quat1*_test_quat2).norm(),_test_quat1.norm()*_test_quat2.norm());
+        assert_eq!(_test_quat1*Quaternion::identity(),_test_quat1);
+        assert_eq!(2.0*_test_quat1 - _test_quat1,_test_quat1);
+        assert!((_test_quat1.normalize().norm() - 1.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let _test_axis = ThreeVec::new(-1.0,0.5,2.0);
+        let _test_vec = ThreeVec::new(3.0,-1.0,0.25);
+        for &angle in [0.0, 1e-8, 1.0, 3.0, std::f64::consts::PI].iter() {
+            let _test_quat = Quaternion::from_axis_angle(_test_axis,angle);
+            let _test_mat = ThreeMat::from_axis_angle(_test_axis,angle);
+            assert!(close(_test_quat.to_rotation(),_test_mat));
+            assert!((_test_quat.rotate(_test_vec) - _test_mat*_test_vec).r() < 1e-14);
+            assert!((_test_quat.rotate(_test_vec) - _test_vec.rotate(_test_axis,angle)).r() < 1e-14);
+
+            let _test_back = Quaternion::from_rotation(_test_mat);
+            assert!((_test_back - _test_quat).norm() < 1e-14 || (_test_back + _test_quat).norm() < 1e-14);
+            let (axis, back) = _test_quat.to_axis_angle();
+            assert!((back - angle).abs() < 1e-14);
+            if angle > 0.0 {
+                assert!((axis - _test_axis.unit()).r() < 1e-14);
+            }
+        }
+        // Composition matches the matrix product
+        let _test_quat1 = Quaternion::from_axis_angle(_test_axis,0.7);
+        let _test_quat2 = Quaternion::from_axis_angle(_test_vec,-2.2);
+        assert!(close((_test_quat1*_test_quat2).to_rotation(),_test_quat1.to_rotation()*_test_quat2.to_rotation()));
+        assert!((_test_quat1.conjugate().rotate(_test_quat1*_test_vec) - _test_vec).r() < 1e-14);
+        assert_eq!((-_test_quat1).to_rotation(),_test_quat1.to_rotation());
+        assert_eq!(Quaternion::from_rotation(ThreeMat::eye()),Quaternion::identity());
+    }
+
+    #[test]
+    fn test_extreme_axis() {
+        for scale in [1e200, 1e-200].iter() {
+            let _test_axis = ThreeVec::new(0.0,0.0,*scale);
+            let _test_quat = Quaternion::from_axis_angle(_test_axis,std::f64::consts::FRAC_PI_2);
+            let _test_mat = ThreeMat::from_axis_angle(_test_axis,std::f64::consts::FRAC_PI_2);
+            assert!((_test_quat.rotate(ThreeVec::new(1.0,0.0,0.0)) - ThreeVec::new(0.0,1.0,0.0)).r() < 1e-15);
+            assert!((_test_mat*ThreeVec::new(1.0,0.0,0.0) - ThreeVec::new(0.0,1.0,0.0)).r() < 1e-15);
+            assert!(((Quaternion::new(1.0,1.0,1.0,1.0)*(*scale)).normalize().norm() - 1.0).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn test_slerp() {
+        let _test_axis = ThreeVec::new(0.0,1.0,1.0);
+        let _test_start = Quaternion::from_axis_angle(ThreeVec::new(1.0,0.0,0.0),0.3);
+        let _test_end = _test_start*Quaternion::from_axis_angle(_test_axis,2.4);
+        for &t in [0.0, 0.25, 0.5, 1.0].iter() {
+            let _test_quat = _test_start.slerp(_test_end,t);
+            let expected = _test_start*Quaternion::from_axis_angle(_test_axis,2.4*t);
+            assert!((_test_quat - expected).norm() < 1e-14);
+        }
+        // The shorter way round, and through nearly equal rotations
+        assert!((_test_start.slerp(-_test_end,1.0) - _test_end).norm() < 1e-14);
+        let _test_near = _test_start*Quaternion::from_axis_angle(_test_axis,1e-9);
+        assert!((_test_start.slerp(_test_near,0.5).norm() - 1.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_quat_parse() -> Result<(),Box<dyn error::Error>>{
+        let _test_quat = Quaternion::new(0.5,-1.0,2.0,-0.25);
+        assert_eq!(Quaternion::from_json(&_test_quat.to_json())?,_test_quat);
+        assert_eq!(Quaternion::from_msg(&_test_quat.to_msg()?)?.0,_test_quat);
+        assert!(Quaternion::from_msg(&ThreeVec::new(1.0,2.0,3.0).to_msg()?).is_err());
+
+        let mut ftree = FeedTree::new("Orientations","Quaternion");
+        ftree.add_feed("body", Collection::from(vec![Quaternion::identity(),_test_quat]))?;
+        let ftree2: FeedTree<Quaternion> = FeedTree::from_msg(&ftree.to_msg()?)?.0;
+        assert_eq!(ftree2,ftree);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(),Box<dyn error::Error>>{
+        let quat = Quaternion::new(1.0,-2.5,3.0,0.5);
+        assert_eq!(rmp_serde::to_vec(&quat)?,quat.to_msg()?);
+        assert_eq!(rmp_serde::from_slice::<Quaternion>(&quat.to_msg()?)?,quat);
+        assert_eq!(Quaternion::from_json(&serde_json::to_string(&quat)?)?,quat);
+        assert_eq!(serde_json::from_str::<Quaternion>(&quat.to_json())?,quat);
+        Ok(())
+    }
+}
@@ -0,0 +1,337 @@
+use std::f64::consts::PI;
+
+use super::ThreeMat;
+use super::ThreeVec;
+
+/// Below this cosine of the middle Euler angle (sine for proper Euler angles) the first and third axes line up
+const GIMBAL_LOCK: f64 = 1e-12;
+
+/// Axis order of a sequence of three Euler angle rotations
+///
+/// The rotations are intrinsic, about the axes of the rotated frame, so `ZYX` is yaw, pitch then roll and
+/// `ThreeMat::from_euler(EulerOrder::ZYX,a,b,c) = Rz(a)*Ry(b)*Rx(c)`. The same matrix is the extrinsic
+/// sequence, about the fixed axes, in the reverse order: roll about x, pitch about y, then yaw about z.
+///
+/// The first six are Tait-Bryan angles, about three different axes, the last six are proper Euler angles.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EulerOrder {
+    XYZ,
+    XZY,
+    YXZ,
+    YZX,
+    ZXY,
+    ZYX,
+    XYX,
+    XZX,
+    YXY,
+    YZY,
+    ZXZ,
+    ZYZ,
+}
+
+impl EulerOrder {
+    /// Indices of the three axes, in order
+    fn axes(self) -> [usize; 3] {
+        match self {
+            EulerOrder::XYZ => [0,1,2],
+            EulerOrder::XZY => [0,2,1],
+            EulerOrder::YXZ => [1,0,2],
+            EulerOrder::YZX => [1,2,0],
+            EulerOrder::ZXY => [2,0,1],
+            EulerOrder::ZYX => [2,1,0],
+            EulerOrder::XYX => [0,1,0],
+            EulerOrder::XZX => [0,2,0],
+            EulerOrder::YXY => [1,0,1],
+            EulerOrder::YZY => [1,2,1],
+            EulerOrder::ZXZ => [2,0,2],
+            EulerOrder::ZYZ => [2,1,2],
+        }
+    }
+}
+
+/// 1 if axis j follows axis i cyclically, so e_i^e_j is the third axis, and -1 otherwise
+fn parity(i: usize, j: usize) -> f64 {
+    if j == (i + 1) % 3 { 1.0 } else { -1.0 }
+}
+
+/// Rotation by angle about the basis axis with index i
+fn basis_rotation(i: usize, angle: f64) -> ThreeMat {
+    let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+    let (s, c) = angle.sin_cos();
+    let mut rows = ThreeMat::eye().rows();
+    rows[j][j] = c;
+    rows[j][k] = -s;
+    rows[k][j] = s;
+    rows[k][k] = c;
+    ThreeMat::from_rows(rows)
+}
+
+/// The matrix of the cross product with v, so `cross_matrix(v)*u = v^u`
+fn cross_matrix(v: ThreeVec) -> ThreeMat {
+    ThreeMat::new(ThreeVec::new(0.0,-*v.x2(),*v.x1()),
+                ThreeVec::new(*v.x2(),0.0,-*v.x0()),
+                ThreeVec::new(-*v.x1(),*v.x0(),0.0))
+}
+
+/// Rotation matrices
+///
+/// Rotations are active and right handed: a positive angle turns a vector counterclockwise when looking down
+/// the axis towards the origin, and `mat3*vec3` is the rotated vector.
+impl ThreeMat {
+    /// Returns the rotation by `angle` radians about `axis`
+    ///
+    /// The axis need not be a unit vector. The zero axis gives the identity.
+    ///
+    /// # Example
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let rot = ThreeMat::from_axis_angle(ThreeVec::new(0.0,0.0,2.0),PI/2.0);
+    /// assert!((rot*ThreeVec::new(1.0,0.0,0.0) - ThreeVec::new(0.0,1.0,0.0)).r() < 1e-15);
+    /// ```
+    pub fn from_axis_angle(axis: ThreeVec, angle: f64) -> ThreeMat {
+        let n = axis.unit();
+        if n.r() == 0.0 {
+            return ThreeMat::eye();
+        }
+        let (s, c) = angle.sin_cos();
+        ThreeMat::eye()*c + cross_matrix(n)*s + n.outer(n)*(1.0 - c)
+    }
+
+    /// Returns the unit axis and angle in [0,π] of a rotation matrix
+    ///
+    /// The identity has the zero axis. The matrix is assumed to be a rotation, see `is_rotation`.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let rot = ThreeMat::from_axis_angle(ThreeVec::new(1.0,1.0,0.0),-2.0);
+    /// let (axis, angle) = rot.to_axis_angle();
+    /// assert!((axis - ThreeVec::new(-1.0,-1.0,0.0).unit()).r() < 1e-15);
+    /// assert!((angle - 2.0).abs() < 1e-15);
+    /// ```
+    pub fn to_axis_angle(&self) -> (ThreeVec, f64) {
+        let rows = self.rows();
+        // Twice the axis times the sine
+        let anti = ThreeVec::new(rows[2][1] - rows[1][2], rows[0][2] - rows[2][0], rows[1][0] - rows[0][1]);
+        let cos = 0.5*(self.trace() - 1.0);
+        let angle = (0.5*anti.r()).atan2(cos);
+        if cos > 0.0 {
+            return (anti.unit(), angle);
+        }
+        // Near π the sine vanishes, but the symmetric part is (1 - cos)*axis⊗axis
+        let sym = ((*self + self.transpose())*0.5 - ThreeMat::eye()*cos).rows();
+        let i = (0..3).fold(0, |i, j| if sym[j][j] > sym[i][i] { j } else { i });
+        let axis = ThreeVec::from(&sym[i]).unit();
+        if axis*anti < 0.0 {
+            (-axis, angle)
+        } else {
+            (axis, angle)
+        }
+    }
+
+    /// Returns the rotation by the Euler angles `a`, `b` and `c`, in radians, about the axes in `order`
+    ///
+    /// # Example
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// use calcify::EulerOrder;
+    /// // Yaw a quarter turn left, then pitch the nose down a quarter turn
+    /// let rot = ThreeMat::from_euler(EulerOrder::ZYX,PI/2.0,PI/2.0,0.0);
+    /// assert!((rot*ThreeVec::new(1.0,0.0,0.0) - ThreeVec::new(0.0,0.0,-1.0)).r() < 1e-15);
+    /// ```
+    pub fn from_euler(order: EulerOrder, a: f64, b: f64, c: f64) -> ThreeMat {
+        let [i, j, k] = order.axes();
+        basis_rotation(i,a)*basis_rotation(j,b)*basis_rotation(k,c)
+    }
+
+    /// Returns the Euler angles `(a,b,c)` of a rotation matrix about the axes in `order`, so that
+    /// `ThreeMat::from_euler(order,a,b,c)` is the matrix
+    ///
+    /// `a` and `c` are in [-π,π]. `b` is in [-π/2,π/2] for Tait-Bryan angles and [0,π] for proper Euler angles.
+    /// At gimbal lock, where the first and third axes line up and only `a + c` or `a - c` is defined, `c` is 0.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeMat;
+    /// use calcify::EulerOrder;
+    /// let rot = ThreeMat::from_euler(EulerOrder::ZXZ,0.5,1.0,-2.0);
+    /// let (a, b, c) = rot.to_euler(EulerOrder::ZXZ);
+    /// assert!((a - 0.5).abs() < 1e-14 && (b - 1.0).abs() < 1e-14 && (c + 2.0).abs() < 1e-14);
+    /// ```
+    pub fn to_euler(&self, order: EulerOrder) -> (f64, f64, f64) {
+        let rows = self.rows();
+        let [i, j, k] = order.axes();
+        let s = parity(i,j);
+        if i != k {
+            let cos_b = rows[i][i].hypot(rows[i][j]);
+            let b = (s*rows[i][k]).atan2(cos_b);
+            if cos_b > GIMBAL_LOCK {
+                ((-s*rows[j][k]).atan2(rows[k][k]), b, (-s*rows[i][j]).atan2(rows[i][i]))
+            } else {
+                ((s*rows[k][j]).atan2(rows[j][j]), b, 0.0)
+            }
+        } else {
+            let m = 3 - i - j;
+            let sin_b = rows[i][j].hypot(rows[i][m]);
+            let b = sin_b.atan2(rows[i][i]);
+            if sin_b > GIMBAL_LOCK {
+                (rows[j][i].atan2(-s*rows[m][i]), b, rows[i][j].atan2(s*rows[i][m]))
+            } else {
+                let cos_b = rows[i][i].signum();
+                ((-s*cos_b*rows[j][m]).atan2(cos_b*rows[m][m]), b, 0.0)
+            }
+        }
+    }
+
+    /// Returns the smallest rotation turning the direction of `from` onto the direction of `to`
+    ///
+    /// Opposite vectors are turned by π about an axis perpendicular to `from`. If either vector is zero this is
+    /// the identity.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let from = ThreeVec::new(1.0,2.0,3.0);
+    /// let to = ThreeVec::new(-2.0,0.0,1.0);
+    /// let rot = ThreeMat::rotation_between(from,to);
+    /// assert!((rot*from.unit() - to.unit()).r() < 1e-15);
+    /// assert!(rot.is_rotation(1e-15));
+    /// ```
+    pub fn rotation_between(from: ThreeVec, to: ThreeVec) -> ThreeMat {
+        let (u, v) = (from.unit(), to.unit());
+        if u.r() == 0.0 || v.r() == 0.0 {
+            return ThreeMat::eye();
+        }
+        let axis = u^v;
+        let (sin, cos) = (axis.r(), u*v);
+        if cos < 0.0 && sin <= f64::EPSILON.sqrt() {
+            // The cross product is too small to give the axis, so turn u onto -u about a perpendicular axis,
+            // from the basis vector least along u, then the short way onto v
+            let x = [u.x0().abs(), u.x1().abs(), u.x2().abs()];
+            let k = (0..3).fold(0, |k, j| if x[j] < x[k] { j } else { k });
+            let perp = u^ThreeVec::from(&ThreeMat::eye().rows()[k]);
+            return ThreeMat::rotation_between(-u,v)*ThreeMat::from_axis_angle(perp,PI);
+        }
+        ThreeMat::from_axis_angle(axis,sin.atan2(cos))
+    }
+
+    /// Returns true if `mat3*mat3.transpose()` is the identity, to within `tol` in each element
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let reflection = ThreeMat::new(ThreeVec::new(1.0,0.0,0.0),
+    ///                              ThreeVec::new(0.0,0.0,1.0),
+    ///                              ThreeVec::new(0.0,1.0,0.0));
+    /// assert!(reflection.is_orthonormal(0.0));
+    /// assert!(!(reflection*2.0).is_orthonormal(1e-12));
+    /// ```
+    pub fn is_orthonormal(&self, tol: f64) -> bool {
+        let diff = (*self*self.transpose() - ThreeMat::eye()).rows();
+        diff.iter().flatten().all(|x| x.abs() <= tol)
+    }
+
+    /// Returns true if the matrix is orthonormal to within `tol`, see `is_orthonormal`, and is not a reflection
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let reflection = ThreeMat::new(ThreeVec::new(1.0,0.0,0.0),
+    ///                              ThreeVec::new(0.0,0.0,1.0),
+    ///                              ThreeVec::new(0.0,1.0,0.0));
+    /// assert!(!reflection.is_rotation(1e-12));
+    /// assert!(ThreeMat::from_axis_angle(ThreeVec::new(1.0,2.0,3.0),1.0).is_rotation(1e-15));
+    /// ```
+    pub fn is_rotation(&self, tol: f64) -> bool {
+        self.is_orthonormal(tol) && self.det() > 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: [EulerOrder; 12] = [EulerOrder::XYZ, EulerOrder::XZY, EulerOrder::YXZ, EulerOrder::YZX,
+                                      EulerOrder::ZXY, EulerOrder::ZYX, EulerOrder::XYX, EulerOrder::XZX,
+                                      EulerOrder::YXY, EulerOrder::YZY, EulerOrder::ZXZ, EulerOrder::ZYZ];
+
+    fn close(a: ThreeMat, b: ThreeMat) -> bool {
+        let diff = a - b;
+        diff.r0().r() + diff.r1().r() + diff.r2().r() < 1e-12
+    }
+
+    #[test]
+    fn test_axis_angle() {
+        let _test_axis = ThreeVec::new(1.0,-2.0,0.5);
+        let _test_vec = ThreeVec::new(0.3,4.0,-1.0);
+        for &angle in [0.0, 1e-9, 0.7, PI/2.0, 2.5, PI - 1e-9, PI].iter() {
+            let _test_mat = ThreeMat::from_axis_angle(_test_axis,angle);
+            assert!(_test_mat.is_rotation(1e-15));
+            assert!((_test_mat*_test_axis - _test_axis).r() < 1e-14);
+            assert!((_test_mat*_test_vec - _test_vec.rotate(_test_axis,angle)).r() < 1e-14);
+            let (axis, back) = _test_mat.to_axis_angle();
+            assert!((back - angle).abs() < 1e-12);
+            if angle > 0.0 {
+                assert!((axis - _test_axis.unit()).r() < 1e-6);
+                assert!(close(ThreeMat::from_axis_angle(axis,back),_test_mat));
+            } else {
+                assert_eq!(axis,ThreeVec::new(0.0,0.0,0.0));
+            }
+        }
+        // Negative angles come back about the opposite axis
+        let (axis, angle) = ThreeMat::from_axis_angle(_test_axis,-1.0).to_axis_angle();
+        assert!((axis + _test_axis.unit()).r() < 1e-15);
+        assert!((angle - 1.0).abs() < 1e-15);
+        assert_eq!(ThreeMat::from_axis_angle(ThreeVec::new(0.0,0.0,0.0),1.0),ThreeMat::eye());
+        assert_eq!(_test_vec.rotate(ThreeVec::new(0.0,0.0,0.0),1.0),_test_vec);
+    }
+
+    #[test]
+    fn test_euler() {
+        for &order in ORDERS.iter() {
+            let [i, j, k] = order.axes();
+            let b = if i == k { 1.2 } else { -0.4 };
+            let _test_mat = ThreeMat::from_euler(order,2.0,b,-0.9);
+            assert!(_test_mat.is_rotation(1e-15));
+            let basis = ThreeMat::eye().rows();
+            assert!(close(_test_mat,ThreeMat::from_axis_angle(ThreeVec::from(&basis[i]),2.0)
+                                    *ThreeMat::from_axis_angle(ThreeVec::from(&basis[j]),b)
+                                    *ThreeMat::from_axis_angle(ThreeVec::from(&basis[k]),-0.9)));
+            let (a2, b2, c2) = _test_mat.to_euler(order);
+            assert!((a2 - 2.0).abs() < 1e-12 && (b2 - b).abs() < 1e-12 && (c2 + 0.9).abs() < 1e-12, "{:?}", order);
+
+            // Gimbal lock, only the combination of a and c is kept
+            let lock = if i == k { PI } else { PI/2.0 };
+            let _test_lock = ThreeMat::from_euler(order,0.3,lock,0.5);
+            let (a3, b3, c3) = _test_lock.to_euler(order);
+            assert_eq!(c3,0.0);
+            assert!((b3 - lock).abs() < 1e-7, "{:?}", order);
+            assert!(close(ThreeMat::from_euler(order,a3,b3,c3),_test_lock), "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_rotation_between() {
+        let _test_from = ThreeVec::new(0.0,3.0,4.0);
+        for _test_to in [ThreeVec::new(1.0,1.0,1.0), _test_from*2.0, -_test_from,
+                         -_test_from + ThreeVec::new(1e-10,0.0,0.0), ThreeVec::new(0.0,0.0,-1.0)].iter() {
+            let _test_mat = ThreeMat::rotation_between(_test_from,*_test_to);
+            assert!(_test_mat.is_rotation(1e-14));
+            assert!((_test_mat*_test_from.unit() - _test_to.unit()).r() < 1e-14);
+            // The axis is perpendicular to both
+            let (axis, _) = _test_mat.to_axis_angle();
+            assert!((axis*_test_from).abs() < 1e-6);
+        }
+        assert_eq!(ThreeMat::rotation_between(_test_from,ThreeVec::new(0.0,0.0,0.0)),ThreeMat::eye());
+        assert!(!ThreeMat::one().is_orthonormal(0.5));
+    }
+}
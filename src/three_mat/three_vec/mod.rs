@@ -8,7 +8,9 @@ use std::ops::Sub;
 use std::ops::SubAssign;
 use std::ops::Mul;
 use std::ops::Neg;
+use std::ops::BitXor;
 use std::iter;
+use std::convert::TryFrom;
 use std::fmt;
 use std::error;
 
@@ -16,9 +18,11 @@ use self::rand::thread_rng;
 use self::rand::distributions::{Distribution, Uniform};
 
 use crate::utils;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
+use super::ThreeMat;
+
 extern crate rmp;
 use rmp::encode::*;
 use rmp::decode::*;
@@ -67,7 +71,7 @@ impl ThreeVec {
     ///
     /// # Panics
     ///
-    /// * `slice` length < 3
+    /// * `slice` length < 3, see `TryFrom`
     pub fn from(slice: &[f64]) -> ThreeVec {
 
         ThreeVec {
@@ -139,6 +143,8 @@ impl ThreeVec {
 
     /// Returns the length of the vector
     ///
+    /// Found with `hypot`, so it doesn't overflow or underflow for very large or small components.
+    ///
     /// # Example
     /// ```
     /// use calcify::ThreeVec;
@@ -146,7 +152,239 @@ impl ThreeVec {
     /// assert_eq!(vec3.r(),1.0);
     /// ```
     pub fn r(&self) -> f64 {
-        (*self**self).sqrt()
+        self.x0.hypot(self.x1).hypot(self.x2)
+    }
+
+    /// Returns the vector scaled to length one
+    ///
+    /// The zero vector has no direction, and is returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let vec3 = ThreeVec::new(3.0,0.0,4.0);
+    /// assert_eq!(vec3.unit(),ThreeVec::new(0.6,0.0,0.8));
+    /// assert_eq!(ThreeVec::new(0.0,0.0,0.0).unit(),ThreeVec::new(0.0,0.0,0.0));
+    /// ```
+    pub fn unit(&self) -> ThreeVec {
+        let r = self.r();
+        if r == 0.0 {
+            *self
+        } else {
+            ThreeVec {
+                x0: self.x0 / r,
+                x1: self.x1 / r,
+                x2: self.x2 / r,
+            }
+        }
+    }
+
+    /// Cross product, also available as the `^` operator
+    ///
+    /// Note that `^` binds looser than `+`, `-` and `*`, so `a^b*c` is `a^(b*c)`.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let x = ThreeVec::new(1.0,0.0,0.0);
+    /// let y = ThreeVec::new(0.0,1.0,0.0);
+    /// assert_eq!(x.cross(y),ThreeVec::new(0.0,0.0,1.0));
+    /// assert_eq!(y^x,ThreeVec::new(0.0,0.0,-1.0));
+    /// ```
+    pub fn cross(&self, other: ThreeVec) -> ThreeVec {
+        ThreeVec {
+            x0: self.x1 * *other.x2() - self.x2 * *other.x1(),
+            x1: self.x2 * *other.x0() - self.x0 * *other.x2(),
+            x2: self.x0 * *other.x1() - self.x1 * *other.x0(),
+        }
+    }
+
+    /// Scalar triple product, `self*(b^c)`
+    ///
+    /// The signed volume of the parallelepiped spanned by the three vectors.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let a = ThreeVec::new(2.0,0.0,0.0);
+    /// let b = ThreeVec::new(0.0,3.0,0.0);
+    /// let c = ThreeVec::new(1.0,1.0,4.0);
+    /// assert_eq!(a.triple(b,c),24.0);
+    /// assert_eq!(b.triple(a,c),-24.0);
+    /// ```
+    pub fn triple(&self, b: ThreeVec, c: ThreeVec) -> f64 {
+        *self*b.cross(c)
+    }
+
+    /// Vector triple product, `self^(b^c)`
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let a = ThreeVec::new(1.0,2.0,3.0);
+    /// let b = ThreeVec::new(0.0,1.0,0.0);
+    /// let c = ThreeVec::new(0.0,0.0,1.0);
+    /// // b*(a*c) - c*(a*b)
+    /// assert_eq!(a.vector_triple(b,c),ThreeVec::new(0.0,3.0,-2.0));
+    /// ```
+    pub fn vector_triple(&self, b: ThreeVec, c: ThreeVec) -> ThreeVec {
+        self.cross(b.cross(c))
+    }
+
+    /// Returns the component of the vector along `other`
+    ///
+    /// Projecting onto the zero vector gives the zero vector.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let vec3 = ThreeVec::new(1.0,2.0,3.0);
+    /// let axis = ThreeVec::new(0.0,0.0,2.0);
+    /// assert_eq!(vec3.project_on(axis),ThreeVec::new(0.0,0.0,3.0));
+    /// ```
+    pub fn project_on(&self, other: ThreeVec) -> ThreeVec {
+        let norm = other*other;
+        if norm == 0.0 {
+            other
+        } else {
+            other*((*self*other)/norm)
+        }
+    }
+
+    /// Returns the component of the vector perpendicular to `other`
+    ///
+    /// `v.project_on(n) + v.reject_from(n) == v`
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let vec3 = ThreeVec::new(1.0,2.0,3.0);
+    /// let axis = ThreeVec::new(0.0,0.0,2.0);
+    /// assert_eq!(vec3.reject_from(axis),ThreeVec::new(1.0,2.0,0.0));
+    /// ```
+    pub fn reject_from(&self, other: ThreeVec) -> ThreeVec {
+        *self - self.project_on(other)
+    }
+
+    /// Reflects the vector in the plane with normal `normal`
+    ///
+    /// The normal need not be a unit vector.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let vec3 = ThreeVec::new(1.0,2.0,3.0);
+    /// let normal = ThreeVec::new(0.0,0.0,5.0);
+    /// assert_eq!(vec3.reflect(normal),ThreeVec::new(1.0,2.0,-3.0));
+    /// ```
+    pub fn reflect(&self, normal: ThreeVec) -> ThreeVec {
+        *self - 2.0*self.project_on(normal)
+    }
+
+    /// Rotates the vector by `angle` radians about `axis`, counterclockwise looking down the axis
+    ///
+    /// The axis need not be a unit vector, and the zero axis leaves the vector unchanged. This is
+    /// `ThreeMat::from_axis_angle(axis,angle)*vec3`, without building the matrix.
+    ///
+    /// # Example
+    /// ```
+    /// use std::f64::consts::PI;
+    /// use calcify::ThreeVec;
+    /// let vec3 = ThreeVec::new(1.0,0.0,5.0);
+    /// let rotated = vec3.rotate(ThreeVec::new(0.0,0.0,1.0),PI/2.0);
+    /// assert!((rotated - ThreeVec::new(0.0,1.0,5.0)).r() < 1e-15);
+    /// ```
+    pub fn rotate(&self, axis: ThreeVec, angle: f64) -> ThreeVec {
+        let n = axis.unit();
+        if n.r() == 0.0 {
+            return *self;
+        }
+        let (s, c) = angle.sin_cos();
+        *self*c + n.cross(*self)*s + n*((n**self)*(1.0 - c))
+    }
+
+    /// Outer product, the ThreeMat with elements `self[i]*other[j]`
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// use calcify::ThreeMat;
+    /// let a = ThreeVec::new(1.0,2.0,3.0);
+    /// let b = ThreeVec::new(1.0,0.0,-1.0);
+    /// assert_eq!(
+    ///     a.outer(b),
+    ///     ThreeMat::new(ThreeVec::new(1.0,0.0,-1.0),
+    ///                   ThreeVec::new(2.0,0.0,-2.0),
+    ///                   ThreeVec::new(3.0,0.0,-3.0))
+    /// );
+    /// // (a⊗b)*c == a*(b*c)
+    /// let c = ThreeVec::new(4.0,5.0,6.0);
+    /// assert_eq!(a.outer(b)*c,a*(b*c));
+    /// ```
+    pub fn outer(&self, other: ThreeVec) -> ThreeMat {
+        ThreeMat::new(
+            other*self.x0,
+            other*self.x1,
+            other*self.x2,
+        )
+    }
+
+    /// Returns the component-wise minimum of two vectors
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let a = ThreeVec::new(1.0,5.0,-3.0);
+    /// let b = ThreeVec::new(2.0,4.0,-6.0);
+    /// assert_eq!(a.min(b),ThreeVec::new(1.0,4.0,-6.0));
+    /// ```
+    pub fn min(&self, other: ThreeVec) -> ThreeVec {
+        ThreeVec {
+            x0: self.x0.min(*other.x0()),
+            x1: self.x1.min(*other.x1()),
+            x2: self.x2.min(*other.x2()),
+        }
+    }
+
+    /// Returns the component-wise maximum of two vectors
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let a = ThreeVec::new(1.0,5.0,-3.0);
+    /// let b = ThreeVec::new(2.0,4.0,-6.0);
+    /// assert_eq!(a.max(b),ThreeVec::new(2.0,5.0,-3.0));
+    /// ```
+    pub fn max(&self, other: ThreeVec) -> ThreeVec {
+        ThreeVec {
+            x0: self.x0.max(*other.x0()),
+            x1: self.x1.max(*other.x1()),
+            x2: self.x2.max(*other.x2()),
+        }
+    }
+}
+
+/// Returns a new ThreeVec from a slice, without panicking
+///
+/// # Example
+/// ```
+/// use std::convert::TryFrom;
+/// use calcify::ThreeVec;
+/// assert_eq!(ThreeVec::try_from(&[1.0,2.0,3.0][..]).unwrap(),ThreeVec::new(1.0,2.0,3.0));
+/// assert!(ThreeVec::try_from(&[1.0,2.0][..]).is_err());
+/// ```
+///
+/// # Errors
+///
+/// * `CalcifyError::LengthError` if `slice` length < 3
+impl TryFrom<&[f64]> for ThreeVec {
+    type Error = CalcifyError;
+
+    fn try_from(slice: &[f64]) -> Result<ThreeVec,CalcifyError> {
+        match slice {
+            [x0, x1, x2, ..] => Ok(ThreeVec::new(*x0,*x1,*x2)),
+            _ => Err(CalcifyError::LengthError{expected: 3, found: slice.len()}),
+        }
     }
 }
 
@@ -174,18 +412,13 @@ impl Serializable for ThreeVec {
 impl Deserializable for ThreeVec {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut x0: f64 = NAN;
-        let mut x1: f64 = NAN;
-        let mut x2: f64 = NAN;
-        for dim in s.trim_matches(|p| p == '{' || p == '}' ).split(',') {
-            let n_v: Vec<&str> = dim.split(':').collect();
-            match n_v[0] {
-                "\"x0\"" => x0 = n_v[1].parse::<f64>()?,
-                "\"x1\"" => x1 = n_v[1].parse::<f64>()?,
-                "\"x2\"" => x2 = n_v[1].parse::<f64>()?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
-        }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let x0 = value.get("x0")?.as_f64()?;
+        let x1 = value.get("x1")?.as_f64()?;
+        let x2 = value.get("x2")?.as_f64()?;
         Ok(ThreeVec{x0,x1,x2})
     }
 
@@ -197,7 +430,7 @@ impl Deserializable for ThreeVec {
             }
             Ok((ThreeVec::from(&x),bytes))
         } else {
-            Err(Box::new(CalcifyError::ParseError))
+            Err(Box::new(CalcifyError::parse("ThreeVec")))
         }
     }
 }
@@ -293,6 +526,27 @@ impl Mul<ThreeVec> for ThreeVec {
     }
 }
 
+impl BitXor for ThreeVec {
+    type Output = ThreeVec;
+    /// Cross product
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use calcify::ThreeVec;
+    /// let x = ThreeVec::new(1.0,0.0,0.0);
+    /// let y = ThreeVec::new(0.0,1.0,0.0);
+    ///
+    /// assert_eq!(
+    ///    x^y,
+    ///    ThreeVec::new(0.0,0.0,1.0)
+    /// );
+    /// ```
+    fn bitxor(self, other: ThreeVec) -> ThreeVec {
+        self.cross(other)
+    }
+}
+
 impl Neg for ThreeVec {
     type Output = ThreeVec;
 
@@ -347,6 +601,41 @@ pub fn degrees_between(one: ThreeVec, other: ThreeVec) -> f64 {
     (dot/(r1*r2)).acos()*(180.0/f64::consts::PI)
 }
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::ThreeVec;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        x0: f64,
+        x1: f64,
+        x2: f64,
+    }
+
+    impl Serialize for ThreeVec {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{x0: self.x0, x1: self.x1, x2: self.x2}.serialize(serializer)
+            } else {
+                (self.x0, self.x1, self.x2).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for ThreeVec {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                Ok(ThreeVec::new(f.x0, f.x1, f.x2))
+            } else {
+                let (x0, x1, x2) = <(f64,f64,f64)>::deserialize(deserializer)?;
+                Ok(ThreeVec::new(x0, x1, x2))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -434,6 +723,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cross() {
+        let _test_vec1 = ThreeVec::new(1.0,2.0,3.0);
+        let _test_vec2 = ThreeVec::new(-2.0,0.5,4.0);
+        let _test_vec3 = ThreeVec::new(0.0,-1.0,2.0);
+
+        let _test_cross = _test_vec1^_test_vec2;
+        assert_eq!(_test_cross,ThreeVec::new(6.5,-10.0,4.5));
+        assert_eq!(_test_cross*_test_vec1,0.0);
+        assert_eq!(_test_cross*_test_vec2,0.0);
+        assert_eq!(_test_vec2^_test_vec1,-_test_cross);
+        assert_eq!(_test_vec1^_test_vec1,ThreeVec::new(0.0,0.0,0.0));
+
+        assert_eq!(_test_vec1.triple(_test_vec2,_test_vec3),_test_vec3.triple(_test_vec1,_test_vec2));
+        assert_eq!(_test_vec1.triple(_test_vec2,_test_vec3),ThreeMat::new(_test_vec1,_test_vec2,_test_vec3).det());
+        assert_eq!(
+            _test_vec1.vector_triple(_test_vec2,_test_vec3),
+            _test_vec2*(_test_vec1*_test_vec3) - _test_vec3*(_test_vec1*_test_vec2)
+        );
+    }
+
+    #[test]
+    fn test_projection() {
+        let _test_vec = ThreeVec::new(3.0,-4.0,12.0);
+        let _test_axis = ThreeVec::new(1.0,1.0,0.0);
+
+        assert_eq!(_test_vec.unit().r(),1.0);
+        assert_eq!(_test_vec.unit()*13.0,_test_vec);
+
+        let _test_proj = _test_vec.project_on(_test_axis);
+        let _test_rej = _test_vec.reject_from(_test_axis);
+        assert_eq!(_test_proj,ThreeVec::new(-0.5,-0.5,0.0));
+        assert_eq!(_test_rej*_test_axis,0.0);
+        assert_eq!(_test_proj + _test_rej,_test_vec);
+        assert_eq!(_test_vec.reflect(_test_axis),ThreeVec::new(4.0,-3.0,12.0));
+        assert_eq!(_test_vec.reflect(_test_axis).reflect(_test_axis),_test_vec);
+        assert_eq!(_test_vec.project_on(ThreeVec::new(0.0,0.0,0.0)),ThreeVec::new(0.0,0.0,0.0));
+
+        let _test_outer = _test_vec.outer(_test_axis);
+        assert_eq!(_test_outer.trace(),_test_vec*_test_axis);
+        assert_eq!(_test_outer.transpose(),_test_axis.outer(_test_vec));
+    }
+
+    #[test]
+    fn test_extreme_lengths() {
+        for scale in [1e200, 1e-200, 1e300, 1e-310].iter() {
+            let _test_vec = ThreeVec::new(3.0,0.0,4.0)*(*scale);
+            assert!((_test_vec.r()/(*scale) - 5.0).abs() < 1e-12);
+            assert!((_test_vec.unit() - ThreeVec::new(0.6,0.0,0.8)).r() < 1e-12);
+            let _test_rot = ThreeVec::new(1.0,0.0,0.0).rotate(ThreeVec::new(0.0,0.0,1.0)*(*scale),std::f64::consts::PI/2.0);
+            assert!((_test_rot - ThreeVec::new(0.0,1.0,0.0)).r() < 1e-15);
+        }
+    }
+
     #[test]
     fn test_copy() {
         let xx = ThreeVec::new(1.0,1.0,1.0);
@@ -451,6 +794,13 @@ mod tests {
         assert_eq!(ThreeVec::from_json(&pp).unwrap(),xx);
     }
 
+    #[test]
+    fn test_parse_reordered() {
+        let xx = ThreeVec::new(1.0,2.0,3.0);
+        assert_eq!(ThreeVec::from_json("{ \"x2\": 3.0,\n  \"x0\": 1,\n  \"x1\": 2e0 }").unwrap(),xx);
+        assert!(ThreeVec::from_json("{\"x0\":1.0,\"x1\":2.0}").is_err());
+    }
+
     #[test]
     fn test_msg_parse() {
         let xx = ThreeVec::new(1.0,2.0,3.0);
@@ -458,4 +808,15 @@ mod tests {
         let (oo,_) = ThreeVec::from_msg(&pp).unwrap();
         assert_eq!(oo,xx);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(),Box<dyn error::Error>>{
+        let vec = ThreeVec::new(1.0,-2.5,3.0);
+        assert_eq!(rmp_serde::to_vec(&vec)?,vec.to_msg()?);
+        assert_eq!(rmp_serde::from_slice::<ThreeVec>(&vec.to_msg()?)?,vec);
+        assert_eq!(ThreeVec::from_json(&serde_json::to_string(&vec)?)?,vec);
+        assert_eq!(serde_json::from_str::<ThreeVec>(&vec.to_json())?,vec);
+        Ok(())
+    }
 }
@@ -9,7 +9,7 @@ use rmp::decode::*;
 
 use crate::utils;
 
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 /// A histogram is a Collection of Bins
@@ -73,21 +73,15 @@ impl Serializable for Bin {
 impl Deserializable for Bin {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut count: u64 = 0;
-        let mut in_edge: f64 = f64::NAN;
-        let mut ex_edge: f64 = f64::NAN;
-        for (i,dim) in s.replace(":",",").replace("[",",").replace("]",",").trim_matches(|p| p == '{' || p == '}' ).split_terminator(",").enumerate() {
-            match i {
-                0 => (),
-                1 => count = dim.parse::<f64>()? as u64,
-                2 => (),
-                3 => (),
-                4 => in_edge = dim.parse::<f64>()?,
-                5 => ex_edge = dim.parse::<f64>()?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let count = value.get("count")?.as_u64()?;
+        if let [in_edge, ex_edge] = value.get("range")?.as_array()? {
+            return Ok(Bin{count,in_edge:in_edge.as_f64()?,ex_edge:ex_edge.as_f64()?});
         }
-        Ok(Bin{count,in_edge,ex_edge})
+        Err(Box::new(CalcifyError::parse("Bin range")))
     }
 
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
@@ -99,7 +93,41 @@ impl Deserializable for Bin {
                 return Ok((Bin{count,in_edge,ex_edge},bytes));
             }
         }
-        Err(Box::new(CalcifyError::ParseError))
+        Err(Box::new(CalcifyError::parse("Bin")))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Bin;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        count: u64,
+        range: (f64,f64),
+    }
+
+    impl Serialize for Bin {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{count: self.count, range: (self.in_edge, self.ex_edge)}.serialize(serializer)
+            } else {
+                (self.count, (self.in_edge, self.ex_edge)).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bin {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let (count, (in_edge, ex_edge)) = if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                (f.count, f.range)
+            } else {
+                <(u64,(f64,f64))>::deserialize(deserializer)?
+            };
+            Ok(Bin::new(in_edge, ex_edge, count))
+        }
     }
 }
 
@@ -113,6 +141,12 @@ mod tests {
         assert_eq!(Bin::from_json(&pp).unwrap(),xx);
     }
 
+    #[test]
+    fn test_parse_reordered() {
+        let xx = Bin::new(0.5,1.5,42);
+        assert_eq!(Bin::from_json("{ \"range\" : [ 0.5 , 1.5 ] , \"count\" : 42 }").unwrap(),xx);
+    }
+
     #[test]
     fn test_msg_parse() {
         let xx = Bin::new(0.0,1.0,0);
@@ -120,4 +154,15 @@ mod tests {
         let (oo,_) = Bin::from_msg(&pp).unwrap();
         assert_eq!(oo,xx);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() -> Result<(),Box<dyn error::Error>>{
+        let bin = Bin::new(0.0,1.5,7);
+        assert_eq!(rmp_serde::to_vec(&bin)?,bin.to_msg()?);
+        assert_eq!(rmp_serde::from_slice::<Bin>(&bin.to_msg()?)?,bin);
+        assert_eq!(Bin::from_json(&serde_json::to_string(&bin)?)?,bin);
+        assert_eq!(serde_json::from_str::<Bin>(&bin.to_json())?,bin);
+        Ok(())
+    }
 }
@@ -13,7 +13,7 @@ pub use point_bin::PointBin;
 
 use crate::utils;
 
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 extern crate rmp;
@@ -69,6 +69,25 @@ impl<T: Serializable> Collection<T> {
         &mut self.vec[i]
     }
 
+    /// Returns a mutable reference to the T: Serializable at index i, without panicking
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `i` is out of bounds
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Collection;
+    ///
+    /// let mut col = Collection::from(vec![1.0]);
+    /// *col.try_at(0).unwrap() += 1.0;
+    /// assert_eq!(col.vec,vec![2.0]);
+    /// assert!(col.try_at(1).is_err());
+    /// ```
+    pub fn try_at(&mut self, i: usize) -> Result<&mut T,CalcifyError> {
+        self.vec.get_mut(i).ok_or_else(|| CalcifyError::key(&i.to_string()))
+    }
+
     /// Push new T: Serializable into Collection
     ///
     /// # Arguments
@@ -218,38 +237,30 @@ impl<T: Serializable> Serializable for Collection<T> {
 
 impl<T: Serializable + Deserializable> Deserializable for Collection<T> {
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
         let mut out: Self = Collection::empty();
-        let s_iter: String;
-        if s.starts_with("[{") {
-            s_iter = s.replace("},{","}|{");
-        } else {
-            s_iter = s.replace(",","|");
-        }
-        for ff in s_iter.trim_matches(|p| p == '[' || p == ']' ).split('|'){
-            if let Ok(f) = T::from_json(ff) {
-                out.push(f);
-            }
-            else {
-                return Err(Box::new(CalcifyError::ParseError));
-            }
+        for (i,ff) in value.as_array()?.iter().enumerate() {
+            let ot = T::from_json_value(ff).map_err(|e| CalcifyError::parse(&format!("Collection element {}",i)).caused_by_boxed(e))?;
+            out.push(ot);
         }
         Ok(out)
     }
 
-    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+    /// Errors name the element that failed, and its byte offset into `bytes`
+    fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
         let mut out: Self = Collection::empty();
-        if let Ok(len) = read_array_len(&mut bytes){
-            for _ in 0..len {
-                if let Ok((ot,rest)) = T::from_msg(&mut bytes) {
-                    out.push(ot);
-                    bytes = rest;
-                } else {
-                    return Err(Box::new(CalcifyError::ParseError));
-                }
-            }
-            return Ok((out,bytes));
+        let mut rest = bytes;
+        let len = read_array_len(&mut rest).map_err(|e| CalcifyError::parse("Collection").at(0).caused_by(e))?;
+        for i in 0..len {
+            let pos = bytes.len() - rest.len();
+            let (ot,unparsed) = T::from_msg(rest).map_err(|e| CalcifyError::parse(&format!("Collection element {}",i)).at(pos).caused_by_boxed(e))?;
+            out.push(ot);
+            rest = unparsed;
         }
-        Err(Box::new(CalcifyError::ParseError))
+        Ok((out,rest))
     }
 }
 
@@ -362,10 +373,44 @@ impl Collection<Point> {
     ///
     /// # Panics
     ///
-    /// * If either num_bins is less than 2
+    /// * If either num_bins is less than 2, the Collection is empty or holds a NaN or infinite coordinate, see `try_hist`
     ///
     pub fn hist(&self, num_bins_x: u64, num_bins_y: u64) -> Collection<PointBin> {
-        if num_bins_x < 2 || num_bins_y < 2 {panic!("num_bins must be 2 or greater.");}
+        self.try_hist(num_bins_x, num_bins_y).unwrap_or_else(|err| panic!("{}",err))
+    }
+
+    /// Return Collection<PointBin> 2D histogram, without panicking
+    ///
+    /// # Arguments
+    ///
+    /// * `num_bins_x` - Number of bins along the x axis: u64 (>= 2)
+    /// * `num_bins_y` - Number of bins along the y axis: u64 (>= 2)
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::LengthError` if either num_bins is less than 2, or the Collection is empty
+    /// * `CalcifyError::NonFiniteError` if a Point has a NaN or infinite coordinate
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::{Collection, Point};
+    ///
+    /// let points: Collection<Point> = Collection::plot(&[0.0,1.0,2.0],&[3.0,4.0,5.0]);
+    /// assert_eq!(points.try_hist(2,2).unwrap().len(),4);
+    /// assert!(points.try_hist(1,2).is_err());
+    /// assert!(Collection::<Point>::empty().try_hist(2,2).is_err());
+    /// assert!(Collection::plot(&[0.0,f64::NAN],&[0.0,1.0]).try_hist(2,2).is_err());
+    /// ```
+    pub fn try_hist(&self, num_bins_x: u64, num_bins_y: u64) -> Result<Collection<PointBin>,CalcifyError> {
+        if num_bins_x < 2 || num_bins_y < 2 {
+            return Err(CalcifyError::LengthError{expected: 2, found: num_bins_x.min(num_bins_y) as usize});
+        }
+        if self.vec.is_empty() {
+            return Err(CalcifyError::LengthError{expected: 1, found: 0});
+        }
+        if let Some(index) = self.vec.iter().position(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            return Err(CalcifyError::NonFiniteError{index});
+        }
         let mut min_x = self.vec[0].x;
         let mut max_x = self.vec[0].x;
         let mut min_y = self.vec[0].y;
@@ -389,14 +434,14 @@ impl Collection<Point> {
                 outs[i as usize].push(PointBin::new(edg0x,edg1x,edg0y,edg1y,0));
             }
         }
+        // The 0.01 pad on the last bin can be lost to rounding for large values
         for p in self.vec.iter() {
-            let x_bin: usize = ((p.x - min_x)/width_x) as usize;
-            let y_bin: usize = ((p.y - min_y)/width_y) as usize;
+            let x_bin: usize = (((p.x - min_x)/width_x) as usize).min(num_bins_x as usize - 1);
+            let y_bin: usize = (((p.y - min_y)/width_y) as usize).min(num_bins_y as usize - 1);
             outs[x_bin][y_bin] += 1;
         }
         let o_vec: Vec<PointBin> = outs.iter().flatten().cloned().collect();
-        let out: Collection<PointBin> = Collection::from(o_vec);
-        return out;
+        Ok(Collection::from(o_vec))
     }
 }
 
@@ -409,7 +454,7 @@ impl Collection<f64> {
     ///
     /// # Panics
     ///
-    /// * If num_bins is less than 2
+    /// * If num_bins is less than 2, the Collection is empty or holds a NaN or infinite value, see `try_hist`
     ///
     /// # Example
     /// ```
@@ -425,35 +470,136 @@ impl Collection<f64> {
     /// let histogram: Collection<Bin> = len_col.hist(50);
     /// ```
     pub fn hist(&self, num_bins: u64) -> Collection<Bin> {
-        let mut st_vec = self.vec.clone();
-        st_vec.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        if num_bins < 2 {panic!("num_bins must be 2 or greater.");}
-        let width = (st_vec[st_vec.len()-1] + 0.01 - st_vec[0])/(num_bins as f64);
+        self.try_hist(num_bins).unwrap_or_else(|err| panic!("{}",err))
+    }
+
+    /// Return Collection<Bin> histogram, without panicking
+    ///
+    /// # Arguments
+    ///
+    /// * `num_bins` - Number of bins: u64 (>= 2)
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::LengthError` if num_bins is less than 2, or the Collection is empty
+    /// * `CalcifyError::NonFiniteError` if a value is NaN or infinite
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Collection;
+    ///
+    /// let col: Collection<f64> = Collection::from(vec![0.0,1.0,2.0]);
+    /// assert_eq!(col.try_hist(2).unwrap().len(),2);
+    /// assert!(col.try_hist(1).is_err());
+    /// assert!(Collection::<f64>::empty().try_hist(2).is_err());
+    /// assert!(Collection::from(vec![0.0,f64::NAN]).try_hist(2).is_err());
+    /// ```
+    pub fn try_hist(&self, num_bins: u64) -> Result<Collection<Bin>,CalcifyError> {
+        if num_bins < 2 {
+            return Err(CalcifyError::LengthError{expected: 2, found: num_bins as usize});
+        }
+        if self.vec.is_empty() {
+            return Err(CalcifyError::LengthError{expected: 1, found: 0});
+        }
+        if let Some(index) = self.vec.iter().position(|x| !x.is_finite()) {
+            return Err(CalcifyError::NonFiniteError{index});
+        }
+        let min = self.vec.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = self.vec.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let width = (max + 0.01 - min)/(num_bins as f64);
         let mut out: Collection<Bin> = Collection::empty();
         for i in 0..(num_bins) {
-            let edg0 = st_vec[0] + width * (i as f64);
-            let edg1 = st_vec[0] + width * ((i+1) as f64);
+            let edg0 = min + width * (i as f64);
+            let edg1 = min + width * ((i+1) as f64);
             out.push(Bin::new(edg0,edg1,0));
         }
-        let mut c_bin = 0;
-        for x in st_vec.iter() {
-            if x >= &out.at(c_bin).in_edge && x < &out.at(c_bin).ex_edge {
-                *out.at(c_bin) += 1;
-            }
-            else {
-                c_bin += 1;
-                *out.at(c_bin) += 1;
-            }
+        // The 0.01 pad on the last bin can be lost to rounding for large values
+        for x in self.vec.iter() {
+            let bin: usize = (((x - min)/width) as usize).min(num_bins as usize - 1);
+            *out.at(bin) += 1;
+        }
+        Ok(out)
+    }
+}
+
+impl Collection<Bin> {
+    /// Adds the counts of another histogram with the same edges, e.g. from another run
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::LengthError` if the histograms have a different number of Bins
+    /// * `CalcifyError::BinningError` if the edges of a Bin differ. Nothing is added.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::{Collection, Bin};
+    ///
+    /// let mut run_1 = Collection::from(vec![Bin::new(0.0,1.0,10),Bin::new(1.0,2.0,5)]);
+    /// let run_2 = Collection::from(vec![Bin::new(0.0,1.0,3),Bin::new(1.0,2.0,4)]);
+    /// run_1.add_counts(&run_2).unwrap();
+    /// assert_eq!(run_1,Collection::from(vec![Bin::new(0.0,1.0,13),Bin::new(1.0,2.0,9)]));
+    ///
+    /// let shifted = Collection::from(vec![Bin::new(0.0,1.0,3),Bin::new(1.0,2.5,4)]);
+    /// assert!(run_1.add_counts(&shifted).is_err());
+    /// ```
+    pub fn add_counts(&mut self, other: &Collection<Bin>) -> Result<(),CalcifyError> {
+        if self.vec.len() != other.vec.len() {
+            return Err(CalcifyError::LengthError{expected: self.vec.len(), found: other.vec.len()});
+        }
+        if let Some(index) = self.vec.iter().zip(other.vec.iter()).position(|(a,b)| a.in_edge != b.in_edge || a.ex_edge != b.ex_edge) {
+            return Err(CalcifyError::BinningError{index});
+        }
+        for (a,b) in self.vec.iter_mut().zip(other.vec.iter()) {
+            *a += b.count;
+        }
+        Ok(())
+    }
+}
+
+impl Collection<PointBin> {
+    /// Adds the counts of another 2D histogram with the same edges, e.g. from another run
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::LengthError` if the histograms have a different number of PointBins
+    /// * `CalcifyError::BinningError` if the edges of a PointBin differ. Nothing is added.
+    pub fn add_counts(&mut self, other: &Collection<PointBin>) -> Result<(),CalcifyError> {
+        if self.vec.len() != other.vec.len() {
+            return Err(CalcifyError::LengthError{expected: self.vec.len(), found: other.vec.len()});
+        }
+        if let Some(index) = self.vec.iter().zip(other.vec.iter()).position(|(a,b)| a.in_edge_x != b.in_edge_x || a.ex_edge_x != b.ex_edge_x || a.in_edge_y != b.in_edge_y || a.ex_edge_y != b.ex_edge_y) {
+            return Err(CalcifyError::BinningError{index});
+        }
+        for (a,b) in self.vec.iter_mut().zip(other.vec.iter()) {
+            *a += b.count;
+        }
+        Ok(())
+    }
+}
+
+/// Serde writes a Collection as a sequence, the same as json and msg.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Collection;
+    use crate::utils::Serializable;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    impl<T: Serializable + Serialize> Serialize for Collection<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.vec.serialize(serializer)
+        }
+    }
+
+    impl<'de, T: Serializable + Deserialize<'de>> Deserialize<'de> for Collection<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(Collection::from(Vec::<T>::deserialize(deserializer)?))
         }
-        out
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::io::prelude::*;
-    use std::io::BufWriter;
-    use std::fs::File;
     use crate::ThreeVec;
     use super::*;
 
@@ -468,32 +614,53 @@ mod tests {
 
     #[test]
     fn test_hist() {
-        let f = File::create("./scratch/test_hist.json").unwrap();
-        let mut wr = BufWriter::new(f);
+        let mut wr: Vec<u8> = Vec::new();
         let mut col_3v = Collection::empty();
             for _i in 0..99999 {
                 col_3v.push(ThreeVec::random(10000.0));
             }
         let len_col: Collection<f64> = col_3v.map(|tv| { *tv.x0()});
-        wr.write(len_col.hist(50).to_json().as_bytes()).unwrap();
+        wr.write_all(len_col.hist(50).to_json().as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn test_add_counts() {
+        let mut hist = Collection::from(vec![PointBin::new(0.0,1.0,0.0,1.0,1),PointBin::new(0.0,1.0,1.0,2.0,2)]);
+        hist.add_counts(&hist.clone()).unwrap();
+        assert_eq!(hist,Collection::from(vec![PointBin::new(0.0,1.0,0.0,1.0,2),PointBin::new(0.0,1.0,1.0,2.0,4)]));
+        let shifted = Collection::from(vec![PointBin::new(0.0,1.0,0.0,1.0,1),PointBin::new(0.0,1.0,1.0,3.0,2)]);
+        assert!(matches!(hist.add_counts(&shifted),Err(CalcifyError::BinningError{index: 1})));
+        assert!(matches!(hist.add_counts(&Collection::empty()),Err(CalcifyError::LengthError{expected: 2, found: 0})));
+        assert_eq!(hist.at(1).count,4);
+    }
+
+    #[test]
+    fn test_try_hist() {
+        let counts = |hist: Collection<Bin>| hist.into_iter().map(|b| b.count).collect::<Vec<u64>>();
+        assert_eq!(counts(Collection::from(vec![0.0,0.1,3.0]).try_hist(3).unwrap()),vec![2,0,1]);
+        assert_eq!(counts(Collection::from(vec![0.0,1e20]).try_hist(2).unwrap()),vec![1,1]);
+        assert!(matches!(Collection::from(vec![0.0,f64::NAN]).try_hist(2),Err(CalcifyError::NonFiniteError{index: 1})));
+        assert!(matches!(Collection::from(vec![f64::INFINITY]).try_hist(2),Err(CalcifyError::NonFiniteError{index: 0})));
+
+        let hist = Collection::plot(&[0.0,1e20],&[0.0,1.0]).try_hist(2,2).unwrap();
+        assert_eq!(hist.into_iter().map(|b| b.count).collect::<Vec<u64>>(),vec![1,0,0,1]);
+        assert!(matches!(Collection::plot(&[0.0,1.0],&[f64::NAN,1.0]).try_hist(2,2),Err(CalcifyError::NonFiniteError{index: 0})));
     }
 
     #[test]
     fn test_plot() {
-        let f = File::create("./scratch/test_plot.json").unwrap();
-        let mut wr = BufWriter::new(f);
+        let mut wr: Vec<u8> = Vec::new();
         let test_plot: Collection<Point> = Collection::plot(&vec![0.0,1.0,2.0],&vec![3.0,4.0,5.0]);
-        wr.write(test_plot.to_json().as_bytes()).unwrap();
+        wr.write_all(test_plot.to_json().as_bytes()).unwrap();
     }
 
     #[test]
     fn test_json() {
-        let f = File::create("./scratch/test_out.json").unwrap();
-        let mut wr = BufWriter::new(f);
+        let mut wr: Vec<u8> = Vec::new();
         let mut col_3v = Collection::empty();
         for _i in 0..9999 {
             col_3v.push(ThreeVec::random(10.0));
         }
-        wr.write(col_3v.map(ThreeVec::r).to_json().as_bytes()).unwrap();
+        wr.write_all(col_3v.map(ThreeVec::r).to_json().as_bytes()).unwrap();
     }
 }
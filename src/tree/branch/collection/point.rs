@@ -9,6 +9,7 @@ use std::ops::SubAssign;
 use std::ops::Mul;
 use std::ops::Neg;
 use std::iter;
+use std::convert::TryFrom;
 use std::fmt;
 use std::error;
 
@@ -20,7 +21,7 @@ use self::rand::thread_rng;
 use self::rand::distributions::{Distribution, Uniform};
 
 use crate::utils;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 /// Point, or Two Vector, depending on your perspective.
@@ -60,7 +61,7 @@ impl Point {
     ///
     /// # Panics
     ///
-    /// * `slice` length < 2
+    /// * `slice` length < 2, see `TryFrom`
     pub fn from(slice: &[f64]) -> Point {
 
         Point {
@@ -119,16 +120,12 @@ impl Serializable for Point {
 impl Deserializable for Point {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut x: f64 = NAN;
-        let mut y: f64 = NAN;
-        for dim in s.trim_matches(|p| p == '{' || p == '}' ).split(',') {
-            let n_v: Vec<&str> = dim.split(':').collect();
-            match n_v[0] {
-                "\"x\"" => x = n_v[1].parse::<f64>()?,
-                "\"y\"" => y = n_v[1].parse::<f64>()?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
-        }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let x = value.get("x")?.as_f64()?;
+        let y = value.get("y")?.as_f64()?;
         Ok(Point{x,y})
     }
 
@@ -140,7 +137,31 @@ impl Deserializable for Point {
             }
             Ok((Point::from(&x),bytes))
         } else {
-            Err(Box::new(CalcifyError::ParseError))
+            Err(Box::new(CalcifyError::parse("Point")))
+        }
+    }
+}
+
+/// Returns a new Point from a slice, without panicking
+///
+/// # Example
+/// ```
+/// use std::convert::TryFrom;
+/// use calcify::Point;
+/// assert_eq!(Point::try_from(&[1.0,2.0][..]).unwrap(),Point::new(1.0,2.0));
+/// assert!(Point::try_from(&[1.0][..]).is_err());
+/// ```
+///
+/// # Errors
+///
+/// * `CalcifyError::LengthError` if `slice` length < 2
+impl TryFrom<&[f64]> for Point {
+    type Error = CalcifyError;
+
+    fn try_from(slice: &[f64]) -> Result<Point,CalcifyError> {
+        match slice {
+            [x, y, ..] => Ok(Point::new(*x,*y)),
+            _ => Err(CalcifyError::LengthError{expected: 2, found: slice.len()}),
         }
     }
 }
@@ -247,6 +268,40 @@ impl Neg for Point {
     }
 }
 
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Point;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        x: f64,
+        y: f64,
+    }
+
+    impl Serialize for Point {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{x: self.x, y: self.y}.serialize(serializer)
+            } else {
+                (self.x, self.y).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Point {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                Ok(Point::new(f.x, f.y))
+            } else {
+                let (x, y) = <(f64,f64)>::deserialize(deserializer)?;
+                Ok(Point::new(x, y))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
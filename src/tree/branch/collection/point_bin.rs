@@ -9,7 +9,7 @@ use rmp::decode::*;
 
 use crate::utils;
 
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
 
 /// A 2D histogram is a Collection of PointBins
@@ -82,25 +82,19 @@ impl Serializable for PointBin {
 impl Deserializable for PointBin {
 
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut count: u64 = 0;
-        let mut in_edge_x: f64 = f64::NAN;
-        let mut ex_edge_x: f64 = f64::NAN;
-        let mut in_edge_y: f64 = f64::NAN;
-        let mut ex_edge_y: f64 = f64::NAN;
-        for (i,dim) in s.replace(":",",").replace("[",",").replace("]",",").trim_matches(|p| p == '{' || p == '}' ).split_terminator(",").enumerate() {
-            match i {
-                0 => (),
-                1 => count = dim.parse::<f64>()? as u64,
-                2 => (),
-                3 => (),
-                4 => in_edge_x = dim.parse::<f64>()?,
-                5 => ex_edge_x = dim.parse::<f64>()?,
-                6 => in_edge_y = dim.parse::<f64>()?,
-                7 => ex_edge_y = dim.parse::<f64>()?,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
-            }
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let count = value.get("count")?.as_u64()?;
+        if let [in_edge_x, ex_edge_x, in_edge_y, ex_edge_y] = value.get("range")?.as_array()? {
+            return Ok(PointBin{count,
+                               in_edge_x:in_edge_x.as_f64()?,
+                               ex_edge_x:ex_edge_x.as_f64()?,
+                               in_edge_y:in_edge_y.as_f64()?,
+                               ex_edge_y:ex_edge_y.as_f64()?});
         }
-        Ok(PointBin{count,in_edge_x,ex_edge_x,in_edge_y,ex_edge_y})
+        Err(Box::new(CalcifyError::parse("PointBin range")))
     }
 
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
@@ -114,7 +108,41 @@ impl Deserializable for PointBin {
                 return Ok((PointBin{count,in_edge_x,ex_edge_x,in_edge_y,ex_edge_y},bytes));
             }
         }
-        Err(Box::new(CalcifyError::ParseError))
+        Err(Box::new(CalcifyError::parse("PointBin")))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::PointBin;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Fields {
+        count: u64,
+        range: (f64,f64,f64,f64),
+    }
+
+    impl Serialize for PointBin {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            if serializer.is_human_readable() {
+                Fields{count: self.count, range: (self.in_edge_x, self.ex_edge_x, self.in_edge_y, self.ex_edge_y)}.serialize(serializer)
+            } else {
+                (self.count, (self.in_edge_x, self.ex_edge_x, self.in_edge_y, self.ex_edge_y)).serialize(serializer)
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for PointBin {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let (count, (in_edge_x, ex_edge_x, in_edge_y, ex_edge_y)) = if deserializer.is_human_readable() {
+                let f = Fields::deserialize(deserializer)?;
+                (f.count, f.range)
+            } else {
+                <(u64,(f64,f64,f64,f64))>::deserialize(deserializer)?
+            };
+            Ok(PointBin::new(in_edge_x, ex_edge_x, in_edge_y, ex_edge_y, count))
+        }
     }
 }
 
@@ -1,3 +1,4 @@
+use std::any::TypeId;
 use std::error;
 use std::f64;
 
@@ -6,29 +7,114 @@ pub use collection::Collection;
 pub use collection::Bin;
 pub use collection::Point;
 pub use collection::PointBin;
-use crate::four_mat::FourVec;
-use crate::four_mat::FourMat;
 
-use crate::three_mat::ThreeMat;
-use crate::three_mat::ThreeVec;
+mod raw_branch;
+pub use raw_branch::RawBranch;
+
+mod subtype;
+pub use subtype::TreeSubtype;
 
 use crate::utils;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue};
 use utils::errors::CalcifyError;
+use utils::container;
 
 extern crate rmp;
 use rmp::encode::*;
 use rmp::decode::*;
 
+type JsonDecoded = Result<Box<dyn Serializable>, Box<dyn error::Error>>;
+type MsgDecoded<'a> = Result<(Box<dyn Serializable>,&'a [u8]), Box<dyn error::Error>>;
+
+fn collection_from_json<T: 'static + Serializable + Deserializable>(value: &JsonValue) -> JsonDecoded {
+    Ok(Box::new(Collection::<T>::from_json_value(value)?))
+}
+
+fn collection_from_msg<T: 'static + Serializable + Deserializable>(bytes: &[u8]) -> MsgDecoded<'_> {
+    let (col, rest) = Collection::<T>::from_msg(bytes)?;
+    Ok((Box::new(col),rest))
+}
+
+/// Decoding functions for the Collection of one subtype.
+///
+/// Built in subtypes and subtypes registered on a Tree both go through this, so a Branch never needs to name its type.
+#[derive(Clone, Copy)]
+pub(crate) struct SubtypeDecoder {
+    type_id: TypeId,
+    from_json: fn(&JsonValue) -> JsonDecoded,
+    from_msg: fn(&[u8]) -> MsgDecoded<'_>,
+}
+
+impl SubtypeDecoder {
+    /// Returns the SubtypeDecoder for Collection<T>
+    pub(crate) fn of<T: 'static + Serializable + Deserializable>() -> SubtypeDecoder {
+        SubtypeDecoder {
+            type_id: TypeId::of::<T>(),
+            from_json: collection_from_json::<T>,
+            from_msg: collection_from_msg::<T>,
+        }
+    }
+
+    /// Returns true if this decodes a Collection<T>
+    pub(crate) fn is<T: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    /// Returns the SubtypeDecoder of a built in subtype, or None
+    pub(crate) fn builtin(subtype: &str) -> Option<SubtypeDecoder> {
+        macro_rules! builtin_decoder {
+            (($subtype:expr) $($t:ty => $name:literal),*) => {
+                match $subtype {
+                    $($name => Some(SubtypeDecoder::of::<$t>()),)*
+                    _ => None,
+                }
+            }
+        }
+        crate::builtin_subtypes!(builtin_decoder (subtype))
+    }
+}
+
+enum Contents {
+    Decoded(Box<dyn Serializable>),
+    Raw(RawBranch),
+}
+
+impl Serializable for Contents {
+    fn to_json(&self) -> String {
+        match self {
+            Contents::Decoded(branch) => branch.to_json(),
+            Contents::Raw(raw) => raw.to_json(),
+        }
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>, ValueWriteError> {
+        match self {
+            Contents::Decoded(branch) => branch.to_msg(),
+            Contents::Raw(raw) => raw.to_msg(),
+        }
+    }
+}
+
+/// Summary of a Branch, see `Branch::info`
+#[derive(Debug, PartialEq, Clone)]
+pub struct BranchInfo {
+    /// Subtype the Branch was created or read with
+    pub subtype: String,
+    /// Number of elements in the Collection
+    pub len: usize,
+    /// Size in bytes of the Collection as msg
+    pub size: usize,
+}
 
 /// Branch
 ///
 /// # Note
 ///
 /// * **Not intended for direct use. Use the memebers of Tree instead.**
+/// * Branches read from a file whose subtype is not built in, or that fail to decode, are kept as a RawBranch.
 pub struct Branch {
     subtype: String,
-    branch: Box<dyn Serializable>,
+    branch: Contents,
     buffer: Option<Vec<u8>>,
 }
 
@@ -37,20 +123,135 @@ impl Branch{
         let buffer: Option<Vec<u8>> = None;
         Branch {
             subtype,
-            branch,
+            branch: Contents::Decoded(branch),
             buffer,
         }
     }
+
+    fn undecoded(raw: RawBranch) -> Branch {
+        Branch {
+            subtype: String::from(raw.subtype()),
+            branch: Contents::Raw(raw),
+            buffer: None,
+        }
+    }
+
+    /// Returns the subtype the Branch was created or read with
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Returns true if the Branch is still undecoded
+    pub fn is_raw(&self) -> bool {
+        matches!(self.branch, Contents::Raw(_))
+    }
+
+    /// Returns the RawBranch if the Branch is still undecoded
+    pub fn raw(&self) -> Option<&RawBranch> {
+        match &self.branch {
+            Contents::Raw(raw) => Some(raw),
+            Contents::Decoded(_) => None,
+        }
+    }
+
+    /// Returns the subtype, element count and msg size of the Branch
+    ///
+    /// Decoded Branches are serialized to find the size, so this is not free for large Collections.
+    pub fn info(&self) -> Result<BranchInfo, Box<dyn error::Error>> {
+        let msg = match self.raw().and_then(RawBranch::msg_bytes) {
+            Some(bytes) => bytes.to_vec(),
+            None => self.collection_msg()?,
+        };
+        let len = read_array_len(&mut &msg[..]).map_err(|e| CalcifyError::parse("Branch").caused_by(e))? as usize;
+        Ok(BranchInfo {
+            subtype: self.subtype.clone(),
+            len,
+            size: msg.len(),
+        })
+    }
+
+    /// Returns the Branch of both Collections, with `other` appended, or for Bin and PointBin histograms the counts added
+    ///
+    /// The subtypes must match. An appended Branch is decoded if its subtype is built in, otherwise it is left raw.
+    pub(crate) fn merge(&mut self, other: &mut Branch) -> Result<Branch, Box<dyn error::Error>> {
+        if self.subtype != other.subtype {
+            return Err(Box::new(CalcifyError::SubtypeError{subtype: other.subtype.clone(), expected: self.subtype.clone()}));
+        }
+        match self.subtype.as_str() {
+            "Bin" => {
+                let mut hist: Collection<Bin> = self.extract()?;
+                hist.add_counts(&other.extract()?)?;
+                Ok(Branch::new(self.subtype.clone(),Box::new(hist)))
+            },
+            "PointBin" => {
+                let mut hist: Collection<PointBin> = self.extract()?;
+                hist.add_counts(&other.extract()?)?;
+                Ok(Branch::new(self.subtype.clone(),Box::new(hist)))
+            },
+            _ => {
+                let first = self.collection_msg()?;
+                let second = other.collection_msg()?;
+                let mut first_els: &[u8] = &first;
+                let mut second_els: &[u8] = &second;
+                let len = read_array_len(&mut first_els)? + read_array_len(&mut second_els)?;
+                let mut buf = Vec::with_capacity(first.len() + second.len());
+                write_array_len(&mut buf, len)?;
+                buf.extend_from_slice(first_els);
+                buf.extend_from_slice(second_els);
+                let (branch,_) = Branch::from_subtype_msg(&self.subtype, &buf)?;
+                Ok(branch)
+            },
+        }
+    }
+
+    /// Returns just the Collection as msg, without the subtype
+    pub(crate) fn collection_msg(&self) -> Result<Vec<u8>, ValueWriteError> {
+        self.branch.to_msg()
+    }
+
+    /// Read the Collection of a Branch from msg, given the subtype it was written with
+    pub(crate) fn from_subtype_msg<'a>(subtype: &str, bytes: &'a [u8]) -> Result<(Branch,&'a [u8]), Box<dyn error::Error>> {
+        if let Some(decoder) = SubtypeDecoder::builtin(subtype) {
+            if let Ok((branch,rest)) = (decoder.from_msg)(bytes) {
+                return Ok((Branch::new(subtype.to_string(),branch),rest));
+            }
+        }
+        let (raw,rest) = bytes.split_at(container::msg_len(bytes)?);
+        Ok((Branch::undecoded(RawBranch::from_msg_bytes(subtype,raw.to_vec())),rest))
+    }
+
+    /// Decode an undecoded Branch in place. Does nothing if it is already decoded.
+    pub(crate) fn decode(&mut self, decoder: SubtypeDecoder) -> Result<(), Box<dyn error::Error>> {
+        if let Some(decoded) = self.decoded(decoder)? {
+            *self = decoded;
+        }
+        Ok(())
+    }
+
+    /// Returns the decoded Branch, leaving this one unchanged, or None if it is already decoded.
+    pub(crate) fn decoded(&self, decoder: SubtypeDecoder) -> Result<Option<Branch>, Box<dyn error::Error>> {
+        match &self.branch {
+            Contents::Decoded(_) => Ok(None),
+            Contents::Raw(raw) => {
+                let decoded = raw.decode(decoder.from_json, |bytes| Ok((decoder.from_msg)(bytes)?.0))?;
+                Ok(Some(Branch::new(self.subtype.clone(),decoded)))
+            },
+        }
+    }
+
     /// Returns a Collection of the specified subtype from the Branch
     ///
     pub fn extract<T: Serializable + Deserializable>(&mut self) -> Result<Collection<T>, Box<dyn error::Error>> {
+        if let Contents::Raw(raw) = &self.branch {
+            if raw.msg_bytes().is_none() {
+                return raw.decode(Collection::<T>::from_json_value, |_| Err(Box::new(CalcifyError::parse("Branch"))));
+            }
+        }
         if self.buffer.is_none() {
             self.buffer = Some(self.branch.to_msg()?);
         }
-        if let Ok((out, _)) = Collection::<T>::from_msg(&mut self.buffer.as_ref().unwrap()){
-            return Ok(out);
-        }
-        Err(Box::new(CalcifyError::ParseError))
+        let (out, _) = Collection::<T>::from_msg(self.buffer.as_ref().unwrap())?;
+        Ok(out)
     }
 }
 
@@ -70,109 +271,36 @@ impl Serializable for Branch {
     }
 }
 
+/// Subtypes without a built in SubtypeDecoder, including `Object`, and contents that fail to decode are read as a RawBranch.
+///
+/// Only a Branch that is not valid json or MsgPack at all is an error. Raw msg is kept as it was read,
+/// including binary and extension values.
 impl Deserializable for Branch {
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut subtype: &str = "";
-        let mut branch_str: &str = "";
-        let pattern: Vec<char> = "{\"subtype\":}".chars().collect();
-        for (i,dim) in s.trim_matches(|p| pattern.contains(&p)).split(",\"branch\":").enumerate() {
-            match i {
-                0 => subtype = dim.trim_matches(|p| p == '\"'),
-                1 => branch_str = dim,
-                _ => return Err(Box::new(CalcifyError::ParseError)),
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let subtype = value.get("subtype")?.as_str()?;
+        let branch_val = value.get("branch")?;
+        if let Some(decoder) = SubtypeDecoder::builtin(subtype) {
+            if let Ok(branch) = (decoder.from_json)(branch_val) {
+                return Ok(Branch::new(subtype.to_string(),branch));
             }
         }
-        let branch: Box<dyn Serializable> = match subtype {
-            "f64" => Box::new(Collection::<f64>::from_json(&branch_str)?),
-            "ThreeVec" => Box::new(Collection::<ThreeVec>::from_json(&branch_str)?),
-            "ThreeMat" => Box::new(Collection::<ThreeMat>::from_json(&branch_str)?),
-            "FourVec" => Box::new(Collection::<FourVec>::from_json(&branch_str)?),
-            "FourMat" => Box::new(Collection::<FourMat>::from_json(&branch_str)?),
-            "Bin" => Box::new(Collection::<Bin>::from_json(&branch_str)?),
-            "Point" => Box::new(Collection::<Point>::from_json(&branch_str)?),
-            "PointBin" => Box::new(Collection::<PointBin>::from_json(&branch_str)?),
-            _ => return Err(Box::new(CalcifyError::ParseError)),
-        };
-        Ok(Branch::new(subtype.to_string(),branch))
+        Ok(Branch::undecoded(RawBranch::from_json_value(subtype,branch_val.clone())))
     }
 
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
-
-        if let Ok(_len) = read_map_len(&mut bytes) {
-            let mut unparsed = &bytes[..];
-            if let Ok((_,rest)) = read_str_from_slice(unparsed) {
-                unparsed = rest;
-                if let Ok((subtype,rest)) = read_str_from_slice(unparsed) {
-                    unparsed = rest;
-                    if let Ok((_,rest)) = read_str_from_slice(unparsed) {
-                        unparsed = rest;
-                        let (branch,rest): (Box<dyn Serializable>,&[u8])  = match subtype {
-                            "f64" => {
-                                if let Ok((ot,rest)) = Collection::<f64>::from_msg(unparsed) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "ThreeVec" => {
-                                if let Ok((ot,rest)) = Collection::<ThreeVec>::from_msg(unparsed) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "ThreeMat" => {
-                                if let Ok((ot,rest)) = Collection::<ThreeMat>::from_msg(unparsed) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "FourVec" => {
-                                if let Ok((ot,rest)) = Collection::<FourVec>::from_msg(unparsed) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "FourMat" => {
-                                if let Ok((ot,rest)) = Collection::<FourMat>::from_msg(unparsed) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "Bin" => {
-                                if let Ok((ot,rest)) = Collection::<Bin>::from_msg(&mut bytes) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "Point" => {
-                                if let Ok((ot,rest)) = Collection::<Point>::from_msg(&mut bytes) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "PointBin" => {
-                                if let Ok((ot,rest)) = Collection::<PointBin>::from_msg(&mut bytes) {
-                                    (Box::new(ot),rest)
-                                } else {
-                                    return Err(Box::new(CalcifyError::ParseError));
-                                }
-                            },
-                            "Object" => {
-                                return Err(Box::new(CalcifyError::ObjectBranchDeserializeError));
-                            },
-                            _ => return Err(Box::new(CalcifyError::ParseError)),
-                        };
-                        return Ok((Branch::new(subtype.to_string(),branch),rest));
+        if let Ok(2) = read_map_len(&mut bytes) {
+            if let Ok(("subtype",rest)) = read_str_from_slice(bytes) {
+                if let Ok((subtype,rest)) = read_str_from_slice(rest) {
+                    if let Ok(("branch",unparsed)) = read_str_from_slice(rest) {
+                        return Branch::from_subtype_msg(subtype,unparsed);
                     }
                 }
             }
         }
-        Err(Box::new(CalcifyError::ParseError))
+        Err(Box::new(CalcifyError::parse("Branch, expected subtype and branch")))
     }
 }
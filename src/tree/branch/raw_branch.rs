@@ -0,0 +1,93 @@
+use std::error;
+
+use crate::utils;
+use utils::{Serializable, Deserializable, JsonValue};
+
+extern crate rmp;
+use rmp::encode::*;
+
+/// The undecoded contents of a Branch, either MsgPack bytes or a json value, depending on what it was read from.
+#[derive(Debug, PartialEq, Clone)]
+enum RawData {
+    Msg(Vec<u8>),
+    Json(JsonValue),
+}
+
+/// A Branch read from a file without being decoded
+///
+/// Trees keep a Branch raw when its subtype is not built in or registered, or when it fails to decode as its subtype.
+/// Raw Branches are written back out unchanged, so reading, modifying and writing a Tree never loses them.
+///
+/// # Note
+///
+/// * A RawBranch read from json is written to msg as generic MsgPack maps, not in the array intensive format of its subtype.
+/// * MsgPack bin and ext values are written to json as the tagged Objects of `JsonValue`, and read back from them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RawBranch {
+    subtype: String,
+    data: RawData,
+}
+
+impl RawBranch {
+    pub(crate) fn from_msg_bytes(subtype: &str, bytes: Vec<u8>) -> RawBranch {
+        RawBranch {
+            subtype: String::from(subtype),
+            data: RawData::Msg(bytes),
+        }
+    }
+
+    pub(crate) fn from_json_value(subtype: &str, value: JsonValue) -> RawBranch {
+        RawBranch {
+            subtype: String::from(subtype),
+            data: RawData::Json(value),
+        }
+    }
+
+    /// Returns the subtype string the Branch was written with
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Returns the undecoded MsgPack bytes of the Collection, if the Branch was read from msg
+    pub fn msg_bytes(&self) -> Option<&[u8]> {
+        match &self.data {
+            RawData::Msg(bytes) => Some(bytes),
+            RawData::Json(_) => None,
+        }
+    }
+
+    /// Returns the contents of the Branch as a generic JsonValue
+    pub fn value(&self) -> Result<JsonValue, Box<dyn error::Error>> {
+        match &self.data {
+            RawData::Msg(bytes) => Ok(JsonValue::from_msg(bytes)?.0),
+            RawData::Json(value) => Ok(value.clone()),
+        }
+    }
+
+    /// Decode with either the json or msg function, depending on what the Branch was read from
+    pub(crate) fn decode<T, J, M>(&self, from_json: J, from_msg: M) -> Result<T, Box<dyn error::Error>>
+        where J: FnOnce(&JsonValue) -> Result<T, Box<dyn error::Error>>,
+              M: FnOnce(&[u8]) -> Result<T, Box<dyn error::Error>> {
+        match &self.data {
+            RawData::Msg(bytes) => from_msg(bytes),
+            RawData::Json(value) => from_json(value),
+        }
+    }
+}
+
+/// Writes only the contents, exactly as they were read, not the subtype.
+impl Serializable for RawBranch {
+    fn to_json(&self) -> String {
+        match self.value() {
+            Ok(value) => value.to_json(),
+            Err(_) => JsonValue::Null.to_json(),
+        }
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>, ValueWriteError> {
+        match &self.data {
+            RawData::Msg(bytes) => Ok(bytes.clone()),
+            RawData::Json(value) => value.to_msg(),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+/// Names the subtype a Collection of Self is written to a Tree with.
+///
+/// `Tree::add_branch` takes the subtype from here, and `Tree::read_branch` checks it against the subtype of the Branch,
+/// so a Branch tagged "ThreeVec" always holds a Collection<ThreeVec>.
+///
+/// Besides the scalars and calcify types, the common containers of them are built in, named as in Rust without spaces,
+/// like "Vec<f64>", "Option<String>", "HashMap<String,i64>", "[f64;3]" and "(f64,f64)".
+///
+/// # Example
+/// ```
+/// use calcify::{Tree, Collection, TreeSubtype};
+/// use calcify::errors::CalcifyError;
+/// # use calcify::{Serializable, Deserializable};
+///
+/// #[derive(Debug, PartialEq, Clone)]
+/// struct Count(u64);
+/// # impl Serializable for Count {
+/// #     fn to_json(&self) -> String { self.0.to_json() }
+/// #     fn to_msg(&self) -> Result<Vec<u8>, rmp::encode::ValueWriteError> { self.0.to_msg() }
+/// # }
+/// # impl Deserializable for Count {
+/// #     fn from_json(s: &str) -> Result<Self, Box<dyn std::error::Error>> { Ok(Count(u64::from_json(s)?)) }
+/// #     fn from_msg(b: &[u8]) -> Result<(Self,&[u8]), Box<dyn std::error::Error>> { let (c,r) = u64::from_msg(b)?; Ok((Count(c),r)) }
+/// # }
+///
+/// impl TreeSubtype for Count {
+///     const NAME: &'static str = "Count";
+/// }
+///
+/// let mut ttree = Tree::new("Test_Tree");
+/// ttree.add_branch("counts", Collection::from(vec![Count(1),Count(2)])).unwrap();
+/// ttree.add_branch("fcol", Collection::from(vec![1.0,2.0])).unwrap();
+/// assert_eq!(ttree.get_branch("fcol").unwrap().subtype(),"f64");
+///
+/// assert_eq!(ttree.read_branch::<Count>("counts").unwrap(),Collection::from(vec![Count(1),Count(2)]));
+/// assert!(matches!(ttree.read_branch::<u64>("counts"),Err(CalcifyError::SubtypeError{..})));
+/// ```
+pub trait TreeSubtype {
+    /// The subtype written to files, must be unique to Self
+    const NAME: &'static str;
+}
+
+/// The built in subtypes, as `Type => "name"`, passed on to `$callback!` after `$args`.
+///
+/// The one list of them, behind `TreeSubtype`, the decoders of Tree and TreeFile, and the `calcify` tool,
+/// so a new built in subtype only needs adding here.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! builtin_subtypes {
+    ($callback:ident ($($args:tt)*)) => {
+        $callback! {($($args)*)
+            f64 => "f64",
+            f32 => "f32",
+            u64 => "u64",
+            i64 => "i64",
+            u32 => "u32",
+            i32 => "i32",
+            usize => "usize",
+            bool => "bool",
+            String => "String",
+            $crate::ThreeVec => "ThreeVec",
+            $crate::ThreeMat => "ThreeMat",
+            $crate::Quaternion => "Quaternion",
+            $crate::FourVec => "FourVec",
+            $crate::FourMat => "FourMat",
+            $crate::Bin => "Bin",
+            $crate::Point => "Point",
+            $crate::PointBin => "PointBin",
+            Vec<f64> => "Vec<f64>",
+            Vec<i64> => "Vec<i64>",
+            Vec<u64> => "Vec<u64>",
+            Vec<bool> => "Vec<bool>",
+            Vec<String> => "Vec<String>",
+            Option<f64> => "Option<f64>",
+            Option<i64> => "Option<i64>",
+            Option<u64> => "Option<u64>",
+            Option<bool> => "Option<bool>",
+            Option<String> => "Option<String>",
+            ::std::collections::HashMap<String,f64> => "HashMap<String,f64>",
+            ::std::collections::HashMap<String,i64> => "HashMap<String,i64>",
+            ::std::collections::HashMap<String,String> => "HashMap<String,String>",
+            [f64; 2] => "[f64;2]",
+            [f64; 3] => "[f64;3]",
+            [f64; 4] => "[f64;4]",
+            (f64, f64) => "(f64,f64)",
+            (f64, f64, f64) => "(f64,f64,f64)"
+        }
+    };
+}
+
+macro_rules! impl_subtype {
+    (() $($t:ty => $name:literal),*) => {$(
+        impl TreeSubtype for $t {
+            const NAME: &'static str = $name;
+        }
+    )*}
+}
+
+crate::builtin_subtypes!(impl_subtype ());
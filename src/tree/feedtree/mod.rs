@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::error;
+use super::Collection;
+use super::MergePolicy;
+
+use crate::utils;
+use utils::{Serializable, Deserializable, JsonValue, FieldValue};
+use utils::errors::CalcifyError;
+use utils::io::{ToFile,FromFile};
+use utils::container::{self, Block, Codec};
+
+extern crate rmp;
+use rmp::encode::*;
+use rmp::decode::*;
+
+mod stream;
+pub use stream::FeedStream;
+
+/// Tree of Collections of only a single type, which impl the Feed trait for added functionality
+///
+/// To write records to disk as they come in, rather than holding them all in memory, use a FeedStream.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeedTree<T: Serializable> {
+    metadata: HashMap<String,FieldValue>,
+    datafeeds: HashMap<String,Collection<T>>,
+}
+
+impl<T: Serializable> FeedTree<T> {
+    /// Returns new FeedTree
+    ///
+    /// Name and subtype is the only required metadata.
+    /// # Arguments
+    ///
+    /// * `name` - string
+    /// * `subtype` - string, must match given Serializable type
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::FeedTree;
+    /// use calcify::Collection;
+    ///
+    /// let f_col: Collection<f64> = Collection::from(vec![0.0,0.0]);
+    /// let mut ftree: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+    /// ftree.add_field("Desc", "This is a FeedTree for testing.");
+    /// ftree.add_feed("fcol", f_col);
+    /// ftree.write("fcol", 1.0);
+    /// ftree.write("fcol", 2.0);
+    /// assert_eq!(Collection::from(vec![0.0,0.0,1.0,2.0]),*ftree.get_feed("fcol").unwrap())
+    /// ```
+    pub fn new(name: &str, subtype: &str) -> FeedTree<T> {
+        let mut md = HashMap::new();
+        md.insert(String::from("Name"),FieldValue::from(name));
+        md.insert(String::from("SubType"),FieldValue::from(subtype));
+        let df = HashMap::new();
+        FeedTree {
+            metadata: md,
+            datafeeds: df,
+        }
+    }
+
+    /// Inserts a new metadata field
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Hash key, String
+    /// * `f` - Anything that converts into a FieldValue, e.g. &str, i64, f64, bool or SystemTime
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is already in the FeedTree. The field is left unchanged, use `set_field` to replace it.
+    pub fn add_field<V: Into<FieldValue>>(&mut self, key: &str, f: V) -> Result<(),CalcifyError> {
+        if self.metadata.contains_key(key) {
+            return Err(CalcifyError::key(key));
+        }
+        self.metadata.insert(String::from(key),f.into());
+        Ok(())
+    }
+
+    /// Inserts or replaces a metadata field, returning the old value
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::{FeedTree, FieldValue};
+    ///
+    /// let mut tree: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+    /// assert_eq!(tree.set_field("dt", 0.01),None);
+    /// assert_eq!(tree.set_field("dt", 0.02),Some(FieldValue::Float(0.01)));
+    /// assert_eq!(tree.get_field("dt").and_then(FieldValue::as_f64),Some(0.02));
+    /// assert_eq!(tree.get_field("Name").unwrap(),"Test_Tree");
+    /// assert_eq!(tree.remove_field("dt"),Some(FieldValue::Float(0.02)));
+    /// assert!(tree.fields().all(|(key,_)| key != "dt"));
+    /// ```
+    pub fn set_field<V: Into<FieldValue>>(&mut self, key: &str, f: V) -> Option<FieldValue> {
+        self.metadata.insert(String::from(key),f.into())
+    }
+
+    /// Returns a metadata field
+    pub fn get_field(&self, key: &str) -> Option<&FieldValue> {
+        self.metadata.get(key)
+    }
+
+    /// Removes a metadata field, returning its value
+    pub fn remove_field(&mut self, key: &str) -> Option<FieldValue> {
+        self.metadata.remove(key)
+    }
+
+    /// Iterates over the metadata fields, in no particular order
+    pub fn fields(&self) -> impl Iterator<Item=(&str,&FieldValue)> {
+        self.metadata.iter().map(|(k,v)| (k.as_str(),v))
+    }
+
+    /// Inserts new Collection<T> into FeedTree.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Hash key, String
+    /// * `f` - Collection<T: Serializable>
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is already in the FeedTree. The existing Collection is left unchanged.
+    pub fn add_feed(&mut self, key: &str, f: Collection<T>) -> Result<(),CalcifyError> {
+        if self.datafeeds.contains_key(key) {
+            return Err(CalcifyError::key(key));
+        }
+        self.datafeeds.insert(String::from(key),f);
+        Ok(())
+    }
+
+    /// Merges another FeedTree into this one, e.g. the output of another run of the same simulation
+    ///
+    /// Feeds in both are concatenated, with the records of `other` after these, and feeds only in `other` are moved over.
+    /// Metadata fields in both with different values are resolved by `policy`.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::MergeError` if the `SubType` fields differ, with a `SubtypeError` as its source, or naming the field that differs under `MergePolicy::Error`. The FeedTree is unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::{FeedTree, Collection, MergePolicy};
+    ///
+    /// let mut run_1: FeedTree<f64> = FeedTree::new("Run","f64");
+    /// run_1.add_feed("energy", Collection::from(vec![1.0,2.0])).unwrap();
+    /// let mut run_2: FeedTree<f64> = FeedTree::new("Run","f64");
+    /// run_2.add_feed("energy", Collection::from(vec![3.0])).unwrap();
+    /// run_2.add_feed("time", Collection::from(vec![0.5])).unwrap();
+    ///
+    /// run_1.merge(run_2, MergePolicy::Error).unwrap();
+    /// assert_eq!(*run_1.get_feed("energy").unwrap(),Collection::from(vec![1.0,2.0,3.0]));
+    /// assert_eq!(*run_1.get_feed("time").unwrap(),Collection::from(vec![0.5]));
+    /// ```
+    pub fn merge(&mut self, other: FeedTree<T>, policy: MergePolicy) -> Result<(),CalcifyError> {
+        let ours = self.get_field("SubType").map(|f| f.to_string()).unwrap_or_default();
+        let theirs = other.get_field("SubType").map(|f| f.to_string()).unwrap_or_default();
+        if ours != theirs {
+            return Err(CalcifyError::merge("FeedTree").caused_by(CalcifyError::SubtypeError{subtype: theirs, expected: ours}));
+        }
+        policy.check_fields(&self.metadata, &other.metadata)?;
+        policy.merge_fields(&mut self.metadata, other.metadata);
+        for (key,feed) in other.datafeeds {
+            match self.datafeeds.get_mut(&key) {
+                Some(ours) => ours.extend(feed),
+                None => { self.datafeeds.insert(key,feed); },
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_feed(&mut self, key: &str) -> Option<&Collection<T>> {
+        self.datafeeds.get(key)
+    }
+
+    /// Iterates over the feeds and their names, in no particular order
+    pub fn feeds(&self) -> impl Iterator<Item=(&str,&Collection<T>)> {
+        self.datafeeds.iter().map(|(k,v)| (k.as_str(),v))
+    }
+
+    pub fn write(&mut self, key: &str, data: T) -> Result<(),CalcifyError> {
+        if let Some(feed) = self.datafeeds.get_mut(key) {
+            feed.push(data);
+            Ok(())
+        } else {
+            Err(CalcifyError::key(key))
+        }
+    }
+}
+
+impl<T: Serializable> Serializable for FeedTree<T> {
+    fn to_json(&self) -> String {
+        let mut out = String::from("{");
+        for (key, val) in &self.metadata {
+            out.push_str(format!("{}:{},",key.to_json(),val.to_json()).as_str());
+        }
+        out.push_str("\"datafeeds\":{");
+        let str_vec: Vec<String> = self.datafeeds.iter().map(|(key,val)| format!("{}:{}",key.to_json(),val.to_json())).collect();
+        out.push_str(&str_vec.join(","));
+        out.push_str("}}");
+        out
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>, ValueWriteError> {
+        let mut buf = Vec::new();
+        write_map_len(&mut buf, (self.metadata.len()+1) as u32)?;
+        for (key, val) in &self.metadata {
+            write_str(&mut buf, key)?;
+            buf.append(&mut val.to_msg()?);
+        }
+        write_str(&mut buf, "datafeeds")?;
+        write_map_len(&mut buf, (self.datafeeds.len()) as u32)?;
+        for (key, val) in &self.datafeeds {
+            write_str(&mut buf, key)?;
+            buf.append(&mut val.to_msg()?);
+        }
+        Ok(buf)
+    }
+}
+
+impl<T: Serializable + Deserializable> Deserializable for FeedTree<T> {
+    fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+        let mut datafeeds: HashMap<String,Collection<T>> = HashMap::new();
+        if value.get("datafeeds").is_err() {
+            return Err(Box::new(container::missing("FeedTree","datafeeds")));
+        }
+        for (key, val) in value.as_object()?.iter() {
+            match key.as_str() {
+                "datafeeds" => {
+                    for (fkey, fval) in val.as_object()?.iter() {
+                        let feed = Collection::<T>::from_json_value(fval)
+                            .map_err(|e| CalcifyError::parse(&format!("feed {:?}",fkey)).caused_by_boxed(e))?;
+                        datafeeds.insert(fkey.clone(),feed);
+                    }
+                },
+                _ => {
+                    let field = FieldValue::from_json_value(val)
+                        .map_err(|e| CalcifyError::parse(&format!("FeedTree metadata {:?}",key)).caused_by_boxed(e))?;
+                    metadata.insert(key.clone(),field);
+                },
+            }
+        }
+        Ok(FeedTree{metadata, datafeeds})
+    }
+
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+        let mut datafeeds: HashMap<String,Collection<T>> = HashMap::new();
+        let mut found_datafeeds = false;
+        let len = read_map_len(&mut bytes)?;
+        for _ in 0..len {
+            let (key,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("FeedTree key"))?;
+            bytes = rest;
+            if key == "datafeeds" {
+                found_datafeeds = true;
+                let flen = read_map_len(&mut bytes)?;
+                for _ in 0..flen {
+                    let (name,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("feed name"))?;
+                    let (feed,rest) = Collection::<T>::from_msg(rest)
+                        .map_err(|e| CalcifyError::parse(&format!("feed {:?}",name)).caused_by_boxed(e))?;
+                    datafeeds.insert(String::from(name),feed);
+                    bytes = rest;
+                }
+            } else {
+                let (value,rest) = FieldValue::from_msg(bytes)
+                    .map_err(|e| CalcifyError::parse(&format!("FeedTree metadata {:?}",key)).caused_by_boxed(e))?;
+                metadata.insert(String::from(key),value);
+                bytes = rest;
+            }
+        }
+        if !found_datafeeds {
+            return Err(Box::new(container::missing("FeedTree","datafeeds")));
+        }
+        Ok((FeedTree{metadata, datafeeds},bytes))
+    }
+}
+
+impl<T: Serializable> ToFile for FeedTree<T> {
+    fn to_container_with(&self, codec: Codec) -> Result<Vec<u8>, Box<dyn error::Error>> {
+        let subtype = self.metadata.get("SubType").and_then(FieldValue::as_str).unwrap_or("");
+        let mut names: Vec<&String> = self.datafeeds.keys().collect();
+        names.sort();
+        let mut blocks: Vec<Block> = Vec::new();
+        for name in names {
+            blocks.push(Block{name, subtype, bytes: self.datafeeds[name].to_msg()?});
+        }
+        container::write_container(&self.metadata, blocks, codec)
+    }
+}
+
+impl<T: Serializable + Deserializable> FromFile for FeedTree<T> {
+    fn from_container(bytes: &[u8]) -> Result<Self, Box<dyn error::Error>> {
+        let header = container::read_header(&mut &bytes[..])?;
+        let mut datafeeds: HashMap<String,Collection<T>> = HashMap::new();
+        for entry in header.index.iter() {
+            let block = &header.block(entry, bytes)?;
+            let (feed,_) = Collection::<T>::from_msg(block).map_err(|e| container::decode_error(&format!("feed {:?}",entry.name),block,e))?;
+            datafeeds.insert(entry.name.clone(),feed);
+        }
+        Ok(FeedTree{metadata: header.metadata, datafeeds})
+    }
+}
+
+/// Serde writes a FeedTree as a map of its metadata and `datafeeds`, the same as json and msg.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::marker::PhantomData;
+    use super::{FeedTree, Collection, FieldValue};
+    use crate::utils::Serializable;
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+    use serde::ser::SerializeMap;
+    use serde::de::{Visitor, MapAccess};
+
+    impl<T: Serializable + Serialize> Serialize for FeedTree<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(self.metadata.len()+1))?;
+            for (key, val) in &self.metadata {
+                map.serialize_entry(key, val)?;
+            }
+            map.serialize_entry("datafeeds", &self.datafeeds)?;
+            map.end()
+        }
+    }
+
+    struct FeedTreeVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Serializable + Deserialize<'de>> Visitor<'de> for FeedTreeVisitor<T> {
+        type Value = FeedTree<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a FeedTree map")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<FeedTree<T>, A::Error> {
+            let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+            let mut datafeeds: HashMap<String,Collection<T>> = HashMap::new();
+            while let Some(key) = access.next_key::<String>()? {
+                if key == "datafeeds" {
+                    datafeeds = access.next_value()?;
+                } else {
+                    metadata.insert(key, access.next_value()?);
+                }
+            }
+            Ok(FeedTree{metadata, datafeeds})
+        }
+    }
+
+    impl<'de, T: Serializable + Deserialize<'de>> Deserialize<'de> for FeedTree<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_map(FeedTreeVisitor(PhantomData))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThreeVec;
+
+    #[test]
+    fn test_ftree_write() -> Result<(),Box<dyn error::Error>>{
+        let mut col_3v: Collection<ThreeVec> = Collection::empty();
+        for _i in 0..9 {col_3v.push(ThreeVec::new(1.0,2.0,3.0));}
+        let mut ttree = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", col_3v)?;
+        let mut buf: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut buf)?;
+        assert!(!buf.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_read() -> Result<(),Box<dyn error::Error>>{
+        let mut col_3v: Collection<ThreeVec> = Collection::empty();
+        for _i in 0..9 {col_3v.push(ThreeVec::new(1.0,2.0,3.0));}
+        let mut ttree = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", col_3v)?;
+
+        let mut buf: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut buf)?;
+        let ftree: FeedTree<ThreeVec> = FeedTree::read_msg_from(&buf[..])?;
+        assert_eq!(ftree,ttree);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_merge() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Seed", 1)?;
+        ttree.add_feed("fcol", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        let mut other = FeedTree::new("Test_Tree","ThreeVec");
+        other.add_field("Seed", 2)?;
+        other.add_feed("fcol", Collection::from(vec![ThreeVec::new(4.0,5.0,6.0)]))?;
+        other.add_feed("fcol1", Collection::from(vec![ThreeVec::new(7.0,8.0,9.0)]))?;
+
+        assert!(matches!(ttree.merge(other.clone(), MergePolicy::Error),Err(CalcifyError::MergeError{..})));
+        assert_eq!(ttree.get_feed("fcol").unwrap().len(),1);
+        let wrong: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","Object");
+        assert!(matches!(ttree.merge(wrong, MergePolicy::KeepFirst),Err(CalcifyError::MergeError{source: Some(_),..})));
+
+        ttree.merge(other, MergePolicy::KeepFirst)?;
+        assert_eq!(ttree.get_field("Seed"),Some(&FieldValue::Int(1)));
+        assert_eq!(*ttree.get_feed("fcol").unwrap(),Collection::from(vec![ThreeVec::new(1.0,2.0,3.0),ThreeVec::new(4.0,5.0,6.0)]));
+        assert_eq!(*ttree.get_feed("fcol1").unwrap(),Collection::from(vec![ThreeVec::new(7.0,8.0,9.0)]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_json() -> Result<(),Box<dyn error::Error>>{
+        let mut col_3v: Collection<ThreeVec> = Collection::empty();
+        for _i in 0..9 {col_3v.push(ThreeVec::random(1.0));}
+        let mut ttree = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", col_3v.clone())?;
+        ttree.add_feed("fcol1", col_3v.clone())?;
+        ttree.add_feed("fcol2", col_3v)?;
+        let pp = ttree.to_json();
+        let oo = FeedTree::<ThreeVec>::from_json(&pp)?;
+        println!("{:?}",oo);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_json_escaped() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+        ttree.add_field("Desc", "Line one\nLine \"two\" \\ ∑")?;
+        ttree.add_feed("fcol", Collection::from(vec![1.0,2.0]))?;
+        let oo = FeedTree::<f64>::from_json(&ttree.to_json())?;
+        assert_eq!(oo,ttree);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_msg() -> Result<(),Box<dyn error::Error>>{
+        let mut col_3v: Collection<ThreeVec> = Collection::empty();
+        for _i in 0..9 {col_3v.push(ThreeVec::random(1.0));}
+        let mut ttree = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", col_3v.clone())?;
+        ttree.add_feed("fcol2", col_3v.clone())?;
+        assert!(matches!(ttree.add_feed("fcol", Collection::empty()),Err(CalcifyError::KeyError{key}) if key == "fcol"));
+        assert_eq!(ttree.get_feed("fcol"),Some(&col_3v));
+        let pp = ttree.to_msg().unwrap();
+        let (oo,_) = FeedTree::<ThreeVec>::from_msg(&pp).unwrap();
+        assert_eq!(oo,ttree);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ftree_container() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        ttree.add_feed("empty", Collection::empty())?;
+        let cont = ttree.to_container()?;
+        let oo: FeedTree<ThreeVec> = FeedTree::read_msg_from(&cont[..])?;
+        assert_eq!(oo,ttree);
+
+        let mut states: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","ThreeVec");
+        states.add_feed("fcol", (0..1000).map(|i| ThreeVec::new(i as f64,0.0,1.0)).collect())?;
+        let z = states.to_container_with(Codec::Deflate)?;
+        assert!(z.len() < states.to_container()?.len());
+        assert_eq!(FeedTree::<ThreeVec>::from_container(&z)?,states);
+
+        let err = FeedTree::<f64>::read_msg_from(&cont[..]).err().unwrap();
+        let schema = err.downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(schema,CalcifyError::SchemaError{what,..} if what == "feed \"fcol\""));
+        assert!(error::Error::source(schema).is_some());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_ftree_serde() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","ThreeVec");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_feed("fcol", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        assert_eq!(FeedTree::<ThreeVec>::from_msg(&rmp_serde::to_vec(&ttree)?)?.0,ttree);
+        assert_eq!(rmp_serde::from_slice::<FeedTree<ThreeVec>>(&ttree.to_msg()?)?,ttree);
+        assert_eq!(FeedTree::<ThreeVec>::from_json(&serde_json::to_string(&ttree)?)?,ttree);
+        assert_eq!(serde_json::from_str::<FeedTree<ThreeVec>>(&ttree.to_json())?,ttree);
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::error;
+
+use std::io::prelude::*;
+use std::io::BufWriter;
+use std::fs;
+
+use super::FeedTree;
+use super::Collection;
+
+use crate::utils;
+use utils::{Serializable, Deserializable, FieldValue};
+use utils::errors::CalcifyError;
+
+extern crate rmp;
+use rmp::encode::*;
+use rmp::decode::*;
+
+/// Number of records buffered per feed before a frame is written, unless set with `set_chunk_size`.
+const DEFAULT_CHUNK_SIZE: usize = 64;
+
+/// FeedTree that is written to disk as it is filled
+///
+/// The file is a sequence of frames, each a MsgPack u32 length followed by either a map of metadata,
+/// or an array of a feed name and a chunk of that feed's Collection. Records are buffered per feed and
+/// written out a chunk at a time, so a crash loses at most the records not yet flushed.
+///
+/// Frames have no checksums, unlike the Branches of a container. Write the FeedTree out with `write_container` once
+/// the run is done if the file is to be kept.
+///
+/// Read the file back into a normal FeedTree with `FeedTree::read_stream`. A FeedStream can also
+/// write to any `io::Write`, see `create_to`, and be read back with `FeedTree::from_stream`.
+///
+/// # Example
+/// ```
+/// use calcify::FeedStream;
+/// use calcify::FeedTree;
+/// use calcify::Collection;
+/// # use std::error;
+/// # fn main() -> Result<(),Box<dyn error::Error>> {
+/// # let path = std::env::temp_dir().join("calcify_doc_stream.msg");
+/// # let filename = path.to_str().unwrap();
+///
+/// let mut stream: FeedStream<f64> = FeedStream::create(filename,"Test_Tree","f64")?;
+/// stream.add_field("Desc", "This is a FeedStream for testing.")?;
+/// stream.add_feed("fcol", Collection::from(vec![0.0,0.0]))?;
+/// stream.write("fcol", 1.0)?;
+/// stream.write("fcol", 2.0)?;
+/// stream.finish()?;
+///
+/// let mut ftree: FeedTree<f64> = FeedTree::read_stream(filename)?;
+/// assert_eq!(Collection::from(vec![0.0,0.0,1.0,2.0]),*ftree.get_feed("fcol").unwrap());
+/// # std::fs::remove_file(filename)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FeedStream<T: Serializable, W: Write = BufWriter<fs::File>> {
+    file: W,
+    fields: Vec<String>,
+    buffers: HashMap<String,Collection<T>>,
+    chunk_size: usize,
+}
+
+impl<T: Serializable> FeedStream<T> {
+    /// Creates the file and writes the Name and SubType metadata
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - string, an existing file is truncated
+    /// * `name` - string
+    /// * `subtype` - string, must match given Serializable type
+    pub fn create(filename: &str, name: &str, subtype: &str) -> Result<FeedStream<T>, Box<dyn error::Error>> {
+        FeedStream::create_to(BufWriter::new(fs::File::create(filename)?), name, subtype)
+    }
+}
+
+impl<T: Serializable, W: Write> FeedStream<T,W> {
+    /// Writes the Name and SubType metadata to a writer, like a `&mut Vec<u8>`, and streams to it
+    ///
+    /// # Arguments
+    ///
+    /// * `wr` - io::Write, flushed after every frame
+    /// * `name` - string
+    /// * `subtype` - string, must match given Serializable type
+    pub fn create_to(wr: W, name: &str, subtype: &str) -> Result<FeedStream<T,W>, Box<dyn error::Error>> {
+        let mut stream = FeedStream {
+            file: wr,
+            fields: Vec::new(),
+            buffers: HashMap::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        };
+        stream.add_field("Name", name)?;
+        stream.add_field("SubType", subtype)?;
+        Ok(stream)
+    }
+
+    /// Sets the number of records buffered per feed before they are written to disk
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Writes a metadata field to disk immediately, see `FeedTree::add_field`
+    pub fn add_field<V: Into<FieldValue>>(&mut self, key: &str, f: V) -> Result<(), Box<dyn error::Error>> {
+        if self.fields.iter().any(|k| k == key) {
+            return Err(Box::new(CalcifyError::key(key)));
+        }
+        let mut frame = Vec::new();
+        write_map_len(&mut frame, 1)?;
+        write_str(&mut frame, key)?;
+        frame.append(&mut f.into().to_msg()?);
+        self.write_frame(&frame)?;
+        self.fields.push(String::from(key));
+        Ok(())
+    }
+
+    /// Starts a new feed and writes its initial Collection to disk immediately
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Hash key, String
+    /// * `f` - Collection<T: Serializable>, may be empty
+    pub fn add_feed(&mut self, key: &str, f: Collection<T>) -> Result<(), Box<dyn error::Error>> {
+        if self.buffers.contains_key(key) {
+            return Err(Box::new(CalcifyError::key(key)));
+        }
+        self.write_chunk(key, &f)?;
+        self.buffers.insert(String::from(key),Collection::empty());
+        Ok(())
+    }
+
+    /// Buffers a record, writing the feed's chunk to disk once it is full
+    pub fn write(&mut self, key: &str, data: T) -> Result<(), Box<dyn error::Error>> {
+        let full = match self.buffers.get_mut(key) {
+            Some(buffer) => {
+                buffer.push(data);
+                buffer.len() >= self.chunk_size
+            },
+            None => return Err(Box::new(CalcifyError::key(key))),
+        };
+        if full {
+            self.flush_feed(key)?;
+        }
+        Ok(())
+    }
+
+    /// Writes every buffered record to disk
+    pub fn flush(&mut self) -> Result<(), Box<dyn error::Error>> {
+        let keys: Vec<String> = self.buffers.keys().cloned().collect();
+        for key in keys {
+            self.flush_feed(&key)?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes the stream
+    pub fn finish(mut self) -> Result<(), Box<dyn error::Error>> {
+        self.flush()
+    }
+
+    fn flush_feed(&mut self, key: &str) -> Result<(), Box<dyn error::Error>> {
+        let chunk = match self.buffers.get_mut(key) {
+            Some(buffer) if buffer.len() > 0 => std::mem::replace(buffer, Collection::empty()),
+            _ => return Ok(()),
+        };
+        self.write_chunk(key, &chunk)
+    }
+
+    fn write_chunk(&mut self, key: &str, chunk: &Collection<T>) -> Result<(), Box<dyn error::Error>> {
+        let mut frame = Vec::new();
+        write_array_len(&mut frame, 2)?;
+        write_str(&mut frame, key)?;
+        frame.append(&mut chunk.to_msg()?);
+        self.write_frame(&frame)
+    }
+
+    fn write_frame(&mut self, frame: &[u8]) -> Result<(), Box<dyn error::Error>> {
+        let mut buf = Vec::with_capacity(frame.len()+5);
+        write_u32(&mut buf, frame.len() as u32)?;
+        buf.extend_from_slice(frame);
+        self.file.write_all(&buf)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Flushes any buffered records, ignoring errors. Call `finish` to see them.
+impl<T: Serializable, W: Write> Drop for FeedStream<T,W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<T: Serializable + Deserializable> FeedTree<T> {
+    /// Read a file written by a FeedStream into a FeedTree
+    ///
+    /// This recovers the file left by a crash: a truncated final frame, as left mid write, is dropped rather than
+    /// returned as `CalcifyError::TruncatedError`, and every complete frame before it is read. See `from_stream`.
+    pub fn read_stream(filename: &str) -> Result<FeedTree<T>, Box<dyn error::Error>> {
+        Self::from_stream(&fs::read(filename)?)
+    }
+
+    /// Reassemble the frames written by a FeedStream into a FeedTree
+    ///
+    /// Deliberately lenient at the end: a final frame cut short, or a length prefix cut short, is ignored, since that is
+    /// what a crash mid write leaves. A frame length corrupted to point past the end looks the same, so everything from
+    /// that frame on is lost without an error. Errors give the byte offset of the frame that failed.
+    pub fn from_stream(all: &[u8]) -> Result<FeedTree<T>, Box<dyn error::Error>> {
+        let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+        let mut datafeeds: HashMap<String,Collection<T>> = HashMap::new();
+        let mut bytes = all;
+        while bytes.len() >= 5 {
+            let pos = all.len() - bytes.len();
+            let bad_frame = || CalcifyError::parse("FeedStream frame").at(pos);
+            let mut frame = bytes;
+            let len = read_u32(&mut frame).map_err(|e| bad_frame().caused_by(e))? as usize;
+            if len > frame.len() {
+                break;
+            } else if len == 0 {
+                return Err(Box::new(bad_frame()));
+            }
+            bytes = &frame[len..];
+            let mut frame = &frame[..len];
+            match rmp::Marker::from_u8(frame[0]) {
+                rmp::Marker::FixArray(_) | rmp::Marker::Array16 | rmp::Marker::Array32 => {
+                    if read_array_len(&mut frame)? != 2 {
+                        return Err(Box::new(bad_frame()));
+                    }
+                    let (key,rest) = read_str_from_slice(frame).map_err(|_| bad_frame())?;
+                    let (chunk,_) = Collection::<T>::from_msg(rest)
+                        .map_err(|e| CalcifyError::parse(&format!("feed {:?}",key)).at(pos).caused_by_boxed(e))?;
+                    datafeeds.entry(String::from(key)).or_insert_with(Collection::empty).extend(chunk);
+                },
+                _ => {
+                    let flen = read_map_len(&mut frame)?;
+                    for _ in 0..flen {
+                        let (key,rest) = read_str_from_slice(frame).map_err(|_| bad_frame())?;
+                        let (value,rest) = FieldValue::from_msg(rest).map_err(|e| bad_frame().caused_by_boxed(e))?;
+                        metadata.insert(String::from(key),value);
+                        frame = rest;
+                    }
+                },
+            }
+        }
+        Ok(FeedTree{metadata, datafeeds})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThreeVec;
+
+    #[test]
+    fn test_stream_roundtrip() -> Result<(),Box<dyn error::Error>>{
+        let mut ftree: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","ThreeVec");
+        ftree.add_field("Desc", "This is a FeedStream for testing.")?;
+        ftree.add_feed("pos", Collection::from(vec![ThreeVec::new(0.0,0.0,0.0)]))?;
+        ftree.add_feed("vel", Collection::empty())?;
+
+        let mut buf: Vec<u8> = Vec::new();
+        let mut stream: FeedStream<ThreeVec,_> = FeedStream::create_to(&mut buf,"Test_Tree","ThreeVec")?;
+        stream.set_chunk_size(4);
+        stream.add_field("Desc", "This is a FeedStream for testing.")?;
+        stream.add_feed("pos", Collection::from(vec![ThreeVec::new(0.0,0.0,0.0)]))?;
+        stream.add_feed("vel", Collection::empty())?;
+        assert!(stream.add_feed("vel", Collection::empty()).is_err());
+        assert!(stream.add_field("Name", "Again").is_err());
+        assert!(stream.write("acc", ThreeVec::new(0.0,0.0,0.0)).is_err());
+        for i in 0..10 {
+            let x = i as f64;
+            stream.write("pos", ThreeVec::new(x,x,x))?;
+            stream.write("vel", ThreeVec::new(1.0,0.0,x))?;
+            ftree.write("pos", ThreeVec::new(x,x,x))?;
+            ftree.write("vel", ThreeVec::new(1.0,0.0,x))?;
+        }
+        stream.finish()?;
+
+        let oo: FeedTree<ThreeVec> = FeedTree::from_stream(&buf)?;
+        assert_eq!(oo,ftree);
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_truncated() -> Result<(),Box<dyn error::Error>>{
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut stream: FeedStream<f64,_> = FeedStream::create_to(&mut bytes,"Test_Tree","f64")?;
+        stream.set_chunk_size(2);
+        stream.add_feed("fcol", Collection::empty())?;
+        for i in 0..6 {
+            stream.write("fcol", i as f64)?;
+        }
+        stream.finish()?;
+
+        let whole = FeedTree::<f64>::from_stream(&bytes)?;
+        assert_eq!(*whole.datafeeds.get("fcol").unwrap(),Collection::from(vec![0.0,1.0,2.0,3.0,4.0,5.0]));
+        let cut = FeedTree::<f64>::from_stream(&bytes[..bytes.len()-3])?;
+        assert_eq!(*cut.datafeeds.get("fcol").unwrap(),Collection::from(vec![0.0,1.0,2.0,3.0]));
+        assert_eq!(cut.metadata["SubType"],"f64");
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::utils;
+use utils::FieldValue;
+use utils::errors::CalcifyError;
+
+/// How `Tree::merge` and `FeedTree::merge` combine a metadata field that both sides have, with different values
+///
+/// Fields with equal values, or on only one side, are always kept.
+///
+/// # Example
+/// ```
+/// use calcify::{Tree, MergePolicy};
+///
+/// let mut run_1 = Tree::new("Run");
+/// run_1.add_field("Seed", 1).unwrap();
+/// let mut run_2 = Tree::new("Run");
+/// run_2.add_field("Seed", 2).unwrap();
+///
+/// assert!(run_1.merge(run_2, MergePolicy::Error).is_err());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MergePolicy {
+    /// Keep the value of the Tree being merged into
+    KeepFirst,
+    /// Take the value of the Tree being merged in
+    KeepLast,
+    /// Fail with `CalcifyError::MergeError`, naming the field
+    Error,
+}
+
+impl MergePolicy {
+    /// Errors on a field of both with different values, if the policy is `Error`
+    pub(crate) fn check_fields(self, ours: &HashMap<String,FieldValue>, theirs: &HashMap<String,FieldValue>) -> Result<(),CalcifyError> {
+        if self != MergePolicy::Error {
+            return Ok(());
+        }
+        let conflict = theirs.iter()
+            .filter(|(key,value)| ours.get(*key).is_some_and(|ours| ours != *value))
+            .map(|(key,_)| key)
+            .min();
+        match conflict {
+            Some(key) => Err(CalcifyError::merge(&format!("field {:?}",key))),
+            None => Ok(()),
+        }
+    }
+
+    /// Moves the fields of `theirs` into `ours`, after `check_fields`
+    pub(crate) fn merge_fields(self, ours: &mut HashMap<String,FieldValue>, theirs: HashMap<String,FieldValue>) {
+        for (key,value) in theirs {
+            match ours.entry(key) {
+                Entry::Vacant(entry) => { entry.insert(value); },
+                Entry::Occupied(mut entry) => if self == MergePolicy::KeepLast { entry.insert(value); },
+            }
+        }
+    }
+}
@@ -8,15 +8,29 @@ pub use branch::Bin;
 pub use branch::Point;
 pub use branch::PointBin;
 pub use branch::Branch;
+pub use branch::BranchInfo;
+pub use branch::RawBranch;
+pub use branch::TreeSubtype;
+use branch::SubtypeDecoder;
 
 mod feedtree;
 
 pub use feedtree::FeedTree;
+pub use feedtree::FeedStream;
+
+mod tree_file;
+
+pub use tree_file::TreeFile;
+
+mod merge;
+
+pub use merge::MergePolicy;
 
 use crate::utils;
-use utils::{Serializable, Deserializable};
+use utils::{Serializable, Deserializable, JsonValue, FieldValue};
 use utils::errors::CalcifyError;
 use utils::io::{ToFile,FromFile};
+use utils::container::{self, Block, Codec};
 
 extern crate rmp;
 use rmp::encode::*;
@@ -24,8 +38,9 @@ use rmp::decode::*;
 
 /// Tree of Collections for saving to a file.
 pub struct Tree {
-    metadata: HashMap<String,String>,
+    metadata: HashMap<String,FieldValue>,
     branches: HashMap<String,Branch>,
+    registry: HashMap<String,SubtypeDecoder>,
 }
 
 impl Tree {
@@ -49,48 +64,177 @@ impl Tree {
     /// let col_hist: Collection<Bin> = v3_col.map(ThreeVec::r).hist(50);
     /// let mut ttree = Tree::new("Test_Tree");
     /// ttree.add_field("Desc", "This is a Tree for testing.");
-    /// ttree.add_branch("fcol", f_col, "f64");
-    /// ttree.add_branch("col_3v", v3_col, "ThreeVec");
-    /// ttree.add_branch("hist_3v", col_hist, "Bin");
+    /// ttree.add_branch("fcol", f_col);
+    /// ttree.add_branch("col_3v", v3_col);
+    /// ttree.add_branch("hist_3v", col_hist);
     /// ```
     pub fn new(name: &str) -> Tree {
         let mut md = HashMap::new();
-        md.insert(String::from("Name"),String::from(name));
+        md.insert(String::from("Name"),FieldValue::from(name));
         let br = HashMap::new();
         Tree {
             metadata: md,
             branches: br,
+            registry: HashMap::new(),
         }
     }
 
-    pub fn add_field(&mut self, key: &str, f: &str) -> Result<(),CalcifyError> {
-        if let Some(_) = self.metadata.insert(String::from(key),String::from(f)) {
-            return Err(CalcifyError::KeyError);
+    /// Registers a user defined subtype, so Branches of that subtype can be added, and decoded when read from a file.
+    ///
+    /// Branches of a subtype that is not built in are read undecoded, and are written back out unchanged.
+    /// Registering the subtype decodes every such Branch already in the Tree into a Collection<T>.
+    ///
+    /// # Arguments
+    ///
+    /// * `subtype` - Name written to the file for Collection<T>. May be "Object" to decode files written with that subtype.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `subtype` is built in, or already registered
+    /// * `CalcifyError::ParseError` if an existing Branch of `subtype` does not decode as Collection<T>.
+    ///   The Tree is left unchanged, with the subtype unregistered and every Branch of it undecoded.
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::Tree;
+    /// use calcify::Collection;
+    /// use calcify::Serializable;
+    /// use calcify::Deserializable;
+    ///
+    /// let mut ttree = Tree::new("Test_Tree");
+    /// ttree.register::<u64>("Counts").unwrap();
+    /// ttree.add_branch_as("counts", Collection::from(vec![1u64,2,3]), "Counts").unwrap();
+    /// assert_eq!(ttree.get_branch("counts").unwrap().subtype(),"Counts");
+    ///
+    /// let (mut read_tree,_) = Tree::from_msg(&ttree.to_msg().unwrap()).unwrap();
+    /// assert!(read_tree.get_branch("counts").unwrap().is_raw());
+    /// read_tree.register::<u64>("Counts").unwrap();
+    /// assert!(!read_tree.get_branch("counts").unwrap().is_raw());
+    /// assert_eq!(read_tree.read_branch::<u64>("counts").unwrap(),Collection::from(vec![1u64,2,3]));
+    /// ```
+    pub fn register<T: 'static + Serializable + Deserializable>(&mut self, subtype: &str) -> Result<(),CalcifyError> {
+        if SubtypeDecoder::builtin(subtype).is_some() || self.registry.contains_key(subtype) {
+            return Err(CalcifyError::key(subtype));
         }
+        let decoder = SubtypeDecoder::of::<T>();
+        let mut decoded: Vec<(String,Branch)> = Vec::new();
+        for (name, branch) in self.branches.iter().filter(|(_,b)| b.subtype() == subtype) {
+            if let Some(branch) = branch.decoded(decoder).map_err(|e| CalcifyError::parse(&format!("branch {:?}",name)).caused_by_boxed(e))? {
+                decoded.push((name.clone(),branch));
+            }
+        }
+        self.branches.extend(decoded);
+        self.registry.insert(String::from(subtype),decoder);
         Ok(())
     }
 
-    /// Inserts new branch into Tree.
+    /// Inserts a new metadata field
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Hash key, String
+    /// * `f` - Anything that converts into a FieldValue, e.g. &str, i64, f64, bool or SystemTime
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is already in the Tree. The field is left unchanged, use `set_field` to replace it.
+    pub fn add_field<V: Into<FieldValue>>(&mut self, key: &str, f: V) -> Result<(),CalcifyError> {
+        if self.metadata.contains_key(key) {
+            return Err(CalcifyError::key(key));
+        }
+        self.metadata.insert(String::from(key),f.into());
+        Ok(())
+    }
+
+    /// Inserts or replaces a metadata field, returning the old value
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::{Tree, FieldValue};
+    ///
+    /// let mut tree = Tree::new("Test_Tree");
+    /// assert_eq!(tree.set_field("dt", 0.01),None);
+    /// assert_eq!(tree.set_field("dt", 0.02),Some(FieldValue::Float(0.01)));
+    /// assert_eq!(tree.get_field("dt").and_then(FieldValue::as_f64),Some(0.02));
+    /// assert_eq!(tree.get_field("Name").unwrap(),"Test_Tree");
+    /// assert_eq!(tree.remove_field("dt"),Some(FieldValue::Float(0.02)));
+    /// assert!(tree.fields().all(|(key,_)| key != "dt"));
+    /// ```
+    pub fn set_field<V: Into<FieldValue>>(&mut self, key: &str, f: V) -> Option<FieldValue> {
+        self.metadata.insert(String::from(key),f.into())
+    }
+
+    /// Returns a metadata field
+    pub fn get_field(&self, key: &str) -> Option<&FieldValue> {
+        self.metadata.get(key)
+    }
+
+    /// Removes a metadata field, returning its value
+    pub fn remove_field(&mut self, key: &str) -> Option<FieldValue> {
+        self.metadata.remove(key)
+    }
+
+    /// Iterates over the metadata fields, in no particular order
+    pub fn fields(&self) -> impl Iterator<Item=(&str,&FieldValue)> {
+        self.metadata.iter().map(|(k,v)| (k.as_str(),v))
+    }
+
+    /// Inserts new branch into Tree, with the subtype of T.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Hash key, String
+    /// * `b` - Branch,  Collection<T: Serializable + TreeSubtype>
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is already in the Tree. The existing Branch is left unchanged.
+    /// * `CalcifyError::SubtypeError` if `T::NAME` is built in or registered for a different type
+    pub fn add_branch<T: 'static + Serializable + TreeSubtype>(&mut self, key: &str, b: Collection<T>) -> Result<(),CalcifyError> {
+        self.check_subtype::<T>(T::NAME)?;
+        self.insert_branch(key, T::NAME, b)
+    }
+
+    /// Inserts new branch into Tree, with an explicit subtype.
+    ///
+    /// For types without a TreeSubtype, and for writing files with the `Object` subtype.
     ///
     /// # Arguments
     ///
     /// * `key` - Hash key, String
     /// * `b` - Branch,  Collection<T: Serializable>
-    /// * `t` - Collection subtype,  String, one of "f64", "String", "ThreeVec", "ThreeMat", "FourVec", "FourMat", "Bin", "Point", "Object"
+    /// * `t` - Collection subtype,  String, one of the built in TreeSubtype names, "Object", or a subtype added with `register`
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// * `t` is invalid
-    pub fn add_branch<T: 'static + Serializable>(&mut self, key: &str, b: Collection<T>, t: &str) -> Result<(),CalcifyError> {
-        let types = ["f64","String","ThreeVec","ThreeMat","FourVec","FourMat","Bin","Point","PointBin","Object"];
-        if types.contains(&t) {
-            let br = Branch::new(String::from(t),Box::new(b));
-            if let Some(_) = self.branches.insert(String::from(key),br) {
-                return Err(CalcifyError::KeyError);
-            }
-            Ok(())
-        } else {
-            panic!("Subtype must be one of \"f64\", \"String\", \"ThreeVec\", \"ThreeMat\", \"FourVec\", \"FourMat\", \"Bin\", \"Point\", \"PointBin\", \"Object\" not {}",t);
+    /// * `CalcifyError::KeyError` if `key` is already in the Tree. The existing Branch is left unchanged.
+    /// * `CalcifyError::SubtypeError` if `t` is unknown, or built in or registered for a different type
+    pub fn add_branch_as<T: 'static + Serializable>(&mut self, key: &str, b: Collection<T>, t: &str) -> Result<(),CalcifyError> {
+        if t != "Object" && self.decoder(t).is_none() {
+            return Err(CalcifyError::SubtypeError{subtype: String::from(t), expected: String::from(std::any::type_name::<T>())});
+        }
+        self.check_subtype::<T>(t)?;
+        self.insert_branch(key, t, b)
+    }
+
+    fn insert_branch<T: 'static + Serializable>(&mut self, key: &str, t: &str, b: Collection<T>) -> Result<(),CalcifyError> {
+        if self.branches.contains_key(key) {
+            return Err(CalcifyError::key(key));
+        }
+        self.branches.insert(String::from(key),Branch::new(String::from(t),Box::new(b)));
+        Ok(())
+    }
+
+    /// Returns the SubtypeDecoder of a built in or registered subtype
+    fn decoder(&self, subtype: &str) -> Option<SubtypeDecoder> {
+        SubtypeDecoder::builtin(subtype).or_else(|| self.registry.get(subtype).copied())
+    }
+
+    /// Errors if `subtype` is built in or registered for a type other than T
+    fn check_subtype<T: 'static>(&self, subtype: &str) -> Result<(),CalcifyError> {
+        match self.decoder(subtype) {
+            Some(decoder) if !decoder.is::<T>() => Err(CalcifyError::SubtypeError{subtype: String::from(subtype), expected: String::from(std::any::type_name::<T>())}),
+            _ => Ok(()),
         }
     }
 
@@ -110,8 +254,8 @@ impl Tree {
     /// let f_col: Collection<f64> = Collection::from(vec![0.0,0.0]);
     /// let b_col: Collection<Bin> = Collection::from(vec![Bin::new(0.0,1.0,10),Bin::new(1.0,2.0,10),Bin::new(2.0,3.0,10)]);
     /// let mut ttree = Tree::new("Test_Tree");
-    /// ttree.add_branch("fcol", f_col, "f64").expect("KeyError");
-    /// ttree.add_branch("bCol", b_col, "Bin").expect("KeyError");
+    /// ttree.add_branch("fcol", f_col).expect("KeyError");
+    /// ttree.add_branch("bCol", b_col).expect("KeyError");
     ///
     /// let ex_f_col: Collection<f64> = ttree.get_branch("fcol").unwrap().extract().unwrap();
     /// let mut ex_b_col: Collection<Bin> = ttree.get_branch("bCol").unwrap().extract().unwrap();
@@ -123,12 +267,147 @@ impl Tree {
         self.branches.get_mut(&String::from(key))
     }
 
+    /// Returns the names of the Branches, in no particular order
+    pub fn branch_names(&self) -> Vec<&str> {
+        self.branches.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Iterates over the Branches and their names, in no particular order
+    pub fn branches(&self) -> impl Iterator<Item=(&str,&Branch)> {
+        self.branches.iter().map(|(k,v)| (k.as_str(),v))
+    }
+
+    /// Returns the subtype, element count and msg size of a Branch, without decoding it
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is not in the Tree
+    /// * `CalcifyError::ParseError` if the Branch is not a msg array
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use calcify::Tree;
+    /// use calcify::Collection;
+    ///
+    /// let mut ttree = Tree::new("Test_Tree");
+    /// ttree.add_branch("fcol", Collection::from(vec![0.0,1.0,2.0])).expect("KeyError");
+    ///
+    /// let info = ttree.branch_info("fcol").unwrap();
+    /// assert_eq!(info.subtype,"f64");
+    /// assert_eq!(info.len,3);
+    /// assert!(ttree.branch_info("none").is_err());
+    /// ```
+    pub fn branch_info(&self, key: &str) -> Result<BranchInfo, CalcifyError> {
+        let branch = self.branches.get(key).ok_or_else(|| CalcifyError::key(key))?;
+        branch.info().map_err(|e| CalcifyError::parse(&format!("branch {:?}",key)).caused_by_boxed(e))
+    }
+
+    /// Removes a Branch from the Tree, returning it
+    pub fn remove_branch(&mut self, key: &str) -> Option<Branch> {
+        self.branches.remove(key)
+    }
+
+    /// Moves a Branch to a new name
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `from` is not in the Tree, or `to` already is. The Tree is unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use calcify::Tree;
+    /// use calcify::Collection;
+    ///
+    /// let mut ttree = Tree::new("Test_Tree");
+    /// ttree.add_branch("fcol", Collection::from(vec![0.0,1.0])).expect("KeyError");
+    /// ttree.add_branch("gcol", Collection::from(vec![2.0])).expect("KeyError");
+    ///
+    /// assert!(ttree.rename_branch("fcol","gcol").is_err());
+    /// ttree.rename_branch("fcol","energy").unwrap();
+    /// assert_eq!(ttree.branch_info("energy").unwrap().len,2);
+    /// assert!(ttree.remove_branch("fcol").is_none());
+    /// ```
+    pub fn rename_branch(&mut self, from: &str, to: &str) -> Result<(),CalcifyError> {
+        if from == to {
+            return if self.branches.contains_key(from) { Ok(()) } else { Err(CalcifyError::key(from)) };
+        }
+        if self.branches.contains_key(to) {
+            return Err(CalcifyError::key(to));
+        }
+        let branch = self.branches.remove(from).ok_or_else(|| CalcifyError::key(from))?;
+        self.branches.insert(String::from(to),branch);
+        Ok(())
+    }
+
+    /// Merges another Tree into this one, e.g. the output of another run of the same simulation
+    ///
+    /// Branches only in `other` are moved over. Branches in both must have the same subtype,
+    /// `Bin` and `PointBin` histograms have their counts added and must have the same edges,
+    /// and all other Collections are appended to. Metadata fields in both with different values are resolved by `policy`.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::MergeError` naming the Branch or field that disagrees, with the `SubtypeError`, `BinningError` or `LengthError` as its source. The Tree is unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use calcify::{Tree, Collection, Bin, MergePolicy};
+    ///
+    /// let mut run_1 = Tree::new("Run");
+    /// run_1.add_field("Seed", 1).unwrap();
+    /// run_1.add_branch("energy", Collection::from(vec![1.0,2.0])).unwrap();
+    /// run_1.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,2)])).unwrap();
+    /// let mut run_2 = Tree::new("Run");
+    /// run_2.add_field("Seed", 2).unwrap();
+    /// run_2.add_branch("energy", Collection::from(vec![3.0])).unwrap();
+    /// run_2.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,1)])).unwrap();
+    ///
+    /// run_1.merge(run_2, MergePolicy::KeepFirst).unwrap();
+    /// assert_eq!(run_1.read_branch::<f64>("energy").unwrap(),Collection::from(vec![1.0,2.0,3.0]));
+    /// assert_eq!(run_1.read_branch::<Bin>("hist").unwrap(),Collection::from(vec![Bin::new(0.0,1.0,3)]));
+    /// assert_eq!(run_1.get_field("Seed").unwrap().as_i64(),Some(1));
+    /// ```
+    pub fn merge(&mut self, mut other: Tree, policy: MergePolicy) -> Result<(),CalcifyError> {
+        policy.check_fields(&self.metadata, &other.metadata)?;
+        let mut keys: Vec<String> = other.branches.keys().filter(|k| self.branches.contains_key(*k)).cloned().collect();
+        keys.sort();
+        let mut merged: Vec<(String,Branch)> = Vec::new();
+        for key in keys {
+            let theirs = other.branches.get_mut(&key).ok_or_else(|| CalcifyError::key(&key))?;
+            let ours = self.branches.get_mut(&key).ok_or_else(|| CalcifyError::key(&key))?;
+            let was_raw = ours.is_raw();
+            let mut branch = ours.merge(theirs).map_err(|e| CalcifyError::merge(&format!("branch {:?}",key)).caused_by_boxed(e))?;
+            if branch.is_raw() && !was_raw {
+                if let Some(decoder) = self.decoder(branch.subtype()) {
+                    branch.decode(decoder).map_err(|e| CalcifyError::merge(&format!("branch {:?}",key)).caused_by_boxed(e))?;
+                }
+            }
+            merged.push((key,branch));
+        }
+        policy.merge_fields(&mut self.metadata, other.metadata);
+        for (subtype,decoder) in other.registry {
+            self.registry.entry(subtype).or_insert(decoder);
+        }
+        self.branches.extend(other.branches);
+        self.branches.extend(merged);
+        Ok(())
+    }
+
     /// Returns Collection from a Trees
     ///
     /// # Arguments
     ///
     /// `key` - String
     ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::KeyError` if `key` is not in the Tree
+    /// * `CalcifyError::SubtypeError` if the Branch subtype is not that of T, or registered for T. `Object` Branches can be read as any T.
+    /// * `CalcifyError::ParseError` if the Branch doesn't decode as a Collection<T>
+    ///
     /// # Example
     ///
     /// ```
@@ -139,8 +418,8 @@ impl Tree {
     /// let f_col: Collection<f64> = Collection::from(vec![0.0,0.0]);
     /// let b_col: Collection<Bin> = Collection::from(vec![Bin::new(0.0,1.0,10),Bin::new(1.0,2.0,10),Bin::new(2.0,3.0,10)]);
     /// let mut ttree = Tree::new("Test_Tree");
-    /// ttree.add_branch("fcol", f_col, "f64").expect("KeyError");
-    /// ttree.add_branch("bCol", b_col, "Bin").expect("KeyError");
+    /// ttree.add_branch("fcol", f_col).expect("KeyError");
+    /// ttree.add_branch("bCol", b_col).expect("KeyError");
     ///
     /// let ex_f_col: Collection<f64> = ttree.read_branch("fcol").unwrap();
     /// let mut ex_b_col: Collection<Bin> = ttree.read_branch("bCol").unwrap();
@@ -150,15 +429,17 @@ impl Tree {
     /// assert_eq!(Collection::from(vec![0.0,0.0]),ex_f_col);
     /// assert_eq!(Collection::from(vec![Bin::new(0.0,1.0,10),Bin::new(1.0,2.0,10),Bin::new(2.0,3.0,10)]),ex_b_col);
     /// ```
-    pub fn read_branch<T: Serializable + Deserializable>(&mut self, key: &str) -> Result<Collection<T>, CalcifyError> {
-        if let Some(branch) = self.branches.get_mut(&String::from(key)) {
-            if let Ok(collect) = branch.extract() {
-                return Ok(collect);
-            } else {
-                return Err(CalcifyError::ParseError);
-            }
+    pub fn read_branch<T: 'static + Serializable + Deserializable + TreeSubtype>(&mut self, key: &str) -> Result<Collection<T>, CalcifyError> {
+        let subtype = self.branches.get(key).ok_or_else(|| CalcifyError::key(key))?.subtype();
+        let matches = match self.decoder(subtype) {
+            Some(decoder) => decoder.is::<T>(),
+            None => subtype == T::NAME || subtype == "Object",
+        };
+        if !matches {
+            return Err(CalcifyError::SubtypeError{subtype: String::from(subtype), expected: String::from(T::NAME)});
         }
-        Err(CalcifyError::KeyError)
+        let branch = self.branches.get_mut(key).ok_or_else(|| CalcifyError::key(key))?;
+        branch.extract().map_err(|e| CalcifyError::parse(&format!("branch {:?}",key)).caused_by_boxed(e))
     }
 }
 
@@ -166,13 +447,11 @@ impl Serializable for Tree {
     fn to_json(&self) -> String {
         let mut out = String::from("{");
         for (key, val) in &self.metadata {
-            out.push_str(format!("\"{}\":\"{}\",",key,val).as_str());
+            out.push_str(format!("{}:{},",key.to_json(),val.to_json()).as_str());
         }
         out.push_str("\"branches\":{");
-        for (key, val) in &self.branches {
-            out.push_str(format!("\"{}\":{},",key,val.to_json()).as_str());
-        }
-        out.pop();
+        let str_vec: Vec<String> = self.branches.iter().map(|(key,val)| format!("{}:{}",key.to_json(),val.to_json())).collect();
+        out.push_str(&str_vec.join(","));
         out.push_str("}}");
         out
     }
@@ -182,7 +461,7 @@ impl Serializable for Tree {
         write_map_len(&mut buf, (self.metadata.len()+1) as u32)?;
         for (key, val) in &self.metadata {
             write_str(&mut buf, key)?;
-            write_str(&mut buf, val)?;
+            buf.append(&mut val.to_msg()?);
         }
         write_str(&mut buf, "branches")?;
         write_map_len(&mut buf, (self.branches.len()) as u32)?;
@@ -196,80 +475,592 @@ impl Serializable for Tree {
 
 impl Deserializable for Tree {
     fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
-        let mut metadata: HashMap<String,String> = HashMap::new();
+        Self::from_json_value(&JsonValue::parse(s)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let mut metadata: HashMap<String,FieldValue> = HashMap::new();
         let mut branches: HashMap<String,Branch> = HashMap::new();
-        for (i,mut dim) in s.split(",\"branches\":").enumerate() {
-            match i {
-                0 => {
-                    for pair in dim.trim_matches(|p| p == '{' || p == '"' ).split("\",\"") {
-                        let ar: Vec<&str> = pair.split("\":\"").collect();
-                        metadata.insert(String::from(ar[0]),String::from(ar[1]));
+        if value.get("branches").is_err() {
+            return Err(Box::new(container::missing("Tree","branches")));
+        }
+        for (key, val) in value.as_object()?.iter() {
+            match key.as_str() {
+                "branches" => {
+                    for (bkey, bval) in val.as_object()?.iter() {
+                        let branch = Branch::from_json_value(bval)
+                            .map_err(|e| CalcifyError::parse(&format!("branch {:?}",bkey)).caused_by_boxed(e))?;
+                        branches.insert(bkey.clone(),branch);
                     }
                 },
-                1 => {
-                    dim = dim.trim_matches(|p| p == '{' || p == '}' || p == '"' );
-                    for pair in dim.split("},\"") {
-                        let ar: Vec<&str> = pair.split("\":").collect();
-                        if let Ok(branch) = Branch::from_json(&ar[1..].join("\":")){
-                            branches.insert(String::from(ar[0]),branch);
-                        } else {
-                            return Err(Box::new(CalcifyError::ParseError));
-                        }
-                    }
+                _ => {
+                    let field = FieldValue::from_json_value(val)
+                        .map_err(|e| CalcifyError::parse(&format!("Tree metadata {:?}",key)).caused_by_boxed(e))?;
+                    metadata.insert(key.clone(),field);
                 },
-                _ => return Err(Box::new(CalcifyError::ParseError)),
             }
         }
-        Ok(Tree{metadata, branches})
+        Ok(Tree{metadata, branches, registry: HashMap::new()})
     }
 
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
-        let mut metadata: HashMap<String,String> = HashMap::new();
+        let mut metadata: HashMap<String,FieldValue> = HashMap::new();
         let mut branches: HashMap<String,Branch> = HashMap::new();
-        if let Ok(len) = read_map_len(&mut bytes) {
-            for _ in 0..len {
-                let mut unparsed: &[u8] = &bytes[..];
-                if let Ok((key,v_rest)) = read_str_from_slice(unparsed) {
-                    match key {
-                        "branches" => {
-                            bytes = v_rest;
-                            break;
-                        },
-                        _ => {
-                            if let Ok((value,rest)) = read_str_from_slice(v_rest) {
-                                unparsed = rest;
-                                metadata.insert(String::from(key),String::from(value));
-                            }
-                        },
-                    }
-
-                }
-                bytes = unparsed;
-            }
-            if let Ok(flen) = read_map_len(&mut bytes) {
-                for _ in 0..flen {
-                    let unparsed: &[u8] = &bytes[..];
-                    if let Ok((key,v_rest)) = read_str_from_slice(unparsed) {
-                        if let Ok((value,rest)) = Branch::from_msg(v_rest) {
-                            bytes = rest;
-                            branches.insert(String::from(key),value);
-                        }
-                    }
+        let mut found_branches = false;
+        let len = read_map_len(&mut bytes)?;
+        for _ in 0..len {
+            let (key,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("Tree key"))?;
+            bytes = rest;
+            if key == "branches" {
+                found_branches = true;
+                let blen = read_map_len(&mut bytes)?;
+                for _ in 0..blen {
+                    let (name,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("branch name"))?;
+                    let (branch,rest) = Branch::from_msg(rest)
+                        .map_err(|e| CalcifyError::parse(&format!("branch {:?}",name)).caused_by_boxed(e))?;
+                    branches.insert(String::from(name),branch);
+                    bytes = rest;
                 }
+            } else {
+                let (value,rest) = FieldValue::from_msg(bytes)
+                    .map_err(|e| CalcifyError::parse(&format!("Tree metadata {:?}",key)).caused_by_boxed(e))?;
+                metadata.insert(String::from(key),value);
+                bytes = rest;
             }
         }
-        Ok((Tree{metadata, branches},bytes))
+        if !found_branches {
+            return Err(Box::new(container::missing("Tree","branches")));
+        }
+        Ok((Tree{metadata, branches, registry: HashMap::new()},bytes))
+    }
+}
+
+impl ToFile for Tree {
+    fn to_container_with(&self, codec: Codec) -> Result<Vec<u8>, Box<dyn error::Error>> {
+        let mut names: Vec<&String> = self.branches.keys().collect();
+        names.sort();
+        let mut blocks: Vec<Block> = Vec::new();
+        for name in names {
+            let branch = &self.branches[name];
+            blocks.push(Block{name, subtype: branch.subtype(), bytes: branch.collection_msg()?});
+        }
+        container::write_container(&self.metadata, blocks, codec)
+    }
+}
+
+impl FromFile for Tree {
+    fn from_container(bytes: &[u8]) -> Result<Self, Box<dyn error::Error>> {
+        let header = container::read_header(&mut &bytes[..])?;
+        let mut branches: HashMap<String,Branch> = HashMap::new();
+        for entry in header.index.iter() {
+            let block = &header.block(entry, bytes)?;
+            let (branch,_) = Branch::from_subtype_msg(&entry.subtype, block).map_err(|e| container::decode_error(&format!("branch {:?}",entry.name),block,e))?;
+            branches.insert(entry.name.clone(),branch);
+        }
+        Ok(Tree{metadata: header.metadata, branches, registry: HashMap::new()})
     }
 }
 
-impl ToFile for Tree{}
-impl FromFile for Tree{}
+/// Serde writes a Tree in the same shape as json and msg.
+///
+/// Branch contents aren't typed in a Tree, so they go through a JsonValue, and are read back the same as by
+/// `from_json` and `from_msg`, decoded if the subtype is built in and raw otherwise.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Tree;
+    use crate::utils::{Serializable, Deserializable, JsonValue};
+    use serde::{Serialize, Serializer, Deserialize, Deserializer};
+    use serde::ser::Error as SerError;
+    use serde::de::Error as DeError;
+
+    impl Serialize for Tree {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let value = if serializer.is_human_readable() {
+                JsonValue::parse(&self.to_json()).map_err(S::Error::custom)?
+            } else {
+                let buf = self.to_msg().map_err(S::Error::custom)?;
+                JsonValue::from_msg(&buf).map_err(S::Error::custom)?.0
+            };
+            value.serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Tree {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let human_readable = deserializer.is_human_readable();
+            let value = JsonValue::deserialize(deserializer)?;
+            if human_readable {
+                Tree::from_json_value(&value).map_err(D::Error::custom)
+            } else {
+                let buf = value.to_msg().map_err(D::Error::custom)?;
+                Ok(Tree::from_msg(&buf).map_err(D::Error::custom)?.0)
+            }
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ThreeVec;
 
+    #[derive(Debug, PartialEq, Clone)]
+    struct Charge {
+        q: f64,
+        r: ThreeVec,
+    }
+
+    impl Serializable for Charge {
+        fn to_json(&self) -> String {
+            format!("{{\"q\":{},\"r\":{}}}",self.q.to_json(),self.r.to_json())
+        }
+
+        fn to_msg(&self) -> Result<Vec<u8>, ValueWriteError> {
+            let mut buf = Vec::new();
+            write_array_len(&mut buf, 2)?;
+            buf.append(&mut self.q.to_msg()?);
+            buf.append(&mut self.r.to_msg()?);
+            Ok(buf)
+        }
+    }
+
+    impl Deserializable for Charge {
+        fn from_json(s: &str) -> Result<Self, Box<dyn error::Error>> {
+            Self::from_json_value(&JsonValue::parse(s)?)
+        }
+
+        fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+            Ok(Charge{q: value.get("q")?.as_f64()?, r: ThreeVec::from_json_value(value.get("r")?)?})
+        }
+
+        fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+            if let Ok(2) = read_array_len(&mut bytes) {
+                let (q,rest) = f64::from_msg(bytes)?;
+                let (r,rest) = ThreeVec::from_msg(rest)?;
+                return Ok((Charge{q,r},rest));
+            }
+            Err(Box::new(CalcifyError::parse("Charge")))
+        }
+    }
+
+    impl TreeSubtype for Charge {
+        const NAME: &'static str = "Charge";
+    }
+
+    fn charges() -> Collection<Charge> {
+        Collection::from(vec![Charge{q:1.0,r:ThreeVec::new(1.0,2.0,3.0)},Charge{q:-1.0,r:ThreeVec::new(0.0,0.0,1.0)}])
+    }
+
+    #[test]
+    fn test_tree_register() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        assert!(ttree.register::<Charge>("Bin").is_err());
+        ttree.register::<Charge>("Charge")?;
+        assert!(ttree.register::<Charge>("Charge").is_err());
+        ttree.add_branch("charges", charges())?;
+        ttree.add_branch_as("old_charges", charges(), "Object")?;
+        ttree.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,2)]))?;
+
+        let (mut oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        assert!(oo.get_branch("charges").unwrap().is_raw());
+        assert!(!oo.get_branch("hist").unwrap().is_raw());
+        oo.register::<Charge>("Charge")?;
+        oo.register::<Charge>("Object")?;
+        assert!(!oo.get_branch("charges").unwrap().is_raw());
+        assert!(!oo.get_branch("old_charges").unwrap().is_raw());
+        assert_eq!(oo.read_branch::<Charge>("charges")?,charges());
+        assert_eq!(oo.read_branch::<Charge>("old_charges")?,charges());
+
+        let mut jj = Tree::from_json(&oo.to_json())?;
+        assert!(jj.get_branch("charges").unwrap().is_raw());
+        jj.register::<Charge>("Charge")?;
+        assert_eq!(jj.read_branch::<Charge>("charges")?,charges());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_register_failed() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch_as("charges", charges(), "Object")?;
+        ttree.add_branch_as("not_charges", Collection::from(vec![1.0]), "Object")?;
+
+        let (mut oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        assert!(matches!(oo.register::<Charge>("Object"),Err(CalcifyError::ParseError{..})));
+        assert!(oo.get_branch("charges").unwrap().is_raw());
+        assert!(oo.get_branch("not_charges").unwrap().is_raw());
+        assert!(matches!(oo.register::<Charge>("Object"),Err(CalcifyError::ParseError{..})));
+        assert!(oo.remove_branch("not_charges").is_some());
+        oo.register::<Charge>("Object")?;
+        assert_eq!(oo.read_branch::<Charge>("charges")?,charges());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_unknown_subtype() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.register::<Charge>("Charge")?;
+        ttree.add_branch("charges", charges())?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0]))?;
+        let pp = ttree.to_msg()?;
+
+        let (mut oo,rest) = Tree::from_msg(&pp)?;
+        assert!(rest.is_empty());
+        assert_eq!(oo.get_branch("charges").unwrap().subtype(),"Charge");
+        assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0]));
+        let (mut again,_) = Tree::from_msg(&oo.to_msg()?)?;
+        again.register::<Charge>("Charge")?;
+        assert_eq!(again.read_branch::<Charge>("charges")?,charges());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_std_subtypes() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch("names", Collection::from(vec![String::from("e-"),String::from("μ+")]))?;
+        ttree.add_branch("ids", Collection::from(vec![-11i32,13]))?;
+        ttree.add_branch("hits", Collection::from(vec![true,false]))?;
+        ttree.add_branch("counts", Collection::from(vec![3usize,0]))?;
+        for mut oo in [Tree::from_msg(&ttree.to_msg()?)?.0, Tree::from_json(&ttree.to_json())?] {
+            assert!(!oo.get_branch("names").unwrap().is_raw());
+            assert_eq!(oo.read_branch::<String>("names")?,Collection::from(vec![String::from("e-"),String::from("μ+")]));
+            assert_eq!(oo.read_branch::<i32>("ids")?,Collection::from(vec![-11i32,13]));
+            assert_eq!(oo.read_branch::<bool>("hits")?,Collection::from(vec![true,false]));
+            assert_eq!(oo.read_branch::<usize>("counts")?,Collection::from(vec![3usize,0]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_composite_subtypes() -> Result<(),Box<dyn error::Error>>{
+        let hits = Collection::from(vec![vec![1.0,2.5],vec![]]);
+        let fits = Collection::from(vec![Some(0.5),None]);
+        let points = Collection::from(vec![[1.0,2.0,3.0]]);
+        let pairs = Collection::from(vec![(1.0,-1.0)]);
+        let mut tags = HashMap::new();
+        tags.insert(String::from("run"),String::from("a"));
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch("hits", hits.clone())?;
+        ttree.add_branch("fits", fits.clone())?;
+        ttree.add_branch("points", points.clone())?;
+        ttree.add_branch("pairs", pairs.clone())?;
+        ttree.add_branch("tags", Collection::from(vec![tags.clone()]))?;
+        assert_eq!(ttree.get_branch("hits").unwrap().subtype(),"Vec<f64>");
+        assert_eq!(ttree.get_branch("points").unwrap().subtype(),"[f64;3]");
+        for mut oo in [Tree::from_msg(&ttree.to_msg()?)?.0, Tree::from_json(&ttree.to_json())?] {
+            assert!(!oo.get_branch("hits").unwrap().is_raw());
+            assert_eq!(oo.read_branch::<Vec<f64>>("hits")?,hits);
+            assert_eq!(oo.read_branch::<Option<f64>>("fits")?,fits);
+            assert_eq!(oo.read_branch::<[f64;3]>("points")?,points);
+            assert_eq!(oo.read_branch::<(f64,f64)>("pairs")?,pairs);
+            assert_eq!(oo.read_branch::<HashMap<String,String>>("tags")?,Collection::from(vec![tags.clone()]));
+            assert!(matches!(oo.read_branch::<Vec<i64>>("hits"),Err(CalcifyError::SubtypeError{..})));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_raw_roundtrip() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::from_json("{\"Name\":\"Test_Tree\",\"branches\":{\"not_bins\":{\"subtype\":\"Bin\",\"branch\":[1.0,2.0]}}}")?;
+        ttree.register::<Charge>("Charge")?;
+        ttree.add_branch("charges", charges())?;
+        let (mut oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        let raw = oo.get_branch("not_bins").unwrap().raw().unwrap().clone();
+        assert_eq!(raw.subtype(),"Bin");
+        assert!(oo.read_branch::<Bin>("not_bins").is_err());
+
+        oo.add_field("Desc", "Modified")?;
+        oo.add_branch("fcol", Collection::from(vec![1.0]))?;
+        let (mut again,_) = Tree::from_msg(&oo.to_msg()?)?;
+        assert_eq!(again.get_branch("not_bins").unwrap().raw(),Some(&raw));
+        assert_eq!(again.metadata["Desc"],"Modified");
+        assert!(matches!(again.read_branch::<f64>("not_bins"),Err(CalcifyError::SubtypeError{..})));
+        assert_eq!(again.get_branch("not_bins").unwrap().extract::<f64>()?,Collection::from(vec![1.0,2.0]));
+        again.register::<Charge>("Charge")?;
+        assert_eq!(again.read_branch::<Charge>("charges")?,charges());
+
+        let mut jj = Tree::from_json(&oo.to_json())?;
+        assert!(jj.get_branch("not_bins").unwrap().is_raw());
+        assert_eq!(jj.get_branch("not_bins").unwrap().extract::<f64>()?,Collection::from(vec![1.0,2.0]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_raw_binary() -> Result<(),Box<dyn error::Error>>{
+        // Branches holding a bin and a non-timestamp ext, of an unknown subtype and of Object
+        let mut blob = Vec::new();
+        write_array_len(&mut blob, 2)?;
+        write_bin(&mut blob, &[1,2,3])?;
+        write_ext_meta(&mut blob, 1, 5)?;
+        blob.push(9);
+        for subtype in ["Blob","Object"] {
+            let mut bytes = Vec::new();
+            write_map_len(&mut bytes, 2)?;
+            write_str(&mut bytes, "Name")?;
+            write_str(&mut bytes, "Test_Tree")?;
+            write_str(&mut bytes, "branches")?;
+            write_map_len(&mut bytes, 1)?;
+            write_str(&mut bytes, "blob")?;
+            write_map_len(&mut bytes, 2)?;
+            write_str(&mut bytes, "subtype")?;
+            write_str(&mut bytes, subtype)?;
+            write_str(&mut bytes, "branch")?;
+            bytes.extend_from_slice(&blob);
+
+            let (mut oo,rest) = Tree::from_msg(&bytes)?;
+            assert!(rest.is_empty());
+            assert_eq!(oo.get_branch("blob").unwrap().raw().unwrap().msg_bytes(),Some(&blob[..]));
+            let (mut again,_) = Tree::from_msg(&oo.to_msg()?)?;
+            assert_eq!(again.get_branch("blob").unwrap().raw().unwrap().msg_bytes(),Some(&blob[..]));
+            assert!(Tree::from_msg(&bytes[..bytes.len()-1]).is_err());
+
+            let pp = oo.to_json();
+            assert!(pp.contains("{\"$bin\":[1,2,3]}"));
+            assert!(pp.contains("{\"$ext\":{\"type\":5,\"data\":[9]}}"));
+            let mut jj = Tree::from_json(&pp)?;
+            assert_eq!(jj.get_branch("blob").unwrap().raw().unwrap().to_msg()?,blob);
+            let (mut mm,_) = Tree::from_msg(&jj.to_msg()?)?;
+            assert_eq!(mm.get_branch("blob").unwrap().raw().unwrap().msg_bytes(),Some(&blob[..]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_subtype_errors() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        assert!(matches!(ttree.add_branch_as("not_bins", Collection::from(vec![1.0,2.0]), "Bin"),Err(CalcifyError::SubtypeError{..})));
+        assert!(matches!(ttree.add_branch_as("fcol", Collection::from(vec![1.0]), "Floats"),Err(CalcifyError::SubtypeError{..})));
+        ttree.register::<f64>("Floats")?;
+        ttree.add_branch_as("fcol", Collection::from(vec![1.0]), "Floats")?;
+        ttree.add_branch_as("old_charges", charges(), "Object")?;
+        ttree.add_branch("vecs", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        assert!(ttree.get_branch("not_bins").is_none());
+        assert_eq!(ttree.get_branch("vecs").unwrap().subtype(),"ThreeVec");
+
+        assert!(matches!(ttree.read_branch::<Bin>("vecs"),Err(CalcifyError::SubtypeError{..})));
+        assert!(matches!(ttree.read_branch::<f64>("vecs"),Err(CalcifyError::SubtypeError{..})));
+        assert!(matches!(ttree.read_branch::<ThreeVec>("nope"),Err(CalcifyError::KeyError{key}) if key == "nope"));
+        assert_eq!(ttree.read_branch::<f64>("fcol")?,Collection::from(vec![1.0]));
+        assert!(matches!(ttree.read_branch::<u64>("fcol"),Err(CalcifyError::SubtypeError{..})));
+
+        let (mut oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        assert_eq!(oo.read_branch::<ThreeVec>("vecs")?,Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]));
+        assert!(matches!(oo.read_branch::<f64>("fcol"),Err(CalcifyError::SubtypeError{..})));
+        assert_eq!(oo.read_branch::<Charge>("old_charges")?,charges());
+        assert!(matches!(oo.read_branch::<ThreeVec>("old_charges"),Err(CalcifyError::ParseError{..})));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_container() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.register::<Charge>("Charge")?;
+        ttree.add_branch("charges", charges())?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0]))?;
+        ttree.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,2)]))?;
+        let mut cont: Vec<u8> = Vec::new();
+        ttree.write_container_to(&mut cont, Codec::None)?;
+        let mut legacy: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut legacy)?;
+
+        for bytes in [cont,legacy].iter() {
+            let mut oo = Tree::read_msg_from(&bytes[..])?;
+            assert_eq!(oo.metadata,ttree.metadata);
+            assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+            assert_eq!(oo.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,2)]));
+            oo.register::<Charge>("Charge")?;
+            assert_eq!(oo.read_branch::<Charge>("charges")?,charges());
+        }
+
+        let mut z: Vec<u8> = Vec::new();
+        ttree.write_container_to(&mut z, Codec::Deflate)?;
+        let mut zz = Tree::read_msg_from(&z[..])?;
+        assert_eq!(zz.metadata,ttree.metadata);
+        assert_eq!(zz.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,2)]));
+        zz.register::<Charge>("Charge")?;
+        assert_eq!(zz.read_branch::<Charge>("charges")?,charges());
+
+        let mut pp = ttree.to_container()?;
+        pp[9] += 1;
+        assert!(Tree::from_container(&pp).is_err());
+        Ok(())
+    }
+
+    fn calcify_err(res: Result<Tree,Box<dyn error::Error>>) -> CalcifyError {
+        res.err().expect("read should fail").downcast_ref::<CalcifyError>().expect("should be a CalcifyError").clone()
+    }
+
+    #[test]
+    fn test_tree_file_corruption() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0,3.0]))?;
+        let pp = ttree.to_container()?;
+
+        let mut flipped = pp.clone();
+        let last = flipped.len()-1;
+        flipped[last] ^= 0x01;
+        assert!(matches!(calcify_err(Tree::read_msg_from(&flipped[..])),CalcifyError::ChecksumError{branch} if branch == "fcol"));
+
+        assert!(matches!(calcify_err(Tree::read_msg_from(&pp[..pp.len()-3])),CalcifyError::TruncatedError));
+        assert!(matches!(calcify_err(Tree::read_msg_from(&pp[..10])),CalcifyError::TruncatedError));
+
+        let legacy = ttree.to_msg()?;
+        assert!(matches!(calcify_err(Tree::read_msg_from(&legacy[..legacy.len()-3])),CalcifyError::TruncatedError));
+
+        let mut feeds: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+        feeds.add_feed("fcol", Collection::from(vec![1.0]))?;
+        assert!(matches!(calcify_err(Tree::read_msg_from(&feeds.to_msg()?[..])),CalcifyError::SchemaError{..}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_typed_fields() -> Result<(),Box<dyn error::Error>>{
+        let started = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_642_291_200);
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("dt", 0.001)?;
+        ttree.add_field("N", 1000usize)?;
+        ttree.add_field("periodic", true)?;
+        ttree.add_field("started", started)?;
+        assert!(matches!(ttree.add_field("N", 10),Err(CalcifyError::KeyError{..})));
+        assert_eq!(ttree.get_field("N"),Some(&FieldValue::Int(1000)));
+        ttree.add_branch("fcol", Collection::from(vec![1.0]))?;
+
+        let (oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        assert_eq!(oo.metadata,ttree.metadata);
+        let cc = Tree::from_container(&ttree.to_container()?)?;
+        assert_eq!(cc.metadata,ttree.metadata);
+        let tfile = TreeFile::open_from(std::io::Cursor::new(ttree.to_msg()?))?;
+        assert_eq!(tfile.get_field("started").and_then(FieldValue::as_time),Some(started));
+
+        let jj = Tree::from_json(&ttree.to_json())?;
+        assert_eq!(jj.get_field("dt"),Some(&FieldValue::Float(0.001)));
+        assert_eq!(jj.get_field("N"),Some(&FieldValue::Int(1000)));
+        assert_eq!(jj.get_field("periodic"),Some(&FieldValue::Bool(true)));
+        assert_eq!(jj.get_field("started").unwrap(),"2022-01-16T00:00:00Z");
+        assert_eq!(jj.get_field("started").and_then(FieldValue::as_time),Some(started));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_inspect() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0,3.0]))?;
+        ttree.add_branch("bins", Collection::from(vec![Bin::new(0.0,1.0,10)]))?;
+        ttree.add_branch_as("strs", Collection::from(vec![String::from("a"),String::from("b")]), "Object")?;
+        assert!(matches!(ttree.add_branch("fcol", Collection::from(vec![4.0])),Err(CalcifyError::KeyError{key}) if key == "fcol"));
+        assert!(matches!(ttree.add_branch_as("fcol", Collection::from(vec![4.0]), "f64"),Err(CalcifyError::KeyError{..})));
+        assert_eq!(ttree.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0,3.0]));
+
+        let mut names = ttree.branch_names();
+        names.sort();
+        assert_eq!(names,vec!["bins","fcol","strs"]);
+        assert_eq!(ttree.branches().filter(|(_,b)| b.subtype() == "Object").map(|(k,_)| k).collect::<Vec<&str>>(),vec!["strs"]);
+
+        let info = ttree.branch_info("fcol")?;
+        assert_eq!(info,BranchInfo{subtype: String::from("f64"), len: 3, size: ttree.get_branch("fcol").unwrap().collection_msg()?.len()});
+        assert_eq!(ttree.branch_info("bins")?.len,1);
+        assert!(matches!(ttree.branch_info("none"),Err(CalcifyError::KeyError{..})));
+
+        // Undecoded Branches report the same as decoded ones
+        let (mut oo,_) = Tree::from_msg(&ttree.to_msg()?)?;
+        let raw = oo.branches().find(|(k,_)| *k == "strs").map(|(_,b)| b.is_raw());
+        assert_eq!(raw,Some(true));
+        assert_eq!(oo.branch_info("strs")?,ttree.branch_info("strs")?);
+        assert_eq!(oo.branch_info("fcol")?,info);
+        let jj = Tree::from_json(&ttree.to_json())?;
+        assert_eq!(jj.branch_info("strs")?.len,2);
+
+        assert!(matches!(oo.rename_branch("none","other"),Err(CalcifyError::KeyError{key}) if key == "none"));
+        assert!(matches!(oo.rename_branch("fcol","bins"),Err(CalcifyError::KeyError{key}) if key == "bins"));
+        oo.rename_branch("fcol","energy")?;
+        assert_eq!(oo.read_branch::<f64>("energy")?,Collection::from(vec![1.0,2.0,3.0]));
+        assert!(oo.remove_branch("bins").is_some());
+        assert!(oo.remove_branch("bins").is_none());
+        let (pp,_) = Tree::from_msg(&oo.to_msg()?)?;
+        let mut names = pp.branch_names();
+        names.sort();
+        assert_eq!(names,vec!["energy","strs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_merge() -> Result<(),Box<dyn error::Error>>{
+        let run = |seed: i64, energy: Vec<f64>, count: u64| -> Result<Tree,Box<dyn error::Error>> {
+            let mut ttree = Tree::new("Test_Tree");
+            ttree.register::<Charge>("Charge")?;
+            ttree.add_field("Seed", seed)?;
+            ttree.add_branch("energy", Collection::from(energy))?;
+            ttree.add_branch("charges", charges())?;
+            ttree.add_branch_as("strs", Collection::from(vec![String::from("a")]), "Object")?;
+            ttree.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,count),Bin::new(1.0,2.0,1)]))?;
+            ttree.add_branch("hist2d", Collection::from(vec![PointBin::new(0.0,1.0,0.0,1.0,count)]))?;
+            Ok(ttree)
+        };
+        let mut ttree = run(1, vec![1.0,2.0], 2)?;
+        let mut other = run(2, vec![3.0], 5)?;
+        other.add_branch("extra", Collection::from(vec![4.0]))?;
+        assert!(matches!(ttree.merge(run(2, vec![3.0], 5)?, MergePolicy::Error),Err(CalcifyError::MergeError{what,..}) if what == "field \"Seed\""));
+        // Raw Branches read from msg merge the same as decoded ones
+        let (raw_other,_) = Tree::from_msg(&other.to_msg()?)?;
+        ttree.merge(raw_other, MergePolicy::KeepLast)?;
+
+        assert_eq!(ttree.get_field("Seed"),Some(&FieldValue::Int(2)));
+        assert_eq!(ttree.read_branch::<f64>("energy")?,Collection::from(vec![1.0,2.0,3.0]));
+        assert_eq!(ttree.read_branch::<f64>("extra")?,Collection::from(vec![4.0]));
+        assert_eq!(ttree.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,7),Bin::new(1.0,2.0,2)]));
+        assert_eq!(ttree.read_branch::<PointBin>("hist2d")?,Collection::from(vec![PointBin::new(0.0,1.0,0.0,1.0,7)]));
+        assert!(!ttree.get_branch("charges").unwrap().is_raw());
+        assert_eq!(ttree.read_branch::<Charge>("charges")?.len(),2*charges().len());
+        assert!(ttree.get_branch("strs").unwrap().is_raw());
+        assert_eq!(ttree.branch_info("strs")?.len,2);
+
+        let mut shifted = Tree::new("Test_Tree");
+        shifted.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,1),Bin::new(1.0,2.5,1)]))?;
+        shifted.add_branch("new", Collection::from(vec![1.0]))?;
+        let err = ttree.merge(shifted, MergePolicy::Error).unwrap_err();
+        assert!(matches!(&err,CalcifyError::MergeError{what,..} if what == "branch \"hist\""));
+        let binning = error::Error::source(&err).unwrap().downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(binning,CalcifyError::BinningError{index: 1}));
+        assert!(ttree.branch_info("new").is_err());
+        assert_eq!(ttree.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,7),Bin::new(1.0,2.0,2)]));
+
+        let mut wrong = Tree::new("Test_Tree");
+        wrong.add_branch("energy", Collection::from(vec![1.0f32]))?;
+        let err = ttree.merge(wrong, MergePolicy::KeepFirst).unwrap_err();
+        let subtype = error::Error::source(&err).unwrap().downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(subtype,CalcifyError::SubtypeError{subtype,expected} if subtype == "f32" && expected == "f64"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_missing_branches() {
+        let mut feeds: FeedTree<f64> = FeedTree::new("Test_Tree","f64");
+        feeds.add_feed("fcol", Collection::from(vec![1.0])).unwrap();
+        for err in [Tree::from_json(&feeds.to_json()).err().unwrap(), Tree::from_msg(&feeds.to_msg().unwrap()).err().unwrap()] {
+            let schema = err.downcast_ref::<CalcifyError>().unwrap();
+            assert!(matches!(schema,CalcifyError::SchemaError{what,..} if what == "Tree"));
+            let key = error::Error::source(schema).unwrap().downcast_ref::<CalcifyError>().unwrap();
+            assert!(matches!(key,CalcifyError::KeyError{key} if key == "branches"));
+        }
+    }
+
+    #[test]
+    fn test_tree_msg_errors() {
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0])).unwrap();
+        let pp = ttree.to_msg().unwrap();
+        assert!(Tree::from_msg(&pp[..pp.len()-4]).is_err());
+        assert!(Tree::from_msg(&[0xc1]).is_err());
+
+        let mut bad = Tree::from_json("{\"Name\":\"Test_Tree\",\"branches\":{\"bins\":{\"subtype\":\"Bin\",\"branch\":[{\"count\":2,\"range\":[0,1]},{\"count\":2}]}}}").unwrap();
+        let err = bad.read_branch::<Bin>("bins").unwrap_err();
+        assert!(matches!(&err,CalcifyError::ParseError{what,..} if what == "branch \"bins\""));
+        let element = error::Error::source(&err).unwrap().downcast_ref::<CalcifyError>().unwrap();
+        assert_eq!(element.to_string(),"Error on parse of Collection element 1.");
+        let key = error::Error::source(element).unwrap().downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(key,CalcifyError::KeyError{key} if key == "range"));
+    }
+
     #[test]
     fn test_tree_write() -> Result<(),Box<dyn error::Error>>{
         let fcol: Collection<f64> = Collection::from(vec![0.0,0.0]);
@@ -277,9 +1068,11 @@ mod tests {
         for _i in 0..9 {col_3v.push(ThreeVec::new(1.0,2.0,3.0));}
         let mut ttree = Tree::new("Test_Tree");
         ttree.add_field("Desc", "This is a Tree for testing.")?;
-        ttree.add_branch("fcol", fcol, "f64")?;
-        ttree.add_branch("col_3v", col_3v, "ThreeVec")?;
-        ttree.write_msg("./scratch/test_tree.msg")?;
+        ttree.add_branch("fcol", fcol)?;
+        ttree.add_branch("col_3v", col_3v)?;
+        let mut buf: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut buf)?;
+        assert!(!buf.is_empty());
         Ok(())
     }
 
@@ -288,8 +1081,14 @@ mod tests {
         let fcol: Collection<f64> = Collection::from(vec![0.0,0.0]);
         let mut col_3v: Collection<ThreeVec> = Collection::empty();
         for _i in 0..9 {col_3v.push(ThreeVec::new(1.0,2.0,3.0));}
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_branch("fcol", fcol.clone())?;
+        ttree.add_branch("col_3v", col_3v.clone())?;
+        let mut buf: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut buf)?;
 
-        let mut ftree = Tree::read_msg("./scratch/test_tree.msg")?;
+        let mut ftree = Tree::read_msg_from(&buf[..])?;
         let ftree_branch1: Collection<f64> = ftree.read_branch("fcol")?;
         assert_eq!(ftree_branch1,fcol);
         let ftree_branch2: Collection<ThreeVec> = ftree.read_branch("col_3v")?;
@@ -305,15 +1104,57 @@ mod tests {
         for _i in 0..9 {col_3v.push(ThreeVec::random(1.0));}
         let mut ttree = Tree::new("Test_Tree");
         ttree.add_field("Desc", "This is a Tree for testing.")?;
-        ttree.add_branch("col_3v", col_3v.clone(), "ThreeVec")?;
-        ttree.add_branch("fcol", fcol, "f64")?;
-        ttree.add_branch("col_3v3", col_3v, "ThreeVec")?;
+        ttree.add_branch("col_3v", col_3v.clone())?;
+        ttree.add_branch("fcol", fcol)?;
+        ttree.add_branch("col_3v3", col_3v)?;
         let pp = ttree.to_json();
         let mut oo = Tree::from_json(&pp)?;
         assert_eq!(oo.read_branch("fcol").unwrap(),Collection::from(vec![0.0,0.0]));
         Ok(())
     }
 
+    #[test]
+    fn test_tree_json_pretty() -> Result<(),Box<dyn error::Error>>{
+        let pretty = r#"{
+    "branches": {
+        "hist": {
+            "branch": [
+                { "range": [0.0, 1.0], "count": 3 },
+                { "count": 4, "range": [1.0, 2.0] }
+            ],
+            "subtype": "Bin"
+        },
+        "fcol": { "subtype": "f64", "branch": [ 0.0, 1.5 ] }
+    },
+    "Name": "Test_Tree",
+    "Desc": "Has \"quotes\", {braces}, and a \\ backslash"
+}"#;
+        let mut oo = Tree::from_json(pretty)?;
+        assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![0.0,1.5]));
+        assert_eq!(oo.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,3),Bin::new(1.0,2.0,4)]));
+        assert_eq!(oo.metadata["Desc"],"Has \"quotes\", {braces}, and a \\ backslash");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_json_escaped() -> Result<(),Box<dyn error::Error>>{
+        let desc = "Run \"B\" at 4.2 K\nC:\\data\\run_07\tΔt = 1 µs";
+        let mut ttree = Tree::new("Tree \"quoted\"");
+        ttree.add_field("Desc", desc)?;
+        ttree.add_field("Empty", "")?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0]))?;
+        let pp = ttree.to_json();
+        assert!(JsonValue::parse(&pp).is_ok());
+        let mut oo = Tree::from_json(&pp)?;
+        assert_eq!(oo.metadata["Desc"],desc);
+        assert_eq!(oo.metadata["Empty"],"");
+        assert_eq!(oo.metadata["Name"],"Tree \"quoted\"");
+        assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+        let empty = Tree::from_json(&Tree::new("No branches").to_json())?;
+        assert_eq!(empty.metadata["Name"],"No branches");
+        Ok(())
+    }
+
     #[test]
     fn test_tree_msg() -> Result<(),Box<dyn error::Error>>{
         let fcol: Collection<f64> = Collection::from(vec![0.0,0.0]);
@@ -321,11 +1162,36 @@ mod tests {
         for _i in 0..9 {col_3v.push(ThreeVec::random(1.0));}
         let mut ttree = Tree::new("Test_Tree");
         ttree.add_field("Desc", "This is a Tree for testing.")?;
-        ttree.add_branch("fcol", fcol, "f64")?;
-        ttree.add_branch("col_3v", col_3v, "ThreeVec")?;
+        ttree.add_branch("fcol", fcol)?;
+        ttree.add_branch("col_3v", col_3v)?;
         let pp = ttree.to_msg().unwrap();
         let (mut oo,_) = Tree::from_msg(&pp).unwrap();
         assert_eq!(oo.read_branch("fcol").unwrap(),Collection::from(vec![0.0,0.0]));
         Ok(())
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_tree_serde() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.register::<Charge>("Charge")?;
+        ttree.add_branch("charges", charges())?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0]))?;
+        ttree.add_branch("hist", Collection::from(vec![Bin::new(0.0,1.0,2)]))?;
+
+        let mut oo: Tree = rmp_serde::from_slice(&rmp_serde::to_vec(&ttree)?)?;
+        assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+        oo.register::<Charge>("Charge")?;
+        assert_eq!(oo.read_branch::<Charge>("charges")?,charges());
+        let (mut mm,_) = Tree::from_msg(&rmp_serde::to_vec(&ttree)?)?;
+        assert_eq!(mm.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,2)]));
+
+        let mut jj: Tree = serde_json::from_str(&ttree.to_json())?;
+        assert_eq!(jj.metadata,ttree.metadata);
+        assert_eq!(jj.read_branch::<Bin>("hist")?,Collection::from(vec![Bin::new(0.0,1.0,2)]));
+        let mut js = Tree::from_json(&serde_json::to_string(&ttree)?)?;
+        assert_eq!(js.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+        Ok(())
+    }
 }
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::error;
+
+use std::io::prelude::*;
+use std::io::{BufReader, SeekFrom};
+use std::fs;
+
+use super::Collection;
+use super::Branch;
+use super::TreeSubtype;
+use super::branch::SubtypeDecoder;
+
+use crate::utils;
+use utils::{Serializable, Deserializable, FieldValue};
+use utils::errors::CalcifyError;
+use utils::container::{self, Codec};
+
+extern crate rmp;
+use rmp::Marker;
+use rmp::decode::*;
+
+/// Metadata and Branch index of a Tree file
+type Index = (HashMap<String,FieldValue>,HashMap<String,BranchEntry>);
+
+/// Location of an undecoded Branch in a Tree file
+#[derive(Debug, PartialEq, Clone)]
+struct BranchEntry {
+    subtype: String,
+    offset: u64,
+    len: u64,
+    checksum: Option<u32>,
+}
+
+/// Tree msg file opened for reading one Branch at a time
+///
+/// Opening the file reads only the metadata and an index of the Branch names, subtypes and byte offsets.
+/// Branches are decoded when they are read, so pulling one histogram out of a large file does not
+/// load the rest.
+///
+/// Containers written by `write_container` or `write_msg_compressed` store the index up front, so only their header is read on open.
+/// Files in the legacy layout are scanned once to build it. Any `io::Read + io::Seek`, like a `Cursor` over
+/// bytes already in memory, can be opened the same way with `open_from`.
+///
+/// # Example
+/// ```
+/// use calcify::Tree;
+/// use calcify::TreeFile;
+/// use calcify::Collection;
+/// use calcify::io::ToFile;
+/// # use std::error;
+/// # fn main() -> Result<(),Box<dyn error::Error>> {
+/// # let path = std::env::temp_dir().join("calcify_doc_tree_file.msg");
+/// # let filename = path.to_str().unwrap();
+///
+/// let mut ttree = Tree::new("Test_Tree");
+/// ttree.add_branch("fcol", Collection::from(vec![0.0,1.0]))?;
+/// ttree.add_branch("big", Collection::from(vec![2.0; 10000]))?;
+/// ttree.write_msg(filename)?;
+///
+/// let mut tfile = TreeFile::open(filename)?;
+/// assert_eq!(tfile.get_field("Name").unwrap(), "Test_Tree");
+/// assert_eq!(tfile.subtype("big"), Some("f64"));
+/// let fcol: Collection<f64> = tfile.read_branch("fcol")?;
+/// assert_eq!(fcol, Collection::from(vec![0.0,1.0]));
+/// # std::fs::remove_file(filename)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TreeFile<R: Read + Seek = fs::File> {
+    file: BufReader<R>,
+    metadata: HashMap<String,FieldValue>,
+    index: HashMap<String,BranchEntry>,
+    codec: Codec,
+}
+
+impl TreeFile {
+    /// Opens a Tree msg file and reads its metadata and Branch index
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::TruncatedError` if the file ends before the last Branch does
+    pub fn open(filename: &str) -> Result<TreeFile, Box<dyn error::Error>> {
+        TreeFile::open_from(fs::File::open(filename)?)
+    }
+}
+
+impl<R: Read + Seek> TreeFile<R> {
+    /// Reads the metadata and Branch index of a Tree from the start of a reader, like a `Cursor`
+    ///
+    /// Has the same errors as `open`.
+    pub fn open_from(mut rd: R) -> Result<TreeFile<R>, Box<dyn error::Error>> {
+        let file_len = rd.seek(SeekFrom::End(0))?;
+        rd.seek(SeekFrom::Start(0))?;
+        let mut file = BufReader::new(rd);
+        let mut codec = Codec::None;
+        let (metadata,index) = if container::is_container(file.fill_buf()?) {
+            let header = container::read_header(&mut file)?;
+            let index = header.index.iter().map(|entry| {
+                let offset = header.data_start.checked_add(entry.offset).ok_or(CalcifyError::TruncatedError)?;
+                Ok((entry.name.clone(),BranchEntry{
+                    subtype: entry.subtype.clone(),
+                    offset,
+                    len: entry.len,
+                    checksum: entry.checksum,
+                }))
+            }).collect::<Result<_,CalcifyError>>()?;
+            codec = header.codec;
+            (header.metadata,index)
+        } else {
+            match index_legacy(&mut file) {
+                Ok(found) => found,
+                Err(_) if file.stream_position()? >= file_len => return Err(Box::new(CalcifyError::TruncatedError)),
+                Err(err) => return Err(err),
+            }
+        };
+        if index.values().any(|entry: &BranchEntry| entry.offset.checked_add(entry.len).filter(|&end| end <= file_len).is_none()) {
+            return Err(Box::new(CalcifyError::TruncatedError));
+        }
+        Ok(TreeFile{file, metadata, index, codec})
+    }
+
+    /// Returns a metadata field
+    pub fn get_field(&self, key: &str) -> Option<&FieldValue> {
+        self.metadata.get(key)
+    }
+
+    /// Returns the names of all Branches in the file
+    pub fn branch_names(&self) -> Vec<&str> {
+        self.index.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Returns the subtype of a Branch, without reading it
+    pub fn subtype(&self, key: &str) -> Option<&str> {
+        self.index.get(key).map(|entry| entry.subtype.as_str())
+    }
+
+    /// Decodes a single Branch from the file as a Collection
+    ///
+    /// # Arguments
+    ///
+    /// `key` - String
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::SubtypeError` if the Branch subtype is not that of T. `Object` Branches can be read as any T.
+    /// * `CalcifyError::ChecksumError` if the Branch doesn't match the checksum written with it
+    /// * `CalcifyError::SchemaError` if the Branch doesn't decode as a Collection<T>
+    pub fn read_branch<T: 'static + Serializable + Deserializable + TreeSubtype>(&mut self, key: &str) -> Result<Collection<T>, Box<dyn error::Error>> {
+        let subtype = self.subtype(key).ok_or_else(|| CalcifyError::key(key))?;
+        let matches = match SubtypeDecoder::builtin(subtype) {
+            Some(decoder) => decoder.is::<T>(),
+            None => subtype == T::NAME || subtype == "Object",
+        };
+        if !matches {
+            return Err(Box::new(CalcifyError::SubtypeError{subtype: String::from(subtype), expected: String::from(T::NAME)}));
+        }
+        let bytes = self.branch_bytes(key)?;
+        let (collect,_) = Collection::<T>::from_msg(&bytes).map_err(|e| container::decode_error(&format!("branch {:?}",key),&bytes,e))?;
+        Ok(collect)
+    }
+
+    /// Reads a single Branch from the file, decoding it if the subtype is built in
+    ///
+    /// Other subtypes are returned raw, the same as when reading a whole Tree.
+    pub fn load_branch(&mut self, key: &str) -> Result<Branch, Box<dyn error::Error>> {
+        let bytes = self.branch_bytes(key)?;
+        let subtype = self.index[key].subtype.clone();
+        Ok(Branch::from_subtype_msg(&subtype, &bytes)?.0)
+    }
+
+    fn branch_bytes(&mut self, key: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+        let entry = self.index.get(key).ok_or_else(|| CalcifyError::key(key))?;
+        let mut bytes = vec![0; entry.len as usize];
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut bytes).map_err(container::truncated)?;
+        if let Some(checksum) = entry.checksum {
+            if checksum != container::crc32(&bytes) {
+                return Err(Box::new(CalcifyError::ChecksumError{branch: String::from(key)}));
+            }
+        }
+        if self.codec == Codec::None {
+            return Ok(bytes);
+        }
+        Ok(self.codec.decompress(&bytes)?.into_owned())
+    }
+}
+
+/// Scans a file in the legacy layout for its metadata and Branch index
+fn index_legacy<R: Read + Seek>(rd: &mut BufReader<R>) -> Result<Index, Box<dyn error::Error>> {
+    let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+    let mut index: HashMap<String,BranchEntry> = HashMap::new();
+    let len = read_map_len(rd)?;
+    for _ in 0..len {
+        let key = read_string(rd)?;
+        if key == "branches" {
+            let blen = read_map_len(rd)?;
+            for _ in 0..blen {
+                let name = read_string(rd)?;
+                let entry = index_branch(rd)?;
+                index.insert(name,entry);
+            }
+        } else {
+            let start = rd.stream_position()?;
+            skip_value(rd)?;
+            let mut buf = vec![0; (rd.stream_position()? - start) as usize];
+            rd.seek(SeekFrom::Start(start))?;
+            rd.read_exact(&mut buf)?;
+            metadata.insert(key,FieldValue::from_msg(&buf)?.0);
+        }
+    }
+    Ok((metadata,index))
+}
+
+/// Reads a Branch map, recording where its Collection is rather than decoding it
+fn index_branch<R: Read + Seek>(rd: &mut BufReader<R>) -> Result<BranchEntry, Box<dyn error::Error>> {
+    let mut subtype: Option<String> = None;
+    let mut extent: Option<(u64,u64)> = None;
+    let len = read_map_len(rd)?;
+    for _ in 0..len {
+        match read_string(rd)?.as_str() {
+            "subtype" => subtype = Some(read_string(rd)?),
+            "branch" => {
+                let offset = rd.stream_position()?;
+                skip_value(rd)?;
+                extent = Some((offset,rd.stream_position()? - offset));
+            },
+            _ => skip_value(rd)?,
+        }
+    }
+    match (subtype,extent) {
+        (Some(subtype),Some((offset,len))) => Ok(BranchEntry{subtype, offset, len, checksum: None}),
+        _ => Err(Box::new(CalcifyError::parse("Branch, expected subtype and branch"))),
+    }
+}
+
+fn read_string<R: Read>(rd: &mut R) -> Result<String, Box<dyn error::Error>> {
+    let len = read_str_len(rd)?;
+    let mut buf = Vec::new();
+    rd.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len as usize {
+        return Err(Box::new(CalcifyError::TruncatedError));
+    }
+    Ok(String::from_utf8(buf)?)
+}
+
+/// Moves past one MsgPack value, including everything nested in it, without decoding it
+///
+/// Skips are relative so that the reader's buffer is kept.
+fn skip_value<R: Read + Seek>(rd: &mut BufReader<R>) -> Result<(), Box<dyn error::Error>> {
+    let mut remaining: u64 = 1;
+    while remaining > 0 {
+        remaining -= 1;
+        let marker = read_marker(rd).map_err(|_| CalcifyError::parse("msg"))?;
+        let skip: u64 = match marker {
+            Marker::FixPos(_) | Marker::FixNeg(_) | Marker::Null | Marker::True | Marker::False => 0,
+            Marker::U8 | Marker::I8 => 1,
+            Marker::U16 | Marker::I16 => 2,
+            Marker::U32 | Marker::I32 | Marker::F32 => 4,
+            Marker::U64 | Marker::I64 | Marker::F64 => 8,
+            Marker::FixStr(n) => n as u64,
+            Marker::Str8 | Marker::Bin8 => rd.read_data_u8()? as u64,
+            Marker::Str16 | Marker::Bin16 => rd.read_data_u16()? as u64,
+            Marker::Str32 | Marker::Bin32 => rd.read_data_u32()? as u64,
+            Marker::FixArray(n) => {remaining += n as u64; 0},
+            Marker::Array16 => {remaining += rd.read_data_u16()? as u64; 0},
+            Marker::Array32 => {remaining += rd.read_data_u32()? as u64; 0},
+            Marker::FixMap(n) => {remaining += 2*n as u64; 0},
+            Marker::Map16 => {remaining += 2*rd.read_data_u16()? as u64; 0},
+            Marker::Map32 => {remaining += 2*rd.read_data_u32()? as u64; 0},
+            Marker::FixExt1 => 2,
+            Marker::FixExt2 => 3,
+            Marker::FixExt4 => 5,
+            Marker::FixExt8 => 9,
+            Marker::FixExt16 => 17,
+            Marker::Ext8 => rd.read_data_u8()? as u64 + 1,
+            Marker::Ext16 => rd.read_data_u16()? as u64 + 1,
+            Marker::Ext32 => rd.read_data_u32()? as u64 + 1,
+            Marker::Reserved => return Err(Box::new(CalcifyError::parse("msg").at(rd.stream_position()? as usize - 1))),
+        };
+        if skip > 0 {
+            rd.seek_relative(skip as i64)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tree;
+    use crate::Bin;
+    use crate::ThreeVec;
+    use utils::io::ToFile;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_tree_file() -> Result<(),Box<dyn error::Error>>{
+        let col_3v: Collection<ThreeVec> = (0..100).map(|i| ThreeVec::new(i as f64,1.0,-1.0)).collect();
+        let hist = Collection::from(vec![Bin::new(0.0,1.0,3),Bin::new(1.0,2.0,4)]);
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_branch("col_3v", col_3v.clone())?;
+        ttree.add_branch("hist", hist.clone())?;
+        ttree.add_branch_as("strs", Collection::from(vec![String::from("a"),String::from("bc")]), "Object")?;
+        let legacy = ttree.to_msg()?;
+
+        let mut tfile = TreeFile::open_from(Cursor::new(&legacy))?;
+        assert_eq!(tfile.get_field("Desc").unwrap(),"This is a Tree for testing.");
+        let mut names = tfile.branch_names();
+        names.sort();
+        assert_eq!(names,vec!["col_3v","hist","strs"]);
+        assert_eq!(tfile.subtype("strs"),Some("Object"));
+        assert_eq!(tfile.read_branch::<Bin>("hist")?,hist);
+        assert_eq!(tfile.read_branch::<ThreeVec>("col_3v")?,col_3v);
+        assert!(tfile.load_branch("strs")?.is_raw());
+        assert!(!tfile.load_branch("hist")?.is_raw());
+        assert!(tfile.read_branch::<f64>("none").is_err());
+
+        let cont = ttree.to_container()?;
+        let mut cfile = TreeFile::open_from(Cursor::new(&cont))?;
+        assert_eq!(cfile.get_field("Desc").unwrap(),"This is a Tree for testing.");
+        assert_eq!(cfile.subtype("strs"),Some("Object"));
+        assert_eq!(cfile.read_branch::<ThreeVec>("col_3v")?,col_3v);
+        assert_eq!(cfile.read_branch::<Bin>("hist")?,hist);
+        assert!(cfile.load_branch("strs")?.is_raw());
+
+        let mut zfile = TreeFile::open_from(Cursor::new(ttree.to_container_with(Codec::Deflate)?))?;
+        assert_eq!(zfile.read_branch::<ThreeVec>("col_3v")?,col_3v);
+        assert_eq!(zfile.read_branch::<Bin>("hist")?,hist);
+        let err = cfile.read_branch::<f64>("hist").err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::SubtypeError{..})));
+        let err = cfile.read_branch::<f64>("strs").err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::SchemaError{..})));
+        assert_eq!(cfile.read_branch::<String>("strs")?,Collection::from(vec![String::from("a"),String::from("bc")]));
+
+        let mut bytes = cont.clone();
+        let last = bytes.len()-1;
+        bytes[last] ^= 0x01;
+        let mut bad = TreeFile::open_from(Cursor::new(&bytes))?;
+        let err = bad.load_branch("strs").err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::ChecksumError{..})));
+        let err = TreeFile::open_from(Cursor::new(&bytes[..last])).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::TruncatedError)));
+
+        let err = TreeFile::open_from(Cursor::new(&legacy[..legacy.len()-2])).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::TruncatedError)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree_file_bad_index() -> Result<(),Box<dyn error::Error>>{
+        use rmp::encode::*;
+        // An index entry past the end of any file, so the offset overflows once the header is added
+        let mut header = Vec::new();
+        write_map_len(&mut header, 1)?;
+        write_str(&mut header, "index")?;
+        write_array_len(&mut header, 1)?;
+        write_array_len(&mut header, 5)?;
+        write_str(&mut header, "a")?;
+        write_str(&mut header, "f64")?;
+        write_uint(&mut header, u64::MAX - 1)?;
+        write_uint(&mut header, 1)?;
+        write_nil(&mut header)?;
+        let mut bytes = container::MAGIC.to_vec();
+        bytes.extend_from_slice(&container::FORMAT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        bytes.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        bytes.append(&mut header);
+        let err = TreeFile::open_from(Cursor::new(&bytes)).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::TruncatedError)));
+
+        // A legacy Tree whose first key claims to be 4GB long
+        let err = TreeFile::open_from(Cursor::new(&[0x81,0xdb,0xff,0xff,0xff,0xff,b'N'])).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::TruncatedError)));
+        Ok(())
+    }
+}
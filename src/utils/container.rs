@@ -0,0 +1,430 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error;
+
+use std::io;
+use std::io::prelude::*;
+
+use super::errors::CalcifyError;
+use super::serializable::{Serializable, Deserializable};
+use super::field_value::FieldValue;
+
+extern crate rmp;
+use rmp::encode::*;
+use rmp::decode::*;
+
+extern crate miniz_oxide;
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec;
+
+/// First bytes of a container file. 0xc1 is never used by MsgPack, so a legacy file can't start with it.
+pub(crate) const MAGIC: &[u8; 8] = b"\xc1CALCIFY";
+
+/// Latest container format version written and understood
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Length of the magic bytes, version, flags and header length
+const PREAMBLE_LEN: usize = 16;
+
+/// Flag set when every index entry has a CRC32 of its block
+pub(crate) const FLAG_CHECKSUMS: u16 = 1;
+
+/// The Codec id is kept in the high byte of the flags
+const CODEC_SHIFT: u16 = 8;
+
+/// Compression applied to each Branch of a container
+///
+/// Branches are compressed one at a time, so a single Branch can still be read without the others.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Codec {
+    None,
+    /// Raw deflate, as in zlib and gzip
+    Deflate,
+}
+
+impl Codec {
+    fn id(self) -> u16 {
+        match self {
+            Codec::None => 0,
+            Codec::Deflate => 1,
+        }
+    }
+
+    fn from_id(id: u16) -> Result<Codec, CalcifyError> {
+        match id {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Deflate),
+            _ => Err(CalcifyError::FormatError),
+        }
+    }
+
+    fn compress(self, bytes: Vec<u8>) -> Vec<u8> {
+        match self {
+            Codec::None => bytes,
+            Codec::Deflate => compress_to_vec(&bytes, 6),
+        }
+    }
+
+    /// Returns the original bytes of a block
+    pub(crate) fn decompress(self, bytes: &[u8]) -> Result<Cow<'_, [u8]>, CalcifyError> {
+        match self {
+            Codec::None => Ok(Cow::Borrowed(bytes)),
+            Codec::Deflate => decompress_to_vec(bytes).map(Cow::Owned).map_err(|_| CalcifyError::parse("compressed block")),
+        }
+    }
+}
+
+/// Lookup table for the reflected CRC32 (IEEE) polynomial
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {0xedb8_8320 ^ (c >> 1)} else {c >> 1};
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// CRC32 (IEEE) of the bytes, the same as zlib and gzip
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut c = 0xffff_ffffu32;
+    for b in bytes {
+        c = CRC32_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    c ^ 0xffff_ffff
+}
+
+/// Maps running out of bytes to a TruncatedError
+pub(crate) fn truncated(err: io::Error) -> Box<dyn error::Error> {
+    if err.kind() == io::ErrorKind::UnexpectedEof {
+        Box::new(CalcifyError::TruncatedError)
+    } else {
+        Box::new(err)
+    }
+}
+
+/// Returns the length of the first MsgPack value in the bytes, without decoding it
+///
+/// # Errors
+///
+/// * `CalcifyError::TruncatedError` if the bytes end before the value does
+/// * `CalcifyError::ParseError` if they are not MsgPack
+pub(crate) fn msg_len(bytes: &[u8]) -> Result<usize, CalcifyError> {
+    let mut pos: usize = 0;
+    let mut remaining: u64 = 1;
+    let take = |pos: &mut usize, n: usize| -> Result<u64, CalcifyError> {
+        if *pos + n > bytes.len() {
+            return Err(CalcifyError::TruncatedError);
+        }
+        let v = bytes[*pos..*pos+n].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        *pos += n;
+        Ok(v)
+    };
+    while remaining > 0 {
+        remaining -= 1;
+        let marker = rmp::Marker::from_u8(take(&mut pos, 1)? as u8);
+        let skip: u64 = match marker {
+            rmp::Marker::FixPos(_) | rmp::Marker::FixNeg(_) | rmp::Marker::Null | rmp::Marker::True | rmp::Marker::False => 0,
+            rmp::Marker::U8 | rmp::Marker::I8 => 1,
+            rmp::Marker::U16 | rmp::Marker::I16 => 2,
+            rmp::Marker::U32 | rmp::Marker::I32 | rmp::Marker::F32 => 4,
+            rmp::Marker::U64 | rmp::Marker::I64 | rmp::Marker::F64 => 8,
+            rmp::Marker::FixStr(n) => n as u64,
+            rmp::Marker::Str8 | rmp::Marker::Bin8 => take(&mut pos, 1)?,
+            rmp::Marker::Str16 | rmp::Marker::Bin16 => take(&mut pos, 2)?,
+            rmp::Marker::Str32 | rmp::Marker::Bin32 => take(&mut pos, 4)?,
+            rmp::Marker::FixArray(n) => {remaining += n as u64; 0},
+            rmp::Marker::Array16 => {remaining += take(&mut pos, 2)?; 0},
+            rmp::Marker::Array32 => {remaining += take(&mut pos, 4)?; 0},
+            rmp::Marker::FixMap(n) => {remaining += 2*n as u64; 0},
+            rmp::Marker::Map16 => {remaining += 2*take(&mut pos, 2)?; 0},
+            rmp::Marker::Map32 => {remaining += 2*take(&mut pos, 4)?; 0},
+            rmp::Marker::FixExt1 => 2,
+            rmp::Marker::FixExt2 => 3,
+            rmp::Marker::FixExt4 => 5,
+            rmp::Marker::FixExt8 => 9,
+            rmp::Marker::FixExt16 => 17,
+            rmp::Marker::Ext8 => take(&mut pos, 1)? + 1,
+            rmp::Marker::Ext16 => take(&mut pos, 2)? + 1,
+            rmp::Marker::Ext32 => take(&mut pos, 4)? + 1,
+            rmp::Marker::Reserved => return Err(CalcifyError::parse("msg").at(pos-1)),
+        };
+        if pos as u64 + skip > bytes.len() as u64 {
+            return Err(CalcifyError::TruncatedError);
+        }
+        pos += skip as usize;
+    }
+    Ok(pos)
+}
+
+/// Classifies why bytes that should hold one MsgPack value failed to decode
+///
+/// Truncated or unparsable bytes keep that error, and anything else is a SchemaError naming `what`, caused by `err`.
+pub(crate) fn decode_error(what: &str, bytes: &[u8], err: Box<dyn error::Error>) -> CalcifyError {
+    match msg_len(bytes) {
+        Ok(_) => CalcifyError::SchemaError{what: String::from(what), source: None}.caused_by_boxed(err),
+        Err(err) => err,
+    }
+}
+
+/// Returns the SchemaError of a Tree or FeedTree, `what`, read without its top level `key`
+///
+/// This is how a FeedTree read as a Tree, and the reverse, fail.
+pub(crate) fn missing(what: &str, key: &str) -> CalcifyError {
+    CalcifyError::SchemaError{what: String::from(what), source: None}.caused_by(CalcifyError::key(key))
+}
+
+/// A named Collection in the container, already serialized to msg
+pub(crate) struct Block<'a> {
+    pub name: &'a str,
+    pub subtype: &'a str,
+    pub bytes: Vec<u8>,
+}
+
+/// Entry of the Branch index, with the offset relative to the start of the data section
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct IndexEntry {
+    pub name: String,
+    pub subtype: String,
+    pub offset: u64,
+    pub len: u64,
+    pub checksum: Option<u32>,
+}
+
+/// Everything in a container before the data section
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Header {
+    pub version: u16,
+    pub flags: u16,
+    pub codec: Codec,
+    pub metadata: HashMap<String,FieldValue>,
+    pub index: Vec<IndexEntry>,
+    pub data_start: u64,
+}
+
+impl Header {
+    /// Returns the decompressed bytes of an entry out of the whole file, verifying its checksum
+    pub fn block<'a>(&self, entry: &IndexEntry, bytes: &'a [u8]) -> Result<Cow<'a, [u8]>, CalcifyError> {
+        let start = self.data_start.checked_add(entry.offset).ok_or(CalcifyError::TruncatedError)?;
+        let end = start.checked_add(entry.len).ok_or(CalcifyError::TruncatedError)?;
+        if end > bytes.len() as u64 {
+            return Err(CalcifyError::TruncatedError);
+        }
+        let block = &bytes[start as usize..end as usize];
+        entry.verify(block)?;
+        self.codec.decompress(block)
+    }
+}
+
+impl IndexEntry {
+    /// Checks the bytes of the block against the checksum, if there is one
+    pub fn verify(&self, bytes: &[u8]) -> Result<(), CalcifyError> {
+        match self.checksum {
+            Some(checksum) if checksum != crc32(bytes) => Err(CalcifyError::ChecksumError{branch: self.name.clone()}),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns true if the bytes start with the container magic bytes
+pub(crate) fn is_container(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Writes a container
+///
+/// The layout is the magic bytes, then big endian u16 version, u16 flags and u32 header length,
+/// then the MsgPack header, a map of the `metadata` fields and an `index` array of
+/// [name, subtype, offset, length, CRC32] arrays, then the blocks back to back.
+///
+/// Blocks are compressed with the Codec before their length and CRC32 are taken.
+pub(crate) fn write_container(metadata: &HashMap<String,FieldValue>, blocks: Vec<Block>, codec: Codec) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let blocks: Vec<Block> = blocks.into_iter().map(|block| Block{bytes: codec.compress(block.bytes), ..block}).collect();
+    let mut header = Vec::new();
+    write_map_len(&mut header, 2)?;
+    write_str(&mut header, "metadata")?;
+    write_map_len(&mut header, metadata.len() as u32)?;
+    let mut keys: Vec<&String> = metadata.keys().collect();
+    keys.sort();
+    for key in keys {
+        write_str(&mut header, key)?;
+        header.append(&mut metadata[key].to_msg()?);
+    }
+    write_str(&mut header, "index")?;
+    write_array_len(&mut header, blocks.len() as u32)?;
+    let mut offset: u64 = 0;
+    for block in blocks.iter() {
+        write_array_len(&mut header, 5)?;
+        write_str(&mut header, block.name)?;
+        write_str(&mut header, block.subtype)?;
+        write_uint(&mut header, offset)?;
+        write_uint(&mut header, block.bytes.len() as u64)?;
+        write_uint(&mut header, crc32(&block.bytes) as u64)?;
+        offset += block.bytes.len() as u64;
+    }
+
+    let mut buf = Vec::with_capacity(PREAMBLE_LEN + header.len() + offset as usize);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+    buf.extend_from_slice(&(FLAG_CHECKSUMS | codec.id() << CODEC_SHIFT).to_be_bytes());
+    buf.extend_from_slice(&(header.len() as u32).to_be_bytes());
+    buf.append(&mut header);
+    for mut block in blocks {
+        buf.append(&mut block.bytes);
+    }
+    Ok(buf)
+}
+
+/// Returns a ParseError at the offset into the file of the `rest` of the header
+fn header_error(header: &[u8], rest: &[u8]) -> CalcifyError {
+    CalcifyError::parse("container header").at(PREAMBLE_LEN + header.len() - rest.len())
+}
+
+/// Reads the header of a container, leaving the reader at the start of the data section
+pub(crate) fn read_header<R: Read>(rd: &mut R) -> Result<Header, Box<dyn error::Error>> {
+    let mut preamble = [0u8; PREAMBLE_LEN];
+    rd.read_exact(&mut preamble).map_err(truncated)?;
+    if !is_container(&preamble) {
+        return Err(Box::new(CalcifyError::FormatError));
+    }
+    let version = u16::from_be_bytes([preamble[8],preamble[9]]);
+    let flags = u16::from_be_bytes([preamble[10],preamble[11]]);
+    let header_len = u32::from_be_bytes([preamble[12],preamble[13],preamble[14],preamble[15]]);
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(Box::new(CalcifyError::FormatError));
+    }
+    let codec = Codec::from_id(flags >> CODEC_SHIFT)?;
+    // header_len isn't trusted until that many bytes arrive
+    let mut header = Vec::new();
+    rd.by_ref().take(header_len as u64).read_to_end(&mut header)?;
+    if header.len() < header_len as usize {
+        return Err(Box::new(CalcifyError::TruncatedError));
+    }
+    let mut bytes: &[u8] = &header;
+
+    let mut metadata: HashMap<String,FieldValue> = HashMap::new();
+    let mut index: Vec<IndexEntry> = Vec::new();
+    let len = read_map_len(&mut bytes)?;
+    for _ in 0..len {
+        let (key,rest) = read_str_from_slice(bytes).map_err(|_| header_error(&header, bytes))?;
+        bytes = rest;
+        match key {
+            "metadata" => {
+                let mlen = read_map_len(&mut bytes)?;
+                for _ in 0..mlen {
+                    let (mkey,rest) = read_str_from_slice(bytes).map_err(|_| header_error(&header, bytes))?;
+                    let (value,rest) = FieldValue::from_msg(rest).map_err(|_| header_error(&header, rest))?;
+                    metadata.insert(String::from(mkey),value);
+                    bytes = rest;
+                }
+            },
+            "index" => {
+                let ilen = read_array_len(&mut bytes)?;
+                for _ in 0..ilen {
+                    if read_array_len(&mut bytes)? != 5 {
+                        return Err(Box::new(header_error(&header, bytes)));
+                    }
+                    let (name,rest) = read_str_from_slice(bytes).map_err(|_| header_error(&header, bytes))?;
+                    let (subtype,mut rest) = read_str_from_slice(rest).map_err(|_| header_error(&header, rest))?;
+                    let offset: u64 = read_int(&mut rest)?;
+                    let len: u64 = read_int(&mut rest)?;
+                    let checksum = if rest.first() == Some(&0xc0) {
+                        read_nil(&mut rest)?;
+                        None
+                    } else {
+                        Some(read_int(&mut rest)?)
+                    };
+                    index.push(IndexEntry{name: String::from(name), subtype: String::from(subtype), offset, len, checksum});
+                    bytes = rest;
+                }
+            },
+            _ => return Err(Box::new(CalcifyError::parse(&format!("container header key {:?}",key)).at(PREAMBLE_LEN + header.len() - bytes.len()))),
+        }
+    }
+    Ok(Header{version, flags, codec, metadata, index, data_start: (PREAMBLE_LEN + header.len()) as u64})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_header() -> Result<(),Box<dyn error::Error>>{
+        let mut metadata = HashMap::new();
+        metadata.insert(String::from("Name"),FieldValue::from("Test"));
+        metadata.insert(String::from("dt"),FieldValue::from(0.5));
+        let blocks = vec![
+            Block{name: "a", subtype: "f64", bytes: vec![0x91,0x01]},
+            Block{name: "b", subtype: "Object", bytes: vec![0x90]},
+        ];
+        let buf = write_container(&metadata, blocks, Codec::None)?;
+        assert!(is_container(&buf));
+
+        let header = read_header(&mut &buf[..])?;
+        assert_eq!(header.version,FORMAT_VERSION);
+        assert_eq!(header.metadata,metadata);
+        assert_eq!(header.index[1].subtype,"Object");
+        assert_eq!(&*header.block(&header.index[0],&buf)?,&[0x91,0x01]);
+        assert_eq!(&*header.block(&header.index[1],&buf)?,&[0x90]);
+        assert!(header.block(&header.index[1],&buf[..buf.len()-1]).is_err());
+        let far = IndexEntry{offset: u64::MAX - 1, ..header.index[0].clone()};
+        assert!(matches!(header.block(&far,&buf),Err(CalcifyError::TruncatedError)));
+        let long = IndexEntry{len: u64::MAX, ..header.index[0].clone()};
+        assert!(matches!(header.block(&long,&buf),Err(CalcifyError::TruncatedError)));
+
+        let mut future = buf.clone();
+        future[9] = 99;
+        assert!(read_header(&mut &future[..]).is_err());
+        assert!(read_header(&mut &buf[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_container_deflate() -> Result<(),Box<dyn error::Error>>{
+        let long: Vec<u8> = (0..4096).map(|i| (i % 7) as u8).collect();
+        let blocks = vec![Block{name: "a", subtype: "f64", bytes: long.clone()}];
+        let buf = write_container(&HashMap::new(), blocks, Codec::Deflate)?;
+        assert!(buf.len() < long.len());
+        let header = read_header(&mut &buf[..])?;
+        assert_eq!(header.codec,Codec::Deflate);
+        assert_eq!(&*header.block(&header.index[0],&buf)?,&long[..]);
+
+        let mut unknown = buf.clone();
+        unknown[10] = 0x7f;
+        assert!(read_header(&mut &unknown[..]).is_err());
+
+        let mut huge = buf.clone();
+        huge[12..16].copy_from_slice(&[0xff; 4]);
+        let err = read_header(&mut &huge[..]).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::TruncatedError)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b"123456789"),0xcbf4_3926);
+        assert_eq!(crc32(b""),0);
+    }
+
+    #[test]
+    fn test_msg_len() {
+        let buf = vec![0x92,0xcb,0,0,0,0,0,0,0,0,0xa2,b'h',b'i',0xc0];
+        assert!(matches!(msg_len(&buf),Ok(13)));
+        assert!(matches!(msg_len(&buf[..12]),Err(CalcifyError::TruncatedError)));
+        assert!(matches!(msg_len(&buf[..5]),Err(CalcifyError::TruncatedError)));
+        assert!(matches!(msg_len(&[0xc1]),Err(CalcifyError::ParseError{position: Some(0),..})));
+        let err = decode_error("buf",&buf,Box::new(CalcifyError::parse("Collection element 1")));
+        assert!(matches!(&err,CalcifyError::SchemaError{what,..} if what == "buf"));
+        let inner = error::Error::source(&err).unwrap().downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(inner,CalcifyError::ParseError{what,..} if what == "Collection element 1"));
+        assert!(matches!(decode_error("buf",&buf[..12],Box::new(CalcifyError::TruncatedError)),CalcifyError::TruncatedError));
+    }
+}
@@ -0,0 +1,113 @@
+//! Support code for the expansions of `#[derive(Serializable, Deserializable)]`.
+//!
+//! Not a public API, the derive macros reach it through `calcify::__private`.
+
+use std::error;
+
+pub use rmp;
+pub use rmp::encode::ValueWriteError;
+
+use rmp::encode::{write_array_len, write_map_len, write_str};
+use rmp::decode::{read_array_len, read_map_len, read_marker, read_str_len};
+use rmp::Marker;
+
+use super::serializable::{Serializable, JsonValue};
+use super::errors::CalcifyError;
+
+/// Returns `{"key":value,...}` from field names and their already serialized values
+pub fn json_object(fields: &[(&str, String)]) -> String {
+    let str_vec: Vec<String> = fields.iter().map(|(k,v)| format!("{}:{}",k.to_json(),v)).collect();
+    format!("{{{}}}",str_vec.join(","))
+}
+
+/// Returns `[value,...]` from already serialized values
+pub fn json_array(vals: &[String]) -> String {
+    format!("[{}]",vals.join(","))
+}
+
+/// Returns the json of an enum variant, the bare name for unit variants, otherwise `{"name":payload}`
+pub fn json_variant(name: &str, payload: Option<String>) -> String {
+    match payload {
+        None => name.to_json(),
+        Some(p) => format!("{{{}:{}}}",name.to_json(),p),
+    }
+}
+
+/// Starts the msg of a struct, or the payload of a variant, as an array of `len` fields
+pub fn msg_array(len: u32) -> Result<Vec<u8>,ValueWriteError> {
+    let mut buf = Vec::new();
+    write_array_len(&mut buf, len)?;
+    Ok(buf)
+}
+
+/// Starts the msg of an enum variant, the bare name for unit variants, otherwise a map of one entry
+/// from the name to the array of `len` fields, which the caller appends
+pub fn msg_variant(name: &str, len: Option<u32>) -> Result<Vec<u8>,ValueWriteError> {
+    let mut buf = Vec::new();
+    if let Some(len) = len {
+        write_map_len(&mut buf, 1)?;
+        write_str(&mut buf, name)?;
+        write_array_len(&mut buf, len)?;
+    } else {
+        write_str(&mut buf, name)?;
+    }
+    Ok(buf)
+}
+
+/// Returns the elements of a json array of exactly `len` values
+pub fn json_fields(value: &JsonValue, len: usize) -> Result<&[JsonValue],CalcifyError> {
+    let vals = value.as_array()?;
+    if vals.len() != len {
+        return Err(CalcifyError::LengthError{expected: len, found: vals.len()});
+    }
+    Ok(vals)
+}
+
+/// Splits the json of an enum variant into its name and payload, see `json_variant`
+pub fn json_variant_parts(value: &JsonValue) -> Result<(&str, Option<&JsonValue>),CalcifyError> {
+    match value {
+        JsonValue::String(name) => Ok((name, None)),
+        JsonValue::Object(pairs) if pairs.len() == 1 => Ok((&pairs[0].0, Some(&pairs[0].1))),
+        _ => Err(CalcifyError::parse("enum variant")),
+    }
+}
+
+/// Wraps the error reading a field in a ParseError naming it
+pub fn field_error(field: &str, err: Box<dyn error::Error>) -> Box<dyn error::Error> {
+    Box::new(CalcifyError::parse(field).caused_by_boxed(err))
+}
+
+/// Reads the header of an array of exactly `len` fields
+pub fn read_fields(bytes: &mut &[u8], len: u32) -> Result<(),CalcifyError> {
+    let n = read_array_len(bytes).map_err(|e| CalcifyError::parse("msg array").caused_by(e))?;
+    if n != len {
+        return Err(CalcifyError::LengthError{expected: len as usize, found: n as usize});
+    }
+    Ok(())
+}
+
+/// Reads the name of an enum variant, and the header of its field array if it has one, see `msg_variant`
+pub fn read_variant(bytes: &mut &[u8]) -> Result<(String, Option<u32>),CalcifyError> {
+    let mut peek: &[u8] = bytes;
+    let has_payload = match read_marker(&mut peek).map_err(|_| CalcifyError::parse("enum variant"))? {
+        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            if read_map_len(bytes).map_err(|e| CalcifyError::parse("enum variant").caused_by(e))? != 1 {
+                return Err(CalcifyError::parse("enum variant"));
+            }
+            true
+        },
+        _ => false,
+    };
+    let len = read_str_len(bytes).map_err(|e| CalcifyError::parse("enum variant name").caused_by(e))? as usize;
+    if bytes.len() < len {
+        return Err(CalcifyError::parse("enum variant name"));
+    }
+    let name = std::str::from_utf8(&bytes[..len]).map_err(|e| CalcifyError::parse("enum variant name").caused_by(e))?.to_string();
+    *bytes = &bytes[len..];
+    if has_payload {
+        let n = read_array_len(bytes).map_err(|e| CalcifyError::parse("enum variant").caused_by(e))?;
+        Ok((name, Some(n)))
+    } else {
+        Ok((name, None))
+    }
+}
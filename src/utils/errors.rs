@@ -1,24 +1,168 @@
 use std::error;
 use std::fmt;
+use std::io;
+use std::num::{ParseFloatError, ParseIntError};
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+use std::sync::Arc;
+
+extern crate rmp;
+use rmp::decode::{ValueReadError, NumValueReadError};
+
+/// An underlying error, from rmp, number parsing or a nested read, kept by `CalcifyError::ParseError` and `MergeError`.
+///
+/// Shared, so CalcifyError stays Clone.
+pub type Source = Arc<dyn error::Error + Send + Sync>;
 
 /// Enum of built in Error types
+///
+/// Variants carry what is known where the error happened, like the key, subtype or byte offset,
+/// and `source` returns the underlying error of a `ParseError`, `SchemaError` or `MergeError`, so nested reads can be followed down
+/// from a Tree to the Branch, Collection element and field that failed.
+///
+/// # Example
+/// ```
+/// use std::error::Error;
+/// use calcify::{Collection, ThreeVec, Deserializable};
+/// use calcify::errors::CalcifyError;
+///
+/// let err = Collection::<ThreeVec>::from_json("[{\"x0\":1,\"x1\":2,\"x2\":3},{\"x0\":1,\"x2\":3}]").err().unwrap();
+/// let err = err.downcast_ref::<CalcifyError>().unwrap();
+/// assert!(matches!(err,CalcifyError::ParseError{..}));
+/// assert_eq!(err.to_string(),"Error on parse of Collection element 1.");
+/// let key = err.source().unwrap().downcast_ref::<CalcifyError>().unwrap();
+/// assert!(matches!(key,CalcifyError::KeyError{key} if key == "x1"));
+/// ```
 #[derive(Debug,Clone)]
 pub enum CalcifyError {
     LightSpeedError,
-    KeyError,
-    ParseError,
-    LengthError,
+    /// A key that is missing, or already present
+    KeyError { key: String },
+    /// Data that isn't valid json or msg, or doesn't have the expected shape
+    ///
+    /// * `what` - What was being read, e.g. `branch "fcol"` or `Collection element 3`
+    /// * `position` - Byte offset into the input, when known
+    /// * `source` - The underlying error, when there is one
+    ParseError { what: String, position: Option<usize>, source: Option<Source> },
+    /// A slice, array or collection of the wrong length
+    LengthError { expected: usize, found: usize },
     ObjectBranchDeserializeError,
+    /// Not a calcify container, or a newer version of one
+    FormatError,
+    TruncatedError,
+    /// A Branch of a container that doesn't match its checksum
+    ChecksumError { branch: String },
+    /// Intact data that doesn't decode as the type being read
+    ///
+    /// * `what` - The data, e.g. `branch "fcol"`
+    /// * `source` - The error it failed to decode with, when there is one
+    SchemaError { what: String, source: Option<Source> },
+    /// A subtype that is unknown, or belongs to a different type than `expected`
+    SubtypeError { subtype: String, expected: String },
+    /// Histograms whose Bins differ in their edges, `index` is the first Bin that does
+    BinningError { index: usize },
+    /// Trees or FeedTrees that can't be merged
+    ///
+    /// * `what` - What disagrees, e.g. `branch "hist"` or `field "Seed"`
+    /// * `source` - The underlying error, when there is one, e.g. a BinningError or SubtypeError
+    MergeError { what: String, source: Option<Source> },
+    /// A matrix with no inverse
+    SingularMatrixError,
+    /// A NaN or infinite value where a finite one is needed, `index` is the first element that is
+    NonFiniteError { index: usize },
 }
 
+impl CalcifyError {
+    /// Returns a KeyError for `key`
+    pub fn key(key: &str) -> CalcifyError {
+        CalcifyError::KeyError{key: String::from(key)}
+    }
+
+    /// Returns a ParseError while reading `what`, without a position or source
+    pub fn parse(what: &str) -> CalcifyError {
+        CalcifyError::ParseError{what: String::from(what), position: None, source: None}
+    }
+
+    /// Returns a MergeError for `what`, without a source
+    pub fn merge(what: &str) -> CalcifyError {
+        CalcifyError::MergeError{what: String::from(what), source: None}
+    }
+
+    /// Sets the byte offset of a ParseError. Other variants are returned unchanged.
+    pub fn at(self, pos: usize) -> CalcifyError {
+        match self {
+            CalcifyError::ParseError{what, source, ..} => CalcifyError::ParseError{what, position: Some(pos), source},
+            other => other,
+        }
+    }
+
+    /// Sets the source of a ParseError, SchemaError or MergeError. Other variants are returned unchanged.
+    pub fn caused_by<E: 'static + error::Error + Send + Sync>(self, err: E) -> CalcifyError {
+        self.with_source(Arc::new(err))
+    }
+
+    /// Sets the source of a ParseError, SchemaError or MergeError from a boxed error, as returned by `Deserializable`.
+    ///
+    /// CalcifyErrors, the rmp, io, number and utf8 errors of the built in reads are kept as they are, so they can be
+    /// downcast from `source()`. Anything else is kept only by its message, since it may not be `Send`.
+    pub fn caused_by_boxed(self, err: Box<dyn error::Error>) -> CalcifyError {
+        let source = keep::<CalcifyError>(err)
+            .or_else(keep::<ValueReadError>)
+            .or_else(keep::<NumValueReadError>)
+            .or_else(keep::<io::Error>)
+            .or_else(keep::<ParseFloatError>)
+            .or_else(keep::<ParseIntError>)
+            .or_else(keep::<Utf8Error>)
+            .or_else(keep::<FromUtf8Error>)
+            .unwrap_or_else(|other| Arc::new(Message(other.to_string())));
+        self.with_source(source)
+    }
+
+    fn with_source(self, source: Source) -> CalcifyError {
+        match self {
+            CalcifyError::ParseError{what, position, ..} => CalcifyError::ParseError{what, position, source: Some(source)},
+            CalcifyError::SchemaError{what, ..} => CalcifyError::SchemaError{what, source: Some(source)},
+            CalcifyError::MergeError{what, ..} => CalcifyError::MergeError{what, source: Some(source)},
+            other => other,
+        }
+    }
+}
+
+/// Moves a boxed error into a Source if it is an E
+fn keep<E: 'static + error::Error + Send + Sync>(err: Box<dyn error::Error>) -> Result<Source, Box<dyn error::Error>> {
+    err.downcast::<E>().map(|err| Arc::new(*err) as Source)
+}
+
+/// The message of an error that couldn't be kept itself
+#[derive(Debug,Clone)]
+struct Message(String);
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,"{}",self.0)
+    }
+}
+
+impl error::Error for Message {}
+
 impl fmt::Display for CalcifyError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             CalcifyError::LightSpeedError => write!(f,"Velocity greater than calcify::C_LIGHT."),
-            CalcifyError::KeyError => write!(f,"Invalid Key"),
-            CalcifyError::ParseError => write!(f,"Error on parse in Deserializable."),
-            CalcifyError::LengthError => write!(f,"Invalid slice length"),
+            CalcifyError::KeyError{key} => write!(f,"Invalid Key {:?}",key),
+            CalcifyError::ParseError{what, position: Some(pos), ..} => write!(f,"Error on parse of {} at byte {}.",what,pos),
+            CalcifyError::ParseError{what, position: None, ..} => write!(f,"Error on parse of {}.",what),
+            CalcifyError::LengthError{expected, found} => write!(f,"Invalid slice length {}, expected {}",found,expected),
             CalcifyError::ObjectBranchDeserializeError => write!(f,"Attempted to deserialize Object Branch."),
+            CalcifyError::FormatError => write!(f,"Unknown file format or version."),
+            CalcifyError::TruncatedError => write!(f,"File ended before the data did."),
+            CalcifyError::ChecksumError{branch} => write!(f,"Checksum mismatch in branch {:?}.",branch),
+            CalcifyError::SchemaError{what, ..} => write!(f,"Data of {} does not match the type being read.",what),
+            CalcifyError::SubtypeError{subtype, expected} => write!(f,"Branch subtype {:?} does not match the Collection type {:?}.",subtype,expected),
+            CalcifyError::BinningError{index} => write!(f,"Edges of bin {} do not match.",index),
+            CalcifyError::MergeError{what, ..} => write!(f,"Cannot merge {}.",what),
+            CalcifyError::SingularMatrixError => write!(f,"Matrix is singular."),
+            CalcifyError::NonFiniteError{index} => write!(f,"Element {} is NaN or infinite.",index),
         }
     }
 }
@@ -27,15 +171,50 @@ impl error::Error for CalcifyError {
     fn description(&self) -> &str {
         match *self {
             CalcifyError::LightSpeedError => "Cannot have a velocity greater than calcify::C_LIGHT",
-            CalcifyError::KeyError => "Convert HashMap Option behavior to Err on bad keys",
-            CalcifyError::ParseError => "Probably a formatting error when the data was serialized, or there is a type mismatch.",
-            CalcifyError::LengthError => "Length of slice must match Vector length",
+            CalcifyError::KeyError{..} => "Convert HashMap Option behavior to Err on bad keys",
+            CalcifyError::ParseError{..} => "Probably a formatting error when the data was serialized, or there is a type mismatch.",
+            CalcifyError::LengthError{..} => "Length of slice must match Vector length",
             CalcifyError::ObjectBranchDeserializeError => "Cannot deserialize Object Branch.",
+            CalcifyError::FormatError => "The file is not a calcify container, or was written by a newer version of calcify.",
+            CalcifyError::TruncatedError => "The file was probably cut short, e.g. by a killed run.",
+            CalcifyError::ChecksumError{..} => "The bytes of a Branch changed after it was written, the file is corrupt.",
+            CalcifyError::SchemaError{..} => "The file is intact, but was written with a different type or subtype.",
+            CalcifyError::SubtypeError{..} => "The subtype is unknown, or is the name of a different TreeSubtype or registered type.",
+            CalcifyError::BinningError{..} => "Histograms can only be added if they have the same edges.",
+            CalcifyError::MergeError{..} => "The subtypes, histogram edges or metadata of the two Trees disagree.",
+            CalcifyError::SingularMatrixError => "The determinant of the matrix is zero, so it has no inverse.",
+            CalcifyError::NonFiniteError{..} => "Histogram edges can only be found for finite values.",
         }
     }
 
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        // Generic error, underlying cause isn't tracked.
-        None
+        match self {
+            CalcifyError::ParseError{source: Some(err), ..} => Some(err.as_ref()),
+            CalcifyError::SchemaError{source: Some(err), ..} => Some(err.as_ref()),
+            CalcifyError::MergeError{source: Some(err), ..} => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Collection, ThreeVec, Deserializable};
+
+    #[test]
+    fn test_caused_by_boxed() {
+        // A Collection of one ThreeVec, whose second component is nil
+        let bytes = [0x91, 0x93, 0xcb, 0, 0, 0, 0, 0, 0, 0, 0, 0xc0, 0xc0];
+        let err = Collection::<ThreeVec>::from_msg(&bytes).err().unwrap();
+        let err = err.downcast_ref::<CalcifyError>().unwrap();
+        assert!(matches!(err,CalcifyError::ParseError{position: Some(1), ..}));
+        let source = error::Error::source(err).unwrap();
+        assert!(matches!(source.downcast_ref::<ValueReadError>(),Some(ValueReadError::TypeMismatch(_))));
+
+        let err = CalcifyError::parse("f64").caused_by_boxed(Box::new("1.x".parse::<f64>().unwrap_err()));
+        assert!(error::Error::source(&err).unwrap().is::<ParseFloatError>());
+        let err = CalcifyError::parse("f64").caused_by_boxed(Box::<dyn error::Error>::from("not kept"));
+        assert_eq!(error::Error::source(&err).unwrap().to_string(),"not kept");
     }
 }
@@ -0,0 +1,539 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+extern crate rmp;
+use rmp::Marker;
+use rmp::encode::*;
+use rmp::decode::*;
+
+use super::serializable::{Serializable, Deserializable, JsonValue, MAX_DEPTH, nests};
+use super::errors::CalcifyError;
+
+/// MsgPack extension type of timestamps
+pub(crate) const TIMESTAMP_EXT: i8 = -1;
+
+/// A metadata value of a Tree or FeedTree
+///
+/// Values are written natively, numbers as numbers, bools as bools and Maps as objects, so run parameters
+/// can be read back by machine. Times are written with the MsgPack timestamp extension, and as an
+/// RFC 3339 UTC string in json, e.g. `"2022-01-16T12:00:00Z"`, which is read back as a `Str`.
+/// `as_time` accepts either.
+///
+/// # Example
+/// ```
+/// use calcify::{Tree, FieldValue};
+/// use std::collections::BTreeMap;
+///
+/// let mut ttree = Tree::new("Test_Tree");
+/// ttree.set_field("dt", 0.01);
+/// ttree.set_field("N", 1000);
+/// let mut box_size = BTreeMap::new();
+/// box_size.insert(String::from("x"), FieldValue::from(10.0));
+/// ttree.set_field("box", box_size);
+///
+/// assert_eq!(ttree.get_field("dt").and_then(FieldValue::as_f64),Some(0.01));
+/// assert_eq!(ttree.get_field("N").and_then(FieldValue::as_i64),Some(1000));
+/// assert_eq!(ttree.get_field("box").unwrap().to_string(),"{\"x\":10.0}");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum FieldValue {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Time(SystemTime),
+    List(Vec<FieldValue>),
+    Map(BTreeMap<String,FieldValue>),
+}
+
+impl FieldValue {
+    /// Returns the contents of a Str
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FieldValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns an Int
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            FieldValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns a Float, or an Int as f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FieldValue::Float(f) => Some(*f),
+            FieldValue::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns a Bool
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            FieldValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns a Time, or a Str holding an RFC 3339 time as read from json
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::FieldValue;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let t = FieldValue::from("1970-01-02T00:00:00.5Z").as_time().unwrap();
+    /// assert_eq!(t,UNIX_EPOCH + Duration::from_millis(86_400_500));
+    /// ```
+    pub fn as_time(&self) -> Option<SystemTime> {
+        match self {
+            FieldValue::Time(t) => Some(*t),
+            FieldValue::Str(s) => parse_timestamp(s).and_then(|(secs,nanos)| from_unix(secs,nanos)),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of a List
+    pub fn as_list(&self) -> Option<&[FieldValue]> {
+        match self {
+            FieldValue::List(vals) => Some(vals),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries of a Map
+    pub fn as_map(&self) -> Option<&BTreeMap<String,FieldValue>> {
+        match self {
+            FieldValue::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+}
+
+/// Strs are displayed bare, everything else as json
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldValue::Str(s) => write!(f, "{}", s),
+            FieldValue::Time(t) => {
+                let (secs,nanos) = to_unix(*t);
+                write!(f, "{}", format_timestamp(secs,nanos))
+            },
+            other => write!(f, "{}", other.to_json()),
+        }
+    }
+}
+
+impl PartialEq<str> for FieldValue {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for FieldValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+macro_rules! impl_from {
+    ($($t:ty => $v:ident),*) => {$(
+        impl From<$t> for FieldValue {
+            fn from(x: $t) -> Self {
+                FieldValue::$v(x.into())
+            }
+        }
+    )*}
+}
+
+impl_from!(
+    &str => Str,
+    String => Str,
+    &String => Str,
+    i64 => Int,
+    i32 => Int,
+    u32 => Int,
+    f64 => Float,
+    f32 => Float,
+    bool => Bool,
+    SystemTime => Time,
+    Vec<FieldValue> => List,
+    BTreeMap<String,FieldValue> => Map
+);
+
+/// Counts above `i64::MAX` are clamped to it
+impl From<usize> for FieldValue {
+    fn from(x: usize) -> Self {
+        FieldValue::Int(i64::try_from(x).unwrap_or(i64::MAX))
+    }
+}
+
+impl Serializable for FieldValue {
+    fn to_json(&self) -> String {
+        match self {
+            FieldValue::Str(s) => s.to_json(),
+            FieldValue::Int(i) => i.to_json(),
+            // Debug keeps the decimal point of integral floats, so they are read back as floats
+            FieldValue::Float(x) => format!("{:?}",x),
+            FieldValue::Bool(b) => b.to_json(),
+            FieldValue::Time(_) => self.to_string().to_json(),
+            FieldValue::List(vals) => {
+                let str_vec: Vec<String> = vals.iter().map(|x| x.to_json()).collect();
+                format!("[{}]",str_vec.join(","))
+            },
+            FieldValue::Map(map) => {
+                let str_vec: Vec<String> = map.iter().map(|(k,v)| format!("{}:{}",k.to_json(),v.to_json())).collect();
+                format!("{{{}}}",str_vec.join(","))
+            },
+        }
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut buf = Vec::new();
+        match self {
+            FieldValue::Str(s) => write_str(&mut buf, s)?,
+            FieldValue::Int(i) => {write_sint(&mut buf, *i)?;},
+            FieldValue::Float(x) => write_f64(&mut buf, *x)?,
+            FieldValue::Bool(b) => write_bool(&mut buf, *b).map_err(ValueWriteError::InvalidMarkerWrite)?,
+            FieldValue::Time(t) => {
+                let (secs,nanos) = to_unix(*t);
+                write_timestamp(&mut buf, secs, nanos)?;
+            },
+            FieldValue::List(vals) => {
+                write_array_len(&mut buf, vals.len() as u32)?;
+                for x in vals.iter() {
+                    buf.append(&mut x.to_msg()?);
+                }
+            },
+            FieldValue::Map(map) => {
+                write_map_len(&mut buf, map.len() as u32)?;
+                for (k,v) in map.iter() {
+                    write_str(&mut buf, k)?;
+                    buf.append(&mut v.to_msg()?);
+                }
+            },
+        }
+        Ok(buf)
+    }
+}
+
+/// Integral json numbers are read as Ints and the rest as Floats. `null` is not a FieldValue.
+impl Deserializable for FieldValue {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(match value {
+            JsonValue::Null => return Err(Box::new(CalcifyError::parse("metadata field, null"))),
+            JsonValue::Bool(b) => FieldValue::Bool(*b),
+            JsonValue::Number(n) => match n.parse::<i64>() {
+                Ok(i) => FieldValue::Int(i),
+                Err(_) => FieldValue::Float(value.as_f64()?),
+            },
+            JsonValue::String(s) => FieldValue::Str(s.clone()),
+            JsonValue::Array(vals) => FieldValue::List(vals.iter().map(FieldValue::from_json_value).collect::<Result<_,_>>()?),
+            JsonValue::Object(pairs) => {
+                let mut map = BTreeMap::new();
+                for (k,v) in pairs.iter() {
+                    map.insert(k.clone(),FieldValue::from_json_value(v)?);
+                }
+                FieldValue::Map(map)
+            },
+        })
+    }
+
+    fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        field_msg(bytes, bytes.len(), 0)
+    }
+}
+
+/// Reads one value of FieldValue::from_msg, `depth` lists and maps down from the start of the `whole` input
+fn field_msg(mut bytes: &[u8], whole: usize, depth: usize) -> Result<(FieldValue,&[u8]), Box<dyn error::Error>> {
+    let marker = Marker::from_u8(*bytes.first().ok_or_else(|| CalcifyError::parse("metadata field"))?);
+    if depth == MAX_DEPTH && nests(marker) {
+        return Err(Box::new(CalcifyError::parse("metadata field, nested too deeply").at(whole - bytes.len())));
+    }
+    let value = match marker {
+        Marker::FixExt4 | Marker::FixExt8 | Marker::Ext8 => {
+            let (secs,nanos) = read_timestamp(&mut bytes)?;
+            FieldValue::Time(from_unix(secs,nanos).ok_or_else(|| CalcifyError::parse("timestamp, out of range"))?)
+        },
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
+            let len = read_array_len(&mut bytes)?;
+            let mut vals = Vec::new();
+            for _ in 0..len {
+                let (v,rest) = field_msg(bytes, whole, depth + 1)?;
+                vals.push(v);
+                bytes = rest;
+            }
+            FieldValue::List(vals)
+        },
+        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            let len = read_map_len(&mut bytes)?;
+            let mut map = BTreeMap::new();
+            for _ in 0..len {
+                let (k,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("msg map key"))?;
+                let (v,rest) = field_msg(rest, whole, depth + 1)?;
+                map.insert(String::from(k),v);
+                bytes = rest;
+            }
+            FieldValue::Map(map)
+        },
+        _ => {
+            let (value,rest) = JsonValue::from_msg(bytes)?;
+            bytes = rest;
+            FieldValue::from_json_value(&value)?
+        },
+    };
+    Ok((value,bytes))
+}
+
+/// Written as a string for Times, the same as json
+#[cfg(feature = "serde")]
+impl serde::Serialize for FieldValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeSeq, SerializeMap};
+        match self {
+            FieldValue::Str(s) => serializer.serialize_str(s),
+            FieldValue::Int(i) => serializer.serialize_i64(*i),
+            FieldValue::Float(x) => serializer.serialize_f64(*x),
+            FieldValue::Bool(b) => serializer.serialize_bool(*b),
+            FieldValue::Time(_) => serializer.serialize_str(&self.to_string()),
+            FieldValue::List(vals) => {
+                let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+                for x in vals.iter() {
+                    seq.serialize_element(x)?;
+                }
+                seq.end()
+            },
+            FieldValue::Map(map) => {
+                let mut ser = serializer.serialize_map(Some(map.len()))?;
+                for (k,v) in map.iter() {
+                    ser.serialize_entry(k,v)?;
+                }
+                ser.end()
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FieldValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let value = <JsonValue as serde::Deserialize>::deserialize(deserializer)?;
+        FieldValue::from_json_value(&value).map_err(D::Error::custom)
+    }
+}
+
+/// Splits a SystemTime into whole seconds since the epoch, rounded down, and nanoseconds
+fn to_unix(t: SystemTime) -> (i64, u32) {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(err) => {
+            let d = err.duration();
+            let secs = -(d.as_secs() as i64);
+            match d.subsec_nanos() {
+                0 => (secs, 0),
+                nanos => (secs - 1, 1_000_000_000 - nanos),
+            }
+        },
+    }
+}
+
+/// Returns None if the time can't be represented by SystemTime
+fn from_unix(secs: i64, nanos: u32) -> Option<SystemTime> {
+    let nanos = Duration::from_nanos(nanos as u64);
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))?.checked_add(nanos)
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))?.checked_add(nanos)
+    }
+}
+
+/// Writes the smallest MsgPack timestamp that holds the time
+fn write_timestamp(buf: &mut Vec<u8>, secs: i64, nanos: u32) -> Result<(),ValueWriteError> {
+    if secs >> 34 == 0 {
+        let data = ((nanos as u64) << 34) | secs as u64;
+        if data >> 32 == 0 {
+            write_ext_meta(buf, 4, TIMESTAMP_EXT)?;
+            buf.extend_from_slice(&(data as u32).to_be_bytes());
+        } else {
+            write_ext_meta(buf, 8, TIMESTAMP_EXT)?;
+            buf.extend_from_slice(&data.to_be_bytes());
+        }
+    } else {
+        write_ext_meta(buf, 12, TIMESTAMP_EXT)?;
+        buf.extend_from_slice(&nanos.to_be_bytes());
+        buf.extend_from_slice(&secs.to_be_bytes());
+    }
+    Ok(())
+}
+
+/// Reads any of the three MsgPack timestamp layouts, as seconds and nanoseconds since the epoch
+pub(crate) fn read_timestamp(bytes: &mut &[u8]) -> Result<(i64, u32), CalcifyError> {
+    let meta = read_ext_meta(bytes).map_err(|e| CalcifyError::parse("timestamp").caused_by(e))?;
+    if meta.typeid != TIMESTAMP_EXT || bytes.len() < meta.size as usize {
+        return Err(CalcifyError::parse("timestamp"));
+    }
+    let (data, rest) = bytes.split_at(meta.size as usize);
+    *bytes = rest;
+    match *data {
+        [a,b,c,d] => Ok((u32::from_be_bytes([a,b,c,d]) as i64, 0)),
+        [a,b,c,d,e,f,g,h] => {
+            let v = u64::from_be_bytes([a,b,c,d,e,f,g,h]);
+            Ok(((v & 0x3_ffff_ffff) as i64, (v >> 34) as u32))
+        },
+        [a,b,c,d,e,f,g,h,i,j,k,l] => Ok((i64::from_be_bytes([e,f,g,h,i,j,k,l]), u32::from_be_bytes([a,b,c,d]))),
+        _ => Err(CalcifyError::parse("timestamp")),
+    }
+}
+
+/// Returns the RFC 3339 UTC form of a time, with as many fractional digits as it needs
+pub(crate) fn format_timestamp(secs: i64, nanos: u32) -> String {
+    let (y, m, d) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    let mut out = format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", y, m, d, rem/3600, rem%3600/60, rem%60);
+    if nanos > 0 {
+        out.push_str(format!(".{:09}", nanos).trim_end_matches('0'));
+    }
+    out.push('Z');
+    out
+}
+
+/// Parses an RFC 3339 time with a `Z` or numeric offset, as seconds and nanoseconds since the epoch
+fn parse_timestamp(s: &str) -> Option<(i64, u32)> {
+    let b = s.as_bytes();
+    let num = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = b.get(range)?;
+        if digits.iter().all(u8::is_ascii_digit) {
+            Some(digits.iter().fold(0, |acc, x| acc*10 + (x - b'0') as i64))
+        } else {
+            None
+        }
+    };
+    if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || !matches!(b[10], b'T' | b't' | b' ') || b[13] != b':' || b[16] != b':' {
+        return None;
+    }
+    let (y, m, d) = (num(0..4)?, num(5..7)?, num(8..10)?);
+    let (hh, mm, ss) = (num(11..13)?, num(14..16)?, num(17..19)?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) || hh > 23 || mm > 59 || ss > 60 {
+        return None;
+    }
+    let mut pos = 19;
+    let mut nanos: u32 = 0;
+    if b[pos] == b'.' {
+        let start = pos + 1;
+        pos = start;
+        while pos < b.len() && b[pos].is_ascii_digit() {
+            pos += 1;
+        }
+        if pos == start {
+            return None;
+        }
+        let frac = &s[start..pos.min(start + 9)];
+        nanos = format!("{:0<9}", frac).parse().ok()?;
+    }
+    let offset = match b.get(pos..)? {
+        [b'Z'] | [b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let off = num(pos+1..pos+3)? * 3600 + num(pos+4..pos+6)? * 60;
+            if *sign == b'+' { off } else { -off }
+        },
+        _ => return None,
+    };
+    let secs = days_from_civil(y, m as u32, d as u32) * 86_400 + hh * 3600 + mm * 60 + ss.min(59) - offset;
+    Some((secs, nanos))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian (year, month, day) of days since 1970-01-01
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let y = yoe + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(format_timestamp(0,0),"1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400,500_000_000),"2000-02-29T00:00:00.5Z");
+        assert_eq!(format_timestamp(-1,0),"1969-12-31T23:59:59Z");
+        for &(secs,nanos) in &[(0,0),(951_782_400,500_000_000),(-1,999_999_999),(1 << 34,1),(-62_135_596_800,0)] {
+            assert_eq!(parse_timestamp(&format_timestamp(secs,nanos)),Some((secs,nanos)));
+            let mut buf = Vec::new();
+            write_timestamp(&mut buf, secs, nanos).unwrap();
+            assert_eq!(read_timestamp(&mut &buf[..]).unwrap(),(secs,nanos));
+        }
+        assert_eq!(parse_timestamp("2000-02-29T01:30:00+01:30"),Some((951_782_400,0)));
+        assert_eq!(parse_timestamp("2000-02-29"),None);
+        assert_eq!(parse_timestamp("2000-13-29T00:00:00Z"),None);
+
+        let before = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(to_unix(before),(-2,500_000_000));
+        assert_eq!(from_unix(-2,500_000_000),Some(before));
+    }
+
+    #[test]
+    fn test_field_value() -> Result<(),Box<dyn error::Error>> {
+        let mut map = BTreeMap::new();
+        map.insert(String::from("dt"),FieldValue::from(0.5));
+        map.insert(String::from("steps"),FieldValue::from(vec![FieldValue::from(1),FieldValue::from(2.0)]));
+        let when = UNIX_EPOCH + Duration::new(1_642_291_200, 250);
+        let vals = [
+            FieldValue::from("text"),
+            FieldValue::from(-3),
+            FieldValue::from(1.0),
+            FieldValue::from(true),
+            FieldValue::from(when),
+            FieldValue::from(map),
+        ];
+        for v in vals.iter() {
+            assert_eq!(&FieldValue::from_msg(&v.to_msg()?)?.0,v);
+            if let FieldValue::Time(_) = v {
+                let json = FieldValue::from_json(&v.to_json())?;
+                assert_eq!(json,"2022-01-16T00:00:00.00000025Z");
+                assert_eq!(json.as_time(),Some(when));
+            } else {
+                assert_eq!(&FieldValue::from_json(&v.to_json())?,v);
+            }
+        }
+        assert_eq!(FieldValue::from(1.0).to_json(),"1.0");
+        assert_eq!(FieldValue::from(1).as_f64(),Some(1.0));
+        assert_eq!(FieldValue::from(1.0).as_i64(),None);
+        assert!(FieldValue::from_json("null").is_err());
+        let err = FieldValue::from_msg(&[0x81, 0xa1, b'a'].repeat(100_000)).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::ParseError{position: Some(_),..})));
+        Ok(())
+    }
+}
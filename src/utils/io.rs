@@ -7,43 +7,171 @@ use std::fs;
 
 use super::serializable::{Serializable, Deserializable};
 use super::errors::CalcifyError;
+use super::container;
+
+pub use super::container::FORMAT_VERSION;
+pub use super::container::Codec;
 
 /// Trait for saving a *Tree to disk.
+///
+/// Each method that takes a filename delegates to a `_to` variant that writes to any `io::Write`,
+/// like an in memory buffer, a socket or stdout.
 pub trait ToFile {
     /// Write Self as json to file.
     fn write_json(&self, filename: &str) -> Result<(), Box<dyn error::Error>>
-        where Self: Serializable {
-            let f = fs::File::create(filename)?;
-            let mut wr = BufWriter::new(f);
-            wr.write(self.to_json().as_bytes())?;
+        where Self: Serializable + Sized {
+            let mut wr = BufWriter::new(fs::File::create(filename)?);
+            self.write_json_to(&mut wr)?;
+            wr.flush()?;
             Ok(())
     }
 
-    /// Write Self as msg to file.
+    /// Write Self as json to a writer.
+    fn write_json_to<W: Write>(&self, mut wr: W) -> Result<(), Box<dyn error::Error>>
+        where Self: Serializable + Sized {
+            wr.write_all(self.to_json().as_bytes())?;
+            Ok(())
+    }
+
+    /// Write Self as msg to file, in the legacy layout without checksums. Use `write_container` for checksummed Branches.
     fn write_msg(&self, filename: &str) -> Result<(), Box<dyn error::Error>>
-        where Self: Serializable {
-            let f = fs::File::create(filename)?;
-            let mut wr = BufWriter::new(f);
-            wr.write(self.to_msg()?.as_slice())?;
+        where Self: Serializable + Sized {
+            let mut wr = BufWriter::new(fs::File::create(filename)?);
+            self.write_msg_to(&mut wr)?;
+            wr.flush()?;
+            Ok(())
+    }
+
+    /// Write Self as msg to a writer.
+    fn write_msg_to<W: Write>(&self, mut wr: W) -> Result<(), Box<dyn error::Error>>
+        where Self: Serializable + Sized {
+            wr.write_all(self.to_msg()?.as_slice())?;
+            Ok(())
+    }
+
+    /// Returns Self in the versioned container format, with each Branch compressed by the Codec.
+    /// Only Trees and FeedTrees have a container format.
+    fn to_container_with(&self, _codec: Codec) -> Result<Vec<u8>, Box<dyn error::Error>> {
+        Err(Box::new(CalcifyError::FormatError))
+    }
+
+    /// Returns Self in the versioned container format, uncompressed.
+    fn to_container(&self) -> Result<Vec<u8>, Box<dyn error::Error>> {
+        self.to_container_with(Codec::None)
+    }
+
+    /// Write Self as a versioned container to file.
+    fn write_container(&self, filename: &str) -> Result<(), Box<dyn error::Error>>
+        where Self: Sized {
+            let mut wr = BufWriter::new(fs::File::create(filename)?);
+            self.write_container_to(&mut wr, Codec::None)?;
+            wr.flush()?;
+            Ok(())
+    }
+
+    /// Write Self as a versioned container to a writer, with each Branch compressed by the Codec.
+    fn write_container_to<W: Write>(&self, mut wr: W, codec: Codec) -> Result<(), Box<dyn error::Error>>
+        where Self: Sized {
+            wr.write_all(self.to_container_with(codec)?.as_slice())?;
+            Ok(())
+    }
+
+    /// Write Self as a versioned container to file, with each Branch compressed by deflate.
+    ///
+    /// The codec is recorded in the file, so it is read back with `read_msg`.
+    fn write_msg_compressed(&self, filename: &str) -> Result<(), Box<dyn error::Error>>
+        where Self: Sized {
+            let mut wr = BufWriter::new(fs::File::create(filename)?);
+            self.write_container_to(&mut wr, Codec::Deflate)?;
+            wr.flush()?;
             Ok(())
     }
 }
 
 /// Trait for loading a *Tree from disk.
+///
+/// Each method that takes a filename delegates to a `_from` variant that reads from any `io::Read`.
 pub trait FromFile {
     /// Read json file to Self.
     fn read_json(filename: &str) -> Result<Self, Box<dyn error::Error>>
         where Self: Deserializable + Sized {
-            Self::from_json(&fs::read_to_string(filename)?)
+            Self::read_json_from(fs::File::open(filename)?)
+    }
+
+    /// Read json from a reader to Self.
+    fn read_json_from<R: Read>(mut rd: R) -> Result<Self, Box<dyn error::Error>>
+        where Self: Deserializable + Sized {
+            let mut s = String::new();
+            rd.read_to_string(&mut s)?;
+            Self::from_json(&s)
     }
 
-    /// Read msg file to Self.
+    /// Read msg file to Self, in either the legacy layout or the versioned container format.
+    ///
+    /// The codec of a container is recorded in its header, so files from `write_msg_compressed` are read here too.
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::TruncatedError` if the file ends early
+    /// * `CalcifyError::ChecksumError` if a Branch of a container doesn't match its checksum
+    /// * `CalcifyError::SchemaError` if the file is intact but doesn't decode as Self
+    /// * `CalcifyError::ParseError` if the file isn't MsgPack at all
     fn read_msg(filename: &str) -> Result<Self, Box<dyn error::Error>>
         where Self: Deserializable + Sized {
-            if let Ok((obj,_)) = Self::from_msg(&fs::read(filename)?) {
-                return Ok(obj);
-            } else {
-                return Err(Box::new(CalcifyError::ParseError));
+            Self::read_msg_from(fs::File::open(filename)?)
+    }
+
+    /// Read msg from a reader to Self, in either the legacy layout or the versioned container format.
+    ///
+    /// Reads to the end of the reader, and has the same errors as `read_msg`.
+    fn read_msg_from<R: Read>(mut rd: R) -> Result<Self, Box<dyn error::Error>>
+        where Self: Deserializable + Sized {
+            let mut bytes = Vec::new();
+            rd.read_to_end(&mut bytes)?;
+            if container::is_container(&bytes) {
+                return Self::from_container(&bytes);
+            }
+            match Self::from_msg(&bytes) {
+                Ok((obj,_)) => Ok(obj),
+                Err(e) => Err(Box::new(container::decode_error("file",&bytes,e))),
             }
     }
+
+    /// Returns Self from the versioned container format. Only Trees and FeedTrees have a container format.
+    fn from_container(_bytes: &[u8]) -> Result<Self, Box<dyn error::Error>>
+        where Self: Sized {
+            Err(Box::new(CalcifyError::FormatError))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Tree, FeedTree, Collection, ThreeVec};
+
+    #[test]
+    fn test_write_to_buffer() -> Result<(),Box<dyn error::Error>>{
+        let mut ttree = Tree::new("Test_Tree");
+        ttree.add_field("Desc", "This is a Tree for testing.")?;
+        ttree.add_branch("fcol", Collection::from(vec![1.0,2.0]))?;
+
+        let mut buf: Vec<u8> = Vec::new();
+        ttree.write_msg_to(&mut buf)?;
+        let mut oo = Tree::read_msg_from(&buf[..])?;
+        assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+
+        for codec in [Codec::None, Codec::Deflate].iter() {
+            let mut buf: Vec<u8> = Vec::new();
+            ttree.write_container_to(&mut buf, *codec)?;
+            let mut oo = Tree::read_msg_from(&buf[..])?;
+            assert_eq!(oo.read_branch::<f64>("fcol")?,Collection::from(vec![1.0,2.0]));
+        }
+
+        let mut ftree: FeedTree<ThreeVec> = FeedTree::new("Test_Tree","ThreeVec");
+        ftree.add_feed("fcol", Collection::from(vec![ThreeVec::new(1.0,2.0,3.0)]))?;
+        let mut buf: Vec<u8> = Vec::new();
+        ftree.write_json_to(&mut buf)?;
+        assert_eq!(FeedTree::<ThreeVec>::read_json_from(&buf[..])?,ftree);
+        Ok(())
+    }
 }
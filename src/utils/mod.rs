@@ -4,21 +4,54 @@ pub mod consts;
 pub mod serializable;
 pub use serializable::Serializable;
 pub use serializable::Deserializable;
+pub use serializable::JsonValue;
+
+mod field_value;
+pub use field_value::FieldValue;
 
 /// Errors  module
 pub mod errors;
 
+pub(crate) mod container;
+
+pub(crate) mod derive;
+
 /// ## File IO
 ///
 /// * Even though json is supported for both reading and writing, it's not as efficiently implemented and may lead to slowdowns when reading large files. Consider only using it for debugging, so that you can read the results of tests, otherwise use msg.
 ///
 /// * Feel free to use Serde when implementing the Serialization traits for your types
 ///
+/// * With the `serde` feature, every calcify type, including Trees and FeedTrees, implements `Serialize` and `Deserialize`. Human readable formats like serde_json get the same shape as `to_json`, and binary formats like rmp-serde the same shape as `to_msg`, so existing files can be read either way.
+///
+/// * With the `derive` feature, `#[derive(Serializable, Deserializable)]` writes both traits for your structs and enums, as object style json and array style msg, like the built in types. See the `calcify-derive` crate docs for the exact format.
+///
 /// ### Trees
 ///
 /// | Write      | Read |
 /// | ----------- | ----------- |
-/// | Supports all subtypes      | Internal types only, and not `Object`|
+/// | Supports all subtypes      | Internal types, and `Object` or other user subtypes once registered with `Tree::register`|
+///
+/// * `Tree::add_branch` takes the Branch subtype from the `TreeSubtype` of the Collection type, and `read_branch` checks it. Implement `TreeSubtype` for your own types, or use `add_branch_as` with a registered subtype or `Object`.
+///
+/// * To read only some Branches of a large Tree msg file, open it as a `TreeFile`.
+///
+/// * `branch_names`, `branches` and `branch_info` list the Branches of a Tree with their subtype, element count and msg size, without decoding them. `remove_branch` and `rename_branch` edit them in place.
+///
+/// * `Tree::merge` and `FeedTree::merge` combine the output of parallel runs. Matching Collections and feeds are appended, `Bin` and `PointBin` histograms with the same edges have their counts added, and metadata fields that disagree are resolved by a `MergePolicy`.
+///
+/// * Metadata fields are `FieldValue`s, strings, ints, floats, bools, times, lists or maps, written natively to both json and msg so run parameters stay machine-readable. Times are RFC 3339 strings in json. Trees and FeedTrees have `add_field`, `set_field`, `get_field`, `remove_field` and `fields`.
+///
+/// ### Containers
+///
+/// `write_msg` writes the legacy layout, a single MsgPack map. `write_container` writes a versioned container with
+/// magic bytes, a format version, the metadata and an index of Branch offsets and CRC32 checksums, followed by the Branches themselves.
+/// `read_msg` and `TreeFile` detect which of the two they are reading.
+///
+/// `write_msg_compressed` writes a container with each Branch compressed by deflate. The codec is recorded in the header, so `read_msg` reads these too.
+///
+/// Only containers have checksums, so only `write_container`, `write_container_to` and `write_msg_compressed` output is checked on read.
+/// The legacy layout and FeedStream files have none, and a corrupt value in them is only caught if it no longer decodes.
 ///
 /// ### FeedTrees
 ///
@@ -1,9 +1,20 @@
 use std::marker::Sized;
 use std::error;
+use std::str;
+use std::char;
+use std::convert::TryFrom;
+use std::convert::TryInto;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
 
 extern crate rmp;
+use rmp::Marker;
 use rmp::encode::*;
 use rmp::decode::*;
+
+use super::errors::CalcifyError;
+use super::field_value::{read_timestamp, format_timestamp, TIMESTAMP_EXT};
+
 /// Serialization trait which all types you intend to put in a Tree need to implement.
 pub trait Serializable {
     /// Return object intensive json string
@@ -24,11 +35,566 @@ pub trait Deserializable {
     /// Return Self from string
     fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>>
         where Self: Sized;
+    /// Return Self from an already parsed JsonValue
+    ///
+    /// The default re-serializes `value` and hands it to `from_json`, so types that only implement
+    /// `from_json` still work when nested inside a Collection or Tree.
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>>
+        where Self: Sized {
+            Self::from_json(&value.to_json())
+    }
     /// Return a tuple of Self and a &[u8] of remaining unparsed bytes from a byte array
     fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>>
         where Self: Sized;
 }
 
+/// A parsed json value.
+///
+/// Every built in `Deserializable::from_json` parses its input into a JsonValue first,
+/// so whitespace, key order and escaped strings are all handled in one place.
+///
+/// # Note
+///
+/// * Numbers keep their source text, so large `u64` counts are not rounded through `f64`.
+/// * `NaN`, `inf` and `-inf` are accepted as numbers, since that is how Rust formats non-finite `f64`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String,JsonValue)>),
+}
+
+impl JsonValue {
+    /// Parse a complete json document into a JsonValue
+    ///
+    /// # Arguments
+    ///
+    /// * `s` - &str
+    ///
+    /// # Example
+    /// ```
+    /// use calcify::JsonValue;
+    ///
+    /// let value = JsonValue::parse("{ \"x1\" : 2.0, \"x0\":1.0 }").unwrap();
+    /// assert_eq!(value.get("x0").unwrap().as_f64().unwrap(),1.0);
+    /// assert_eq!(value.get("x1").unwrap().as_f64().unwrap(),2.0);
+    /// ```
+    pub fn parse(s: &str) -> Result<JsonValue,CalcifyError> {
+        let mut parser = JsonParser::new(s);
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.bytes.len() {
+            return Err(parser.error("json, trailing characters"));
+        }
+        Ok(value)
+    }
+
+    /// Returns the value stored under `key` in an Object
+    ///
+    /// # Errors
+    ///
+    /// * `CalcifyError::ParseError` if self is not an Object
+    /// * `CalcifyError::KeyError` if `key` is missing
+    pub fn get(&self, key: &str) -> Result<&JsonValue,CalcifyError> {
+        match self {
+            JsonValue::Object(pairs) => pairs.iter()
+                                            .find(|(k,_)| k == key)
+                                            .map(|(_,v)| v)
+                                            .ok_or_else(|| CalcifyError::key(key)),
+            _ => Err(CalcifyError::parse("json object")),
+        }
+    }
+
+    /// Returns the (key, value) pairs of an Object, in the order they were written
+    pub fn as_object(&self) -> Result<&[(String,JsonValue)],CalcifyError> {
+        match self {
+            JsonValue::Object(pairs) => Ok(pairs),
+            _ => Err(CalcifyError::parse("json object")),
+        }
+    }
+
+    /// Returns the elements of an Array
+    pub fn as_array(&self) -> Result<&[JsonValue],CalcifyError> {
+        match self {
+            JsonValue::Array(vals) => Ok(vals),
+            _ => Err(CalcifyError::parse("json array")),
+        }
+    }
+
+    /// Returns the unescaped contents of a String
+    pub fn as_str(&self) -> Result<&str,CalcifyError> {
+        match self {
+            JsonValue::String(s) => Ok(s),
+            _ => Err(CalcifyError::parse("json string")),
+        }
+    }
+
+    /// Returns a Number as f64
+    pub fn as_f64(&self) -> Result<f64,CalcifyError> {
+        match self {
+            JsonValue::Number(n) => n.parse::<f64>().map_err(|e| CalcifyError::parse("json number").caused_by(e)),
+            _ => Err(CalcifyError::parse("json number")),
+        }
+    }
+
+    /// Returns a Number as u64
+    ///
+    /// Integral values written in floating point notation, e.g. `10.0`, are also accepted.
+    pub fn as_u64(&self) -> Result<u64,CalcifyError> {
+        match self {
+            JsonValue::Number(n) => {
+                if let Ok(u) = n.parse::<u64>() {
+                    return Ok(u);
+                }
+                let f = n.parse::<f64>().map_err(|e| CalcifyError::parse("json number as u64").caused_by(e))?;
+                if f >= 0.0 && f.fract() == 0.0 && f <= u64::MAX as f64 {
+                    Ok(f as u64)
+                } else {
+                    Err(CalcifyError::parse("json number as u64"))
+                }
+            },
+            _ => Err(CalcifyError::parse("json number as u64")),
+        }
+    }
+
+    /// Returns a Number as i64
+    ///
+    /// Integral values written in floating point notation, e.g. `-10.0`, are also accepted.
+    pub fn as_i64(&self) -> Result<i64,CalcifyError> {
+        match self {
+            JsonValue::Number(n) => {
+                if let Ok(i) = n.parse::<i64>() {
+                    return Ok(i);
+                }
+                let f = n.parse::<f64>().map_err(|e| CalcifyError::parse("json number as i64").caused_by(e))?;
+                if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+                    Ok(f as i64)
+                } else {
+                    Err(CalcifyError::parse("json number as i64"))
+                }
+            },
+            _ => Err(CalcifyError::parse("json number as i64")),
+        }
+    }
+
+    /// Returns the value of a Bool
+    pub fn as_bool(&self) -> Result<bool,CalcifyError> {
+        match self {
+            JsonValue::Bool(b) => Ok(*b),
+            _ => Err(CalcifyError::parse("json bool")),
+        }
+    }
+}
+
+impl Serializable for JsonValue {
+    fn to_json(&self) -> String {
+        match self {
+            JsonValue::Null => String::from("null"),
+            JsonValue::Bool(b) => format!("{}",b),
+            JsonValue::Number(n) => n.clone(),
+            JsonValue::String(s) => s.to_json(),
+            JsonValue::Array(vals) => {
+                let str_vec: Vec<String> = vals.iter().map(|x| x.to_json()).collect();
+                format!("[{}]",str_vec.join(","))
+            },
+            JsonValue::Object(pairs) => {
+                let str_vec: Vec<String> = pairs.iter().map(|(k,v)| format!("{}:{}",k.to_json(),v.to_json())).collect();
+                format!("{{{}}}",str_vec.join(","))
+            },
+        }
+    }
+
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut buf = Vec::new();
+        match self {
+            JsonValue::Null => write_nil(&mut buf).map_err(ValueWriteError::InvalidMarkerWrite)?,
+            JsonValue::Bool(b) => write_bool(&mut buf, *b).map_err(ValueWriteError::InvalidMarkerWrite)?,
+            JsonValue::Number(n) => {
+                if let Ok(u) = n.parse::<u64>() {
+                    write_uint(&mut buf, u)?;
+                } else if let Ok(i) = n.parse::<i64>() {
+                    write_sint(&mut buf, i)?;
+                } else {
+                    write_f64(&mut buf, n.parse::<f64>().unwrap_or(f64::NAN))?;
+                }
+            },
+            JsonValue::String(s) => write_str(&mut buf, s)?,
+            JsonValue::Array(vals) => {
+                write_array_len(&mut buf, vals.len() as u32)?;
+                for x in vals.iter() {
+                    buf.append(&mut x.to_msg()?);
+                }
+            },
+            JsonValue::Object(pairs) => {
+                if let Some(tagged) = tagged_msg(pairs) {
+                    return tagged;
+                }
+                write_map_len(&mut buf, pairs.len() as u32)?;
+                for (k,v) in pairs.iter() {
+                    write_str(&mut buf, k)?;
+                    buf.append(&mut v.to_msg()?);
+                }
+            },
+        }
+        Ok(buf)
+    }
+}
+
+/// Reads any json document, and any MsgPack value made of nil, bools, numbers, strings, arrays and maps.
+/// MsgPack timestamps are read as RFC 3339 strings, the same as they are written to json.
+/// Other MsgPack bin and ext values are read as the tagged Objects `{"$bin":[..]}` and `{"$ext":{"type":..,"data":[..]}}`,
+/// which `to_msg` writes back as bin and ext.
+///
+/// This is what lets a Tree hold on to branches whose subtype it does not know.
+impl Deserializable for JsonValue {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Ok(JsonValue::parse(string)?)
+    }
+
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(value.clone())
+    }
+
+    fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        msg_value(bytes, bytes.len(), 0)
+    }
+}
+
+/// Deepest nesting of arrays and objects read into a JsonValue or FieldValue,
+/// so a malicious file can't overflow the stack
+pub(crate) const MAX_DEPTH: usize = 512;
+
+/// Returns true for the markers of arrays and maps
+pub(crate) fn nests(marker: Marker) -> bool {
+    matches!(marker, Marker::FixArray(_) | Marker::Array16 | Marker::Array32 | Marker::FixMap(_) | Marker::Map16 | Marker::Map32)
+}
+
+/// Reads one value of JsonValue::from_msg, `depth` arrays and maps down from the start of the `whole` input
+fn msg_value(mut bytes: &[u8], whole: usize, depth: usize) -> Result<(JsonValue,&[u8]), Box<dyn error::Error>> {
+    let marker = Marker::from_u8(*bytes.first().ok_or_else(|| CalcifyError::parse("msg value"))?);
+    if depth == MAX_DEPTH && nests(marker) {
+        return Err(Box::new(CalcifyError::parse("msg, nested too deeply").at(whole - bytes.len())));
+    }
+    let value = match marker {
+        Marker::Null => {
+            read_nil(&mut bytes)?;
+            JsonValue::Null
+        },
+        Marker::True | Marker::False => JsonValue::Bool(read_bool(&mut bytes)?),
+        Marker::FixPos(_) | Marker::U8 | Marker::U16 | Marker::U32 | Marker::U64 => {
+            let u: u64 = read_int(&mut bytes)?;
+            JsonValue::Number(u.to_string())
+        },
+        Marker::FixNeg(_) | Marker::I8 | Marker::I16 | Marker::I32 | Marker::I64 => {
+            let i: i64 = read_int(&mut bytes)?;
+            JsonValue::Number(i.to_string())
+        },
+        // Debug keeps the decimal point of integral floats, so they are written back as floats
+        Marker::F32 => JsonValue::Number(format!("{:?}",read_f32(&mut bytes)?)),
+        Marker::F64 => JsonValue::Number(format!("{:?}",read_f64(&mut bytes)?)),
+        Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+            let (s,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("msg string"))?;
+            bytes = rest;
+            JsonValue::String(String::from(s))
+        },
+        Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => {
+            let len = read_array_len(&mut bytes)?;
+            let mut vals = Vec::new();
+            for _ in 0..len {
+                let (v,rest) = msg_value(bytes, whole, depth + 1)?;
+                vals.push(v);
+                bytes = rest;
+            }
+            JsonValue::Array(vals)
+        },
+        Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => {
+            let len = read_map_len(&mut bytes)?;
+            let mut pairs = Vec::new();
+            for _ in 0..len {
+                let (k,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("msg map key"))?;
+                let (v,rest) = msg_value(rest, whole, depth + 1)?;
+                pairs.push((String::from(k),v));
+                bytes = rest;
+            }
+            JsonValue::Object(pairs)
+        },
+        Marker::Bin8 | Marker::Bin16 | Marker::Bin32
+            | Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 | Marker::FixExt16
+            | Marker::Ext8 | Marker::Ext16 | Marker::Ext32 => return msg_binary(bytes, whole),
+        _ => return Err(Box::new(CalcifyError::parse("msg value, unsupported marker"))),
+    };
+    Ok((value,bytes))
+}
+
+/// Key of the single entry Object a MsgPack bin is read as, `{"$bin":[1,2,3]}`
+const BIN_TAG: &str = "$bin";
+/// Key of the single entry Object a MsgPack ext is read as, `{"$ext":{"type":5,"data":[1,2,3]}}`
+const EXT_TAG: &str = "$ext";
+
+/// Returns bytes as an Array of Numbers
+fn byte_array(data: &[u8]) -> JsonValue {
+    JsonValue::Array(data.iter().map(|b| JsonValue::Number(b.to_string())).collect())
+}
+
+/// Returns the bytes of an Array of Numbers that all fit in a u8
+fn array_bytes(value: &JsonValue) -> Option<Vec<u8>> {
+    value.as_array().ok()?.iter().map(|x| x.as_u64().ok().and_then(|u| u8::try_from(u).ok())).collect()
+}
+
+/// Returns the MsgPack bin or ext an Object was read from, if it is one of the tagged Objects of JsonValue::from_msg
+fn tagged_msg(pairs: &[(String,JsonValue)]) -> Option<Result<Vec<u8>,ValueWriteError>> {
+    let mut buf = Vec::new();
+    match pairs {
+        [(k,v)] if k == BIN_TAG => {
+            let data = array_bytes(v)?;
+            Some(write_bin(&mut buf, &data).map(|_| buf))
+        },
+        [(k,v)] if k == EXT_TAG => {
+            let typeid = i8::try_from(v.get("type").ok()?.as_i64().ok()?).ok()?;
+            let data = array_bytes(v.get("data").ok()?)?;
+            if v.as_object().ok()?.len() != 2 {
+                return None;
+            }
+            Some(write_ext_meta(&mut buf, data.len() as u32, typeid).map(|_| {
+                buf.extend_from_slice(&data);
+                buf
+            }))
+        },
+        _ => None,
+    }
+}
+
+/// Reads a MsgPack bin or ext value of JsonValue::from_msg, kept out of msg_value so its stack frame stays small
+fn msg_binary(mut bytes: &[u8], whole: usize) -> Result<(JsonValue,&[u8]), Box<dyn error::Error>> {
+    let mut data = bytes;
+    let (tag,typeid,len) = match Marker::from_u8(bytes[0]) {
+        Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => (BIN_TAG,None,read_bin_len(&mut data)?),
+        _ => {
+            let meta = read_ext_meta(&mut data)?;
+            if meta.typeid == TIMESTAMP_EXT {
+                let (secs,nanos) = read_timestamp(&mut bytes)?;
+                return Ok((JsonValue::String(format_timestamp(secs,nanos)),bytes));
+            }
+            (EXT_TAG,Some(meta.typeid),meta.size)
+        },
+    };
+    if data.len() < len as usize {
+        return Err(Box::new(CalcifyError::parse("msg bin or ext").at(whole - bytes.len())));
+    }
+    let (data,rest) = data.split_at(len as usize);
+    let value = match typeid {
+        None => byte_array(data),
+        Some(typeid) => JsonValue::Object(vec![
+            (String::from("type"),JsonValue::Number(typeid.to_string())),
+            (String::from("data"),byte_array(data)),
+        ]),
+    };
+    Ok((JsonValue::Object(vec![(String::from(tag),value)]),rest))
+}
+
+/// Recursive descent json tokenizer behind JsonValue::parse
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(s: &'a str) -> JsonParser<'a> {
+        JsonParser {
+            bytes: s.as_bytes(),
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    /// Returns a ParseError at the current position
+    fn error(&self, what: &str) -> CalcifyError {
+        CalcifyError::parse(what).at(self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(),CalcifyError> {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error("json"))
+        }
+    }
+
+    fn literal(&mut self, word: &str) -> bool {
+        if self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            self.pos += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue,CalcifyError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') | Some(b'[') if self.depth == MAX_DEPTH => Err(self.error("json, nested too deeply")),
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => Ok(JsonValue::String(self.string()?)),
+            Some(b't') if self.literal("true") => Ok(JsonValue::Bool(true)),
+            Some(b'f') if self.literal("false") => Ok(JsonValue::Bool(false)),
+            Some(b'n') if self.literal("null") => Ok(JsonValue::Null),
+            Some(b'N') if self.literal("NaN") => Ok(JsonValue::Number(String::from("NaN"))),
+            Some(b'i') if self.literal("inf") => Ok(JsonValue::Number(String::from("inf"))),
+            Some(b'-') if self.literal("-inf") => Ok(JsonValue::Number(String::from("-inf"))),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(self.error("json value")),
+        }
+    }
+
+    /// Parses an object or array one level deeper
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<JsonValue,CalcifyError>) -> Result<JsonValue,CalcifyError> {
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<JsonValue,CalcifyError> {
+        self.expect(b'{')?;
+        let mut pairs = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(pairs));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            let value = self.value()?;
+            pairs.push((key,value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(pairs));
+                },
+                _ => return Err(self.error("json object")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue,CalcifyError> {
+        self.expect(b'[')?;
+        let mut vals = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(vals));
+        }
+        loop {
+            vals.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(vals));
+                },
+                _ => return Err(self.error("json array")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue,CalcifyError> {
+        let start = self.pos;
+        while let Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E') | Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        let text = str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| self.error("json number"))?;
+        if text.parse::<f64>().is_err() {
+            return Err(self.error("json number"));
+        }
+        Ok(JsonValue::Number(String::from(text)))
+    }
+
+    fn hex4(&mut self) -> Result<u32,CalcifyError> {
+        let digits = self.bytes.get(self.pos..self.pos+4).ok_or(self.error("json string"))?;
+        let text = str::from_utf8(digits).map_err(|_| self.error("json string"))?;
+        let code = u32::from_str_radix(text, 16).map_err(|_| self.error("json string"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String,CalcifyError> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("json string"));
+        }
+        self.pos += 1;
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("json string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    return String::from_utf8(out).map_err(|_| self.error("json string"));
+                },
+                Some(b'\\') => {
+                    self.pos += 1;
+                    let esc = self.peek().ok_or(self.error("json string"))?;
+                    self.pos += 1;
+                    let c: char = match esc {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{08}',
+                        b'f' => '\u{0c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let hi = self.hex4()?;
+                            let code = if (0xD800..0xDC00).contains(&hi) {
+                                if !self.literal("\\u") {
+                                    return Err(self.error("json string"));
+                                }
+                                let lo = self.hex4()?;
+                                if !(0xDC00..0xE000).contains(&lo) {
+                                    return Err(self.error("json string"));
+                                }
+                                0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00)
+                            } else {
+                                hi
+                            };
+                            char::from_u32(code).ok_or(self.error("json string"))?
+                        },
+                        _ => return Err(self.error("json string")),
+                    };
+                    let mut tmp = [0u8;4];
+                    out.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
+                },
+                Some(b) => {
+                    out.push(b);
+                    self.pos += 1;
+                },
+            }
+        }
+    }
+}
+
 impl Serializable for u64 {
     fn to_json(&self) -> String {
         format!("{}",self)
@@ -43,7 +609,10 @@ impl Serializable for u64 {
 
 impl Deserializable for u64 {
     fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
-        string.parse::<u64>().map_err(|e| e.into())
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(value.as_u64()?)
     }
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
         Ok((read_int(&mut bytes)?,bytes))
@@ -64,7 +633,10 @@ impl Serializable for f64 {
 
 impl Deserializable for f64 {
     fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
-        string.parse::<f64>().map_err(|e| e.into())
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(value.as_f64()?)
     }
     fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
         Ok((read_f64(&mut bytes)?,bytes))
@@ -72,14 +644,597 @@ impl Deserializable for f64 {
 }
 
 
-/// Wraps the String in quotes("").
+/// Wraps the str in quotes(""), escaping it so the output is always valid, pure ASCII json.
+///
+/// `"` and `\` are backslash escaped, control characters use their short escape or `\u00XX`,
+/// and everything outside ASCII is written as `\uXXXX`, with surrogate pairs above the BMP.
+impl Serializable for str {
+    fn to_json(&self) -> String {
+        let mut out = String::with_capacity(self.len()+2);
+        out.push('"');
+        for c in self.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                '\u{08}' => out.push_str("\\b"),
+                '\u{0c}' => out.push_str("\\f"),
+                ' '..='~' => out.push(c),
+                _ => {
+                    let mut units = [0u16;2];
+                    for unit in c.encode_utf16(&mut units).iter() {
+                        out.push_str(&format!("\\u{:04x}",unit));
+                    }
+                },
+            }
+        }
+        out.push('"');
+        out
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut buf = Vec::new();
+        write_str(&mut buf, self)?;
+        Ok(buf)
+    }
+}
+
+/// Wraps the String in quotes(""), see the str impl for escaping.
 impl Serializable for String {
     fn to_json(&self) -> String {
-        format!("\"{}\"",self)
+        self.as_str().to_json()
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        self.as_str().to_msg()
+    }
+}
+
+impl Deserializable for String {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(String::from(value.as_str()?))
+    }
+    fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        let (s,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("msg string"))?;
+        Ok((String::from(s),rest))
+    }
+}
+
+/// Signed integers are written to msg in the smallest int that fits, and read from any int in range.
+macro_rules! impl_sint {
+    ($($t:ty),*) => {$(
+        impl Serializable for $t {
+            fn to_json(&self) -> String {
+                format!("{}",self)
+            }
+            fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+                let mut buf = Vec::new();
+                write_sint(&mut buf, *self as i64)?;
+                Ok(buf)
+            }
+        }
+
+        impl Deserializable for $t {
+            fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+                Self::from_json_value(&JsonValue::parse(string)?)
+            }
+            fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+                Ok(<$t>::try_from(value.as_i64()?).map_err(|e| CalcifyError::parse(stringify!($t)).caused_by(e))?)
+            }
+            fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+                Ok((read_int(&mut bytes)?,bytes))
+            }
+        }
+    )*}
+}
+
+/// Unsigned integers are written to msg in the smallest uint that fits, and read from any int in range.
+macro_rules! impl_uint {
+    ($($t:ty),*) => {$(
+        impl Serializable for $t {
+            fn to_json(&self) -> String {
+                format!("{}",self)
+            }
+            fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+                let mut buf = Vec::new();
+                write_uint(&mut buf, *self as u64)?;
+                Ok(buf)
+            }
+        }
+
+        impl Deserializable for $t {
+            fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+                Self::from_json_value(&JsonValue::parse(string)?)
+            }
+            fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+                Ok(<$t>::try_from(value.as_u64()?).map_err(|e| CalcifyError::parse(stringify!($t)).caused_by(e))?)
+            }
+            fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+                Ok((read_int(&mut bytes)?,bytes))
+            }
+        }
+    )*}
+}
+
+impl_sint!(i64, i32);
+impl_uint!(u32, usize);
+
+impl Serializable for f32 {
+    fn to_json(&self) -> String {
+        format!("{}",self)
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut buf = Vec::new();
+        write_f32(&mut buf, *self)?;
+        Ok(buf)
+    }
+}
+
+impl Deserializable for f32 {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(value.as_f64()? as f32)
+    }
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        Ok((read_f32(&mut bytes)?,bytes))
+    }
+}
+
+impl Serializable for bool {
+    fn to_json(&self) -> String {
+        format!("{}",self)
     }
     fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
         let mut buf = Vec::new();
-        write_str(&mut buf, self.as_str())?;
+        write_bool(&mut buf, *self).map_err(ValueWriteError::InvalidMarkerWrite)?;
         Ok(buf)
     }
 }
+
+impl Deserializable for bool {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        Ok(value.as_bool()?)
+    }
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        Ok((read_bool(&mut bytes)?,bytes))
+    }
+}
+
+/// `None` is written as json `null` and msg nil, `Some` as the bare value.
+///
+/// # Note
+///
+/// * Because of that `Some(None)` reads back as `None`.
+impl<T: Serializable> Serializable for Option<T> {
+    fn to_json(&self) -> String {
+        match self {
+            Some(x) => x.to_json(),
+            None => String::from("null"),
+        }
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        match self {
+            Some(x) => x.to_msg(),
+            None => {
+                let mut buf = Vec::new();
+                write_nil(&mut buf).map_err(ValueWriteError::InvalidMarkerWrite)?;
+                Ok(buf)
+            },
+        }
+    }
+}
+
+impl<T: Deserializable> Deserializable for Option<T> {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        match value {
+            JsonValue::Null => Ok(None),
+            _ => Ok(Some(T::from_json_value(value)?)),
+        }
+    }
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        if let Some(&b) = bytes.first() {
+            if let Marker::Null = Marker::from_u8(b) {
+                read_nil(&mut bytes)?;
+                return Ok((None,bytes));
+            }
+        }
+        let (x,rest) = T::from_msg(bytes)?;
+        Ok((Some(x),rest))
+    }
+}
+
+/// Writes the elements of a sequence as a json array
+fn seq_to_json<'a, T: 'a + Serializable, I: Iterator<Item=&'a T>>(iter: I) -> String {
+    let str_vec: Vec<String> = iter.map(|x| x.to_json()).collect();
+    format!("[{}]",str_vec.join(","))
+}
+
+/// Writes the elements of a sequence as a msg array
+fn seq_to_msg<'a, T: 'a + Serializable, I: ExactSizeIterator<Item=&'a T>>(iter: I) -> Result<Vec<u8>,ValueWriteError> {
+    let mut buf = Vec::new();
+    write_array_len(&mut buf, iter.len() as u32)?;
+    for x in iter {
+        buf.append(&mut x.to_msg()?);
+    }
+    Ok(buf)
+}
+
+/// Written as an array, the same as a Collection.
+impl<T: Serializable> Serializable for Vec<T> {
+    fn to_json(&self) -> String {
+        seq_to_json(self.iter())
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        seq_to_msg(self.iter())
+    }
+}
+
+impl<T: Deserializable> Deserializable for Vec<T> {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        value.as_array()?.iter().map(T::from_json_value).collect()
+    }
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        let len = read_array_len(&mut bytes)?;
+        let mut out = Vec::new();
+        for _ in 0..len {
+            let (x,rest) = T::from_msg(bytes)?;
+            out.push(x);
+            bytes = rest;
+        }
+        Ok((out,bytes))
+    }
+}
+
+/// Written as an array. Reading an array of the wrong length is a `CalcifyError::LengthError`.
+impl<T: Serializable, const N: usize> Serializable for [T; N] {
+    fn to_json(&self) -> String {
+        seq_to_json(self.iter())
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        seq_to_msg(self.iter())
+    }
+}
+
+impl<T: Deserializable, const N: usize> Deserializable for [T; N] {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        let vec = Vec::<T>::from_json_value(value)?;
+        let found = vec.len();
+        Ok(vec.try_into().map_err(|_| CalcifyError::LengthError{expected: N, found})?)
+    }
+    fn from_msg(bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        let (vec,rest) = Vec::<T>::from_msg(bytes)?;
+        let found = vec.len();
+        Ok((vec.try_into().map_err(|_| CalcifyError::LengthError{expected: N, found})?,rest))
+    }
+}
+
+/// Tuples are written as arrays, reading an array of the wrong length is a `CalcifyError::LengthError`.
+macro_rules! impl_tuple {
+    ($len:expr => $($t:ident $i:tt),+) => {
+        impl<$($t: Serializable),+> Serializable for ($($t,)+) {
+            fn to_json(&self) -> String {
+                let str_vec: Vec<String> = vec![$(self.$i.to_json()),+];
+                format!("[{}]",str_vec.join(","))
+            }
+            fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+                let mut buf = Vec::new();
+                write_array_len(&mut buf, $len)?;
+                $(buf.append(&mut self.$i.to_msg()?);)+
+                Ok(buf)
+            }
+        }
+
+        impl<$($t: Deserializable),+> Deserializable for ($($t,)+) {
+            fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+                Self::from_json_value(&JsonValue::parse(string)?)
+            }
+            fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+                let vals = value.as_array()?;
+                if vals.len() != $len {
+                    return Err(Box::new(CalcifyError::LengthError{expected: $len, found: vals.len()}));
+                }
+                Ok(($($t::from_json_value(&vals[$i])?,)+))
+            }
+            fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+                let found = read_array_len(&mut bytes)? as usize;
+                if found != $len {
+                    return Err(Box::new(CalcifyError::LengthError{expected: $len, found}));
+                }
+                Ok((($({
+                    let (x,rest) = $t::from_msg(bytes)?;
+                    bytes = rest;
+                    x
+                },)+),bytes))
+            }
+        }
+    }
+}
+
+impl_tuple!(1 => A 0);
+impl_tuple!(2 => A 0, B 1);
+impl_tuple!(3 => A 0, B 1, C 2);
+impl_tuple!(4 => A 0, B 1, C 2, D 3);
+impl_tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+impl_tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Written as a json object and a msg map, with the keys sorted so the output doesn't depend on the hash order.
+impl<T: Serializable, S: BuildHasher> Serializable for HashMap<String,T,S> {
+    fn to_json(&self) -> String {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+        let str_vec: Vec<String> = keys.iter().map(|k| format!("{}:{}",k.to_json(),self[*k].to_json())).collect();
+        format!("{{{}}}",str_vec.join(","))
+    }
+    fn to_msg(&self) -> Result<Vec<u8>,ValueWriteError> {
+        let mut keys: Vec<&String> = self.keys().collect();
+        keys.sort();
+        let mut buf = Vec::new();
+        write_map_len(&mut buf, keys.len() as u32)?;
+        for k in keys {
+            write_str(&mut buf, k)?;
+            buf.append(&mut self[k].to_msg()?);
+        }
+        Ok(buf)
+    }
+}
+
+impl<T: Deserializable, S: BuildHasher + Default> Deserializable for HashMap<String,T,S> {
+    fn from_json(string: &str) -> Result<Self, Box<dyn error::Error>> {
+        Self::from_json_value(&JsonValue::parse(string)?)
+    }
+    fn from_json_value(value: &JsonValue) -> Result<Self, Box<dyn error::Error>> {
+        value.as_object()?.iter().map(|(k,v)| Ok((k.clone(),T::from_json_value(v)?))).collect()
+    }
+    fn from_msg(mut bytes: &[u8]) -> Result<(Self,&[u8]), Box<dyn error::Error>> {
+        let len = read_map_len(&mut bytes)?;
+        let mut out = HashMap::with_hasher(S::default());
+        for _ in 0..len {
+            let (k,rest) = read_str_from_slice(bytes).map_err(|_| CalcifyError::parse("msg map key"))?;
+            let (v,rest) = T::from_msg(rest)?;
+            out.insert(String::from(k),v);
+            bytes = rest;
+        }
+        Ok((out,bytes))
+    }
+}
+
+/// Numbers keep the integer or float type they were read with.
+#[cfg(feature = "serde")]
+impl serde::Serialize for JsonValue {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeSeq, SerializeMap};
+        match self {
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Bool(b) => serializer.serialize_bool(*b),
+            JsonValue::Number(n) => {
+                if let Ok(u) = n.parse::<u64>() {
+                    serializer.serialize_u64(u)
+                } else if let Ok(i) = n.parse::<i64>() {
+                    serializer.serialize_i64(i)
+                } else {
+                    serializer.serialize_f64(n.parse::<f64>().unwrap_or(f64::NAN))
+                }
+            },
+            JsonValue::String(st) => serializer.serialize_str(st),
+            JsonValue::Array(vals) => {
+                let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+                for x in vals.iter() {
+                    seq.serialize_element(x)?;
+                }
+                seq.end()
+            },
+            JsonValue::Object(pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for (k,v) in pairs.iter() {
+                    map.serialize_entry(k,v)?;
+                }
+                map.end()
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for JsonValue {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct JsonValueVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("a json or MsgPack value")
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        serde::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Bool(b))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(u.to_string()))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(i.to_string()))
+    }
+
+    fn visit_f64<E>(self, x: f64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(format!("{:?}",x)))
+    }
+
+    fn visit_str<E>(self, st: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(String::from(st)))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut vals = Vec::new();
+        while let Some(x) = seq.next_element()? {
+            vals.push(x);
+        }
+        Ok(JsonValue::Array(vals))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut pairs = Vec::new();
+        while let Some(pair) = map.next_entry()? {
+            pairs.push(pair);
+        }
+        Ok(JsonValue::Object(pairs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_whitespace() {
+        let value = JsonValue::parse(" {\n\t\"a\" : [ 1 , 2.5 ,-3e2 ] ,\r\n \"b\" : { } , \"c\":[ ] } ").unwrap();
+        let a = value.get("a").unwrap().as_array().unwrap();
+        assert_eq!(a.len(),3);
+        assert_eq!(a[1].as_f64().unwrap(),2.5);
+        assert_eq!(a[2].as_f64().unwrap(),-300.0);
+        assert_eq!(value.get("b").unwrap().as_object().unwrap().len(),0);
+        assert_eq!(value.get("c").unwrap().as_array().unwrap().len(),0);
+    }
+
+    #[test]
+    fn test_parse_key_order() {
+        let value = JsonValue::parse("{\"x2\":3.0,\"x0\":1.0,\"x1\":2.0}").unwrap();
+        assert_eq!(value.get("x0").unwrap().as_f64().unwrap(),1.0);
+        assert_eq!(value.get("x2").unwrap().as_f64().unwrap(),3.0);
+        assert!(value.get("x3").is_err());
+    }
+
+    #[test]
+    fn test_parse_escapes() {
+        let value = JsonValue::parse(r#"{"desc":"a \"quoted\" {brace}, \\ back\nslash \u00e9 \ud83d\ude00 ü"}"#).unwrap();
+        assert_eq!(value.get("desc").unwrap().as_str().unwrap(),"a \"quoted\" {brace}, \\ back\nslash é 😀 ü");
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(u64::from_json(" 18446744073709551615 ").unwrap(),u64::MAX);
+        assert_eq!(u64::from_json("10.0").unwrap(),10);
+        assert!(u64::from_json("-1").is_err());
+        assert!(f64::from_json("NaN").unwrap().is_nan());
+        assert_eq!(f64::from_json("-inf").unwrap(),f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_string_escape() {
+        let raw = String::from("say \"hi\"\\path\n\ttab\r\u{08}\u{0c}\u{01} é 😀");
+        let json = raw.to_json();
+        assert_eq!(json,"\"say \\\"hi\\\"\\\\path\\n\\ttab\\r\\b\\f\\u0001 \\u00e9 \\ud83d\\ude00\"");
+        assert!(json.is_ascii());
+        assert_eq!(JsonValue::parse(&json).unwrap().as_str().unwrap(),raw);
+        assert_eq!(JsonValue::String(raw.clone()).to_json(),json);
+    }
+
+    #[test]
+    fn test_value_msg() {
+        let value = JsonValue::parse("{\"a\":[1,-2,2.5,\"s\",true,null],\"b\":{}}").unwrap();
+        let pp = value.to_msg().unwrap();
+        let (oo,rest) = JsonValue::from_msg(&pp).unwrap();
+        assert_eq!(oo,value);
+        assert!(rest.is_empty());
+        assert!(JsonValue::from_msg(&pp[..pp.len()-1]).is_err());
+    }
+
+    fn roundtrip<T: Serializable + Deserializable + PartialEq + std::fmt::Debug>(x: T) {
+        assert_eq!(T::from_json(&x.to_json()).unwrap(),x);
+        let pp = x.to_msg().unwrap();
+        let (oo,rest) = T::from_msg(&pp).unwrap();
+        assert_eq!(oo,x);
+        assert!(rest.is_empty());
+    }
+
+    #[test]
+    fn test_std_types() {
+        roundtrip(-5i64);
+        roundtrip(i32::MIN);
+        roundtrip(u32::MAX);
+        roundtrip(12usize);
+        roundtrip(0.25f32);
+        roundtrip(true);
+        roundtrip(String::from("say \"hi\" é"));
+        roundtrip(Some(3.5));
+        roundtrip(None::<f64>);
+        roundtrip((1u64,-2i32,String::from("c")));
+        roundtrip([1.0,2.0,3.0]);
+        roundtrip(vec![vec![1u32],vec![],vec![2,3]]);
+        let mut map: HashMap<String,Vec<bool>> = HashMap::new();
+        map.insert(String::from("b"),vec![true]);
+        map.insert(String::from("a"),vec![false,true]);
+        assert_eq!(map.to_json(),"{\"a\":[false,true],\"b\":[true]}");
+        roundtrip(map);
+
+        assert_eq!(None::<f64>.to_json(),"null");
+        assert_eq!((1u64,2.5).to_json(),"[1,2.5]");
+        assert_eq!(i64::from_json("-10.0").unwrap(),-10);
+        assert_eq!(u32::from_msg(&7i64.to_msg().unwrap()).unwrap().0,7);
+        assert!(i32::from_json("3000000000").is_err());
+        assert!(u32::from_msg(&(-1i64).to_msg().unwrap()).is_err());
+        assert!(bool::from_json("1").is_err());
+        assert!(<[f64;2]>::from_json("[1.0,2.0,3.0]").is_err());
+        assert!(<(f64,f64)>::from_msg(&vec![1.0].to_msg().unwrap()).is_err());
+        let err = <HashMap<String,f64>>::from_msg(&[0xdf,0xff,0xff,0xff,0xff]).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::ParseError{..})));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(JsonValue::parse("{\"a\":1,}").is_err());
+        assert!(JsonValue::parse("[1,2").is_err());
+        assert!(JsonValue::parse("{\"a\" 1}").is_err());
+        assert!(JsonValue::parse("\"open").is_err());
+        assert!(JsonValue::parse("[1] 2").is_err());
+    }
+
+    #[test]
+    fn test_nesting_depth() -> Result<(),Box<dyn error::Error>> {
+        let deepest = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        let value = JsonValue::parse(&deepest)?;
+        assert_eq!(JsonValue::from_msg(&value.to_msg()?)?.0,value);
+        assert!(matches!(JsonValue::parse(&"[".repeat(200_000)),Err(CalcifyError::ParseError{position: Some(MAX_DEPTH),..})));
+        assert!(matches!(JsonValue::parse(&"{\"a\":".repeat(200_000)),Err(CalcifyError::ParseError{position: Some(_),..})));
+        let err = JsonValue::from_msg(&[0x91; 200_000]).err().unwrap();
+        assert!(matches!(err.downcast_ref::<CalcifyError>(),Some(CalcifyError::ParseError{position: Some(MAX_DEPTH),..})));
+        Ok(())
+    }
+}